@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE pull_request ADD COLUMN auto_build_id INT NULL REFERENCES build(id);
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE pull_request ADD COLUMN rollup_pr_id INT NULL REFERENCES pull_request(id);
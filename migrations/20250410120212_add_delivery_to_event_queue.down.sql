@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS event_queue_delivery_id_idx;
+ALTER TABLE event_queue DROP COLUMN repository;
+ALTER TABLE event_queue DROP COLUMN delivery_id;
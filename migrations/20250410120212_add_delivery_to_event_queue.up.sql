@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE event_queue ADD COLUMN delivery_id TEXT NULL;
+ALTER TABLE event_queue ADD COLUMN repository TEXT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS event_queue_delivery_id_idx ON event_queue (delivery_id);
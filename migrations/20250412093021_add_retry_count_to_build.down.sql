@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE build DROP COLUMN retried_at;
+ALTER TABLE build DROP COLUMN retry_count;
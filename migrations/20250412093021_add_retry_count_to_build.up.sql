@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE build ADD COLUMN retry_count INT NOT NULL DEFAULT 0;
+ALTER TABLE build ADD COLUMN retried_at TIMESTAMPTZ NULL;
@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS try_request (
+  id SERIAL PRIMARY KEY,
+  pull_request_id INT NOT NULL REFERENCES pull_request(id),
+  requester TEXT NOT NULL,
+  parent TEXT NULL,
+  jobs TEXT[] NOT NULL DEFAULT '{}',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS try_request_pull_request_id_idx ON try_request (pull_request_id);
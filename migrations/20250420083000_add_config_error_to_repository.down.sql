@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE repository DROP COLUMN config_error_revision;
+ALTER TABLE repository DROP COLUMN config_error;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE repository ADD COLUMN config_error TEXT NULL;
+ALTER TABLE repository ADD COLUMN config_error_revision TEXT NULL;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS audit_entry (
+  id SERIAL PRIMARY KEY,
+  repository TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  branch TEXT NOT NULL,
+  sha TEXT NOT NULL,
+  pr_number BIGINT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_entry_repository_idx ON audit_entry (repository);
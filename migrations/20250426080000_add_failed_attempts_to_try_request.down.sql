@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE try_request DROP COLUMN failed_attempts;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE try_request ADD COLUMN failed_attempts INT NOT NULL DEFAULT 0;
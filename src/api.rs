@@ -0,0 +1,644 @@
+//! JSON API that exposes the state of the bot to external tools.
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use axum::extract::{FromRequestParts, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use crate::bors::{PullRequestStatus, RollupMode};
+use crate::database::{
+    BuildModel, PullRequestFilter, PullRequestModel, QueuedEventModel, QueuedEventStatus,
+    TreeState, WORKFLOW_STATS_DAYS, WorkflowModel, WorkflowStats,
+};
+use crate::github::server::ServerStateRef;
+use crate::github::{GithubRepoName, PullRequestNumber};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 100;
+const MAX_WORKFLOW_STATS_DAYS: u32 = 3650;
+
+pub fn api_routes() -> Router<ServerStateRef> {
+    Router::new()
+        .route("/repos", get(list_repositories))
+        .route("/repos/{owner}/{name}/prs", get(list_pull_requests))
+        .route("/repos/{owner}/{name}/prs/{number}", get(get_pull_request))
+        .route("/repos/{owner}/{name}/builds/{id}", get(get_build))
+        .route(
+            "/repos/{owner}/{name}/workflows/stats",
+            get(get_workflow_stats),
+        )
+        .route("/admin/deliveries/{delivery_id}", get(get_delivery))
+        .route(
+            "/admin/deliveries/{delivery_id}/replay",
+            post(replay_delivery),
+        )
+}
+
+enum ApiError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError::Internal(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Internal(error) => {
+                tracing::error!("API request failed: {error:?}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal server error".to_string(),
+                )
+            }
+        };
+        (status, Json(ErrorResponse { error: message })).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize)]
+struct Page<T> {
+    page: u32,
+    per_page: u32,
+    items: Vec<T>,
+}
+
+#[derive(Serialize)]
+struct RepositoryResponse {
+    name: String,
+    tree_state: TreeStateResponse,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum TreeStateResponse {
+    Open,
+    Closed { priority: u32, source: String },
+}
+
+impl From<TreeState> for TreeStateResponse {
+    fn from(state: TreeState) -> Self {
+        match state {
+            TreeState::Open => TreeStateResponse::Open,
+            TreeState::Closed { priority, source } => {
+                TreeStateResponse::Closed { priority, source }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PullRequestResponse {
+    repository: String,
+    number: u64,
+    status: String,
+    base_branch: String,
+    mergeable_state: String,
+    approved_by: Option<String>,
+    approved_sha: Option<String>,
+    delegated_permission: Option<String>,
+    priority: Option<i32>,
+    rollup: Option<String>,
+    try_build: Option<BuildResponse>,
+    auto_build: Option<BuildResponse>,
+    created_at: String,
+}
+
+impl From<PullRequestModel> for PullRequestResponse {
+    fn from(pr: PullRequestModel) -> Self {
+        Self {
+            repository: pr.repository.to_string(),
+            number: pr.number.0,
+            status: pr.pr_status.to_string(),
+            base_branch: pr.base_branch.clone(),
+            mergeable_state: pr.mergeable_state.to_string(),
+            approved_by: pr.approver().map(|approver| approver.to_string()),
+            approved_sha: pr.approved_sha().map(|sha| sha.to_string()),
+            delegated_permission: pr.delegated_permission.map(|perm| perm.to_string()),
+            priority: pr.priority,
+            rollup: pr.rollup.map(|rollup| rollup.to_string()),
+            try_build: pr.try_build.map(BuildResponse::from),
+            auto_build: pr.auto_build.map(BuildResponse::from),
+            created_at: pr.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BuildResponse {
+    id: i32,
+    branch: String,
+    commit_sha: String,
+    parent: String,
+    status: String,
+    retry_count: i32,
+    created_at: String,
+}
+
+impl From<BuildModel> for BuildResponse {
+    fn from(build: BuildModel) -> Self {
+        Self {
+            id: build.id,
+            branch: build.branch,
+            commit_sha: build.commit_sha,
+            parent: build.parent,
+            status: build.status.to_string(),
+            retry_count: build.retry_count,
+            created_at: build.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BuildDetailResponse {
+    #[serde(flatten)]
+    build: BuildResponse,
+    workflows: Vec<WorkflowResponse>,
+}
+
+#[derive(Serialize)]
+struct WorkflowResponse {
+    id: i32,
+    name: String,
+    url: String,
+    run_id: u64,
+    workflow_type: String,
+    status: String,
+    created_at: String,
+}
+
+impl From<WorkflowModel> for WorkflowResponse {
+    fn from(workflow: WorkflowModel) -> Self {
+        Self {
+            id: workflow.id,
+            name: workflow.name,
+            url: workflow.url,
+            run_id: workflow.run_id.0,
+            workflow_type: workflow.workflow_type.to_string(),
+            status: workflow.status.to_string(),
+            created_at: workflow.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WorkflowStatsResponse {
+    name: String,
+    attempts: i64,
+    failed_attempts: i64,
+    passed_on_retry: i64,
+    failure_rate: f64,
+}
+
+impl From<WorkflowStats> for WorkflowStatsResponse {
+    fn from(stats: WorkflowStats) -> Self {
+        Self {
+            failure_rate: stats.failure_rate(),
+            name: stats.name,
+            attempts: stats.attempts,
+            failed_attempts: stats.failed_attempts,
+            passed_on_retry: stats.passed_on_retry,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DeliveryResponse {
+    id: i32,
+    delivery_id: Option<String>,
+    event_type: String,
+    repository: Option<String>,
+    status: String,
+    attempts: i32,
+    last_error: Option<String>,
+    created_at: String,
+}
+
+impl From<QueuedEventModel> for DeliveryResponse {
+    fn from(event: QueuedEventModel) -> Self {
+        Self {
+            id: event.id,
+            delivery_id: event.delivery_id,
+            event_type: event.event_type,
+            repository: event.repository,
+            status: event.status.to_string(),
+            attempts: event.attempts,
+            last_error: event.last_error,
+            created_at: event.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReplayResponse {
+    event_id: i32,
+}
+
+#[derive(Deserialize)]
+struct WorkflowStatsQuery {
+    days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestQuery {
+    status: Option<String>,
+    approved: Option<bool>,
+    rollup: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+async fn list_repositories(
+    State(state): State<ServerStateRef>,
+) -> ApiResult<Vec<RepositoryResponse>> {
+    let repos = state.db().get_repositories().await?;
+    Ok(Json(
+        repos
+            .into_iter()
+            .map(|(name, tree_state)| RepositoryResponse {
+                name: name.to_string(),
+                tree_state: tree_state.into(),
+            })
+            .collect(),
+    ))
+}
+
+async fn list_pull_requests(
+    Path((owner, name)): Path<(String, String)>,
+    Query(query): Query<PullRequestQuery>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<Page<PullRequestResponse>> {
+    let repo = GithubRepoName::new(&owner, &name);
+    let filter = PullRequestFilter {
+        status: parse_param::<PullRequestStatus>("status", query.status)?,
+        approved: query.approved,
+        rollup: parse_param::<RollupMode>("rollup", query.rollup)?,
+    };
+
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return Err(ApiError::BadRequest("`page` starts at 1".to_string()));
+    }
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = (page as i64 - 1) * per_page as i64;
+
+    let prs = state
+        .db()
+        .get_pull_requests(&repo, &filter, per_page as i64, offset)
+        .await?;
+    Ok(Json(Page {
+        page,
+        per_page,
+        items: prs.into_iter().map(PullRequestResponse::from).collect(),
+    }))
+}
+
+async fn get_pull_request(
+    Path((owner, name, number)): Path<(String, String, u64)>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<PullRequestResponse> {
+    let repo = GithubRepoName::new(&owner, &name);
+    let pr = state
+        .db()
+        .get_pull_request(&repo, PullRequestNumber(number))
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Pull request {repo}#{number} not found")))?;
+    Ok(Json(pr.into()))
+}
+
+async fn get_build(
+    Path((owner, name, id)): Path<(String, String, i32)>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<BuildDetailResponse> {
+    let repo = GithubRepoName::new(&owner, &name);
+    let build = state
+        .db()
+        .get_build(&repo, id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Build {id} of {repo} not found")))?;
+    let workflows = state.db().get_workflows_for_build(&build).await?;
+    Ok(Json(BuildDetailResponse {
+        build: build.into(),
+        workflows: workflows.into_iter().map(WorkflowResponse::from).collect(),
+    }))
+}
+
+async fn get_workflow_stats(
+    Path((owner, name)): Path<(String, String)>,
+    Query(query): Query<WorkflowStatsQuery>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<Vec<WorkflowStatsResponse>> {
+    let repo = GithubRepoName::new(&owner, &name);
+    let days = query.days.unwrap_or(WORKFLOW_STATS_DAYS);
+    if !(1..=MAX_WORKFLOW_STATS_DAYS).contains(&days) {
+        return Err(ApiError::BadRequest(format!(
+            "`days` must be between 1 and {MAX_WORKFLOW_STATS_DAYS}"
+        )));
+    }
+    let stats = state.db().get_workflow_stats(&repo, days).await?;
+    Ok(Json(
+        stats.into_iter().map(WorkflowStatsResponse::from).collect(),
+    ))
+}
+
+/// Authenticates requests to the admin API using the `Authorization: Bearer <token>` header.
+struct AdminAuth;
+
+impl FromRequestParts<ServerStateRef> for AdminAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ServerStateRef,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(token) = state.admin_token() else {
+            return Err(ApiError::NotFound("Admin API is disabled".to_string()));
+        };
+        let authorized = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|value| constant_time_eq(value.as_bytes(), token.as_bytes()));
+        if authorized {
+            Ok(AdminAuth)
+        } else {
+            Err(ApiError::Unauthorized)
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn get_delivery(
+    _: AdminAuth,
+    Path(delivery_id): Path<String>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<DeliveryResponse> {
+    let event = state
+        .db()
+        .get_event_by_delivery_id(&delivery_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Delivery {delivery_id} not found")))?;
+    Ok(Json(event.into()))
+}
+
+/// Stores a copy of a previously received webhook in the event queue, so that it is handled
+/// again.
+async fn replay_delivery(
+    _: AdminAuth,
+    Path(delivery_id): Path<String>,
+    State(state): State<ServerStateRef>,
+) -> ApiResult<ReplayResponse> {
+    let event = state
+        .db()
+        .get_event_by_delivery_id(&delivery_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Delivery {delivery_id} not found")))?;
+
+    // The replayed event does not have a delivery ID, so that it is not considered a duplicate.
+    let event_id = state
+        .db()
+        .enqueue_event(
+            None,
+            &event.event_type,
+            event.repository.as_deref(),
+            &event.payload,
+            QueuedEventStatus::Pending,
+        )
+        .await?
+        .ok_or_else(|| anyhow!("Replayed event of delivery {delivery_id} was not stored"))?;
+    tracing::info!("Replaying delivery {delivery_id} as event {event_id}");
+    state.notify_event_queue();
+
+    Ok(Json(ReplayResponse { event_id }))
+}
+
+fn parse_param<T: FromStr<Err = String>>(
+    name: &str,
+    value: Option<String>,
+) -> Result<Option<T>, ApiError> {
+    value
+        .map(|value| {
+            value
+                .parse::<T>()
+                .map_err(|error| ApiError::BadRequest(format!("Invalid `{name}`: {error}")))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::mocks::run_test;
+
+    #[sqlx::test]
+    async fn list_repositories(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors treeclosed=5").await?;
+            tester.expect_comments(1).await;
+
+            let response = tester.get_page("/api/repos").await?;
+            insta::assert_snapshot!(response, @r#"[{"name":"rust-lang/borstest","tree_state":{"state":"closed","priority":5,"source":"https://github.com/rust-lang/borstest/pull/1#issuecomment-1"}}]"#);
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn list_pull_requests_filter_approved(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+
+            let response: serde_json::Value = serde_json::from_str(
+                &tester
+                    .get_page("/api/repos/rust-lang/borstest/prs?approved=true&status=open")
+                    .await?,
+            )?;
+            assert_eq!(response["items"].as_array().unwrap().len(), 1);
+            assert_eq!(response["items"][0]["number"], 1);
+            assert_eq!(response["items"][0]["approved_by"], "default-user");
+
+            let response: serde_json::Value = serde_json::from_str(
+                &tester
+                    .get_page("/api/repos/rust-lang/borstest/prs?approved=false")
+                    .await?,
+            )?;
+            assert!(response["items"].as_array().unwrap().is_empty());
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn list_pull_requests_invalid_filter(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            assert!(
+                tester
+                    .get_page("/api/repos/rust-lang/borstest/prs?rollup=sometimes")
+                    .await
+                    .is_err()
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn get_try_build(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+
+            let pr: serde_json::Value = serde_json::from_str(
+                &tester
+                    .get_page("/api/repos/rust-lang/borstest/prs/1")
+                    .await?,
+            )?;
+            let build_id = pr["try_build"]["id"].as_i64().unwrap();
+            assert_eq!(pr["try_build"]["status"], "pending");
+
+            let build: serde_json::Value = serde_json::from_str(
+                &tester
+                    .get_page(&format!("/api/repos/rust-lang/borstest/builds/{build_id}"))
+                    .await?,
+            )?;
+            assert_eq!(build["branch"], "automation/bors/try");
+            assert!(build["workflows"].as_array().unwrap().is_empty());
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn get_workflow_stats(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+            tester.workflow_failure(tester.try_branch()).await?;
+            tester.expect_comments(1).await;
+
+            let response = tester
+                .get_page("/api/repos/rust-lang/borstest/workflows/stats")
+                .await?;
+            insta::assert_snapshot!(response, @r#"[{"name":"Workflow1","attempts":1,"failed_attempts":1,"passed_on_retry":0,"failure_rate":1.0}]"#);
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn get_workflow_stats_invalid_days(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            for days in [0, 3651, u32::MAX] {
+                assert!(
+                    tester
+                        .get_page(&format!(
+                            "/api/repos/rust-lang/borstest/workflows/stats?days={days}"
+                        ))
+                        .await
+                        .is_err()
+                );
+            }
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn get_delivery(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+
+            let delivery_id = tester.last_delivery_id().to_string();
+            let delivery: serde_json::Value = serde_json::from_str(
+                &tester
+                    .admin_request("GET", &format!("/api/admin/deliveries/{delivery_id}"))
+                    .await?,
+            )?;
+            assert_eq!(delivery["delivery_id"], delivery_id);
+            assert_eq!(delivery["event_type"], "issue_comment");
+            assert_eq!(delivery["repository"], "rust-lang/borstest");
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn replay_delivery(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+
+            let delivery_id = tester.last_delivery_id().to_string();
+            tester
+                .admin_request(
+                    "POST",
+                    &format!("/api/admin/deliveries/{delivery_id}/replay"),
+                )
+                .await?;
+            assert_eq!(tester.get_comment().await?, "Pong 🏓!");
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn admin_api_requires_token(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+
+            let delivery_id = tester.last_delivery_id().to_string();
+            assert!(
+                tester
+                    .get_page(&format!("/api/admin/deliveries/{delivery_id}"))
+                    .await
+                    .is_err()
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn get_missing_pull_request(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            assert!(
+                tester
+                    .get_page("/api/repos/rust-lang/borstest/prs/100")
+                    .await
+                    .is_err()
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+}
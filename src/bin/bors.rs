@@ -39,6 +39,11 @@ struct Opts {
     /// Prefix used for bot commands in PR comments.
     #[arg(long, env = "CMD_PREFIX", default_value = "@bors")]
     cmd_prefix: String,
+
+    /// Token used to authenticate requests to the admin API.
+    /// The admin API is disabled if it is not set.
+    #[arg(long, env = "ADMIN_TOKEN")]
+    admin_token: Option<String>,
 }
 
 /// Starts a server that receives GitHub webhooks and generates events into a queue
@@ -88,7 +93,7 @@ fn try_main(opts: Opts) -> anyhow::Result<()> {
             "https://api.github.com".to_string(),
             opts.private_key.into(),
         )?;
-        let repos = load_repositories(&client, &team_api).await?;
+        let repos = load_repositories(&client, &team_api, &db).await?;
         Ok::<_, anyhow::Error>((client, repos))
     })?;
 
@@ -108,10 +113,10 @@ fn try_main(opts: Opts) -> anyhow::Result<()> {
         repos.insert(name, Arc::new(repo));
     }
 
-    let ctx = BorsContext::new(CommandParser::new(opts.cmd_prefix), Arc::new(db), repos);
-    let (repository_tx, global_tx, bors_process) = create_bors_process(ctx, client, team_api);
+    let db = Arc::new(db);
+    let ctx = BorsContext::new(CommandParser::new(opts.cmd_prefix), db.clone(), repos);
+    let (queue_tx, refresh_tx, bors_process) = create_bors_process(ctx, client, team_api);
 
-    let refresh_tx = global_tx.clone();
     let refresh_process = async move {
         loop {
             tokio::time::sleep(PERIODIC_REFRESH).await;
@@ -119,11 +124,10 @@ fn try_main(opts: Opts) -> anyhow::Result<()> {
         }
     };
 
-    let state = ServerState::new(
-        repository_tx,
-        global_tx,
-        WebhookSecret::new(opts.webhook_secret),
-    );
+    let mut state = ServerState::new(queue_tx, WebhookSecret::new(opts.webhook_secret), db);
+    if let Some(admin_token) = opts.admin_token {
+        state = state.with_admin_token(admin_token);
+    }
     let server_process = webhook_server(state);
 
     let fut = async move {
@@ -3,7 +3,7 @@ use std::fmt;
 use std::str::FromStr;
 
 use crate::{database::DelegatedPermission, github::CommitSha};
-pub use parser::{CommandParseError, CommandParser};
+pub use parser::{CommandParseError, CommandParser, parse_bare_command};
 
 /// Priority of a commit.
 pub type Priority = u32;
@@ -89,10 +89,16 @@ pub enum BorsCommand {
     },
     /// Cancel a try build.
     TryCancel,
+    /// Re-run the failed workflows of the latest try build.
+    Retry,
     /// Set the priority of a PR.
     SetPriority(Priority),
     /// Get information about the current PR.
     Info,
+    /// Print statistics of workflows that fail often.
+    Flaky,
+    /// Check that the configuration file of the PR can be loaded.
+    ValidateConfig,
     /// Delegate approval authority to the pull request author.
     SetDelegate(DelegatedPermission),
     /// Revoke any previously granted delegation.
@@ -103,4 +109,31 @@ pub enum BorsCommand {
     OpenTree,
     /// Set the tree closed with a priority level.
     TreeClosed(Priority),
+    /// Create a rollup PR from approved PRs that are marked for rollup.
+    CreateRollup,
+}
+
+impl BorsCommand {
+    /// Short name of the command, used e.g. for labelling metrics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BorsCommand::Approve { .. } => "approve",
+            BorsCommand::Unapprove => "unapprove",
+            BorsCommand::Help => "help",
+            BorsCommand::Ping => "ping",
+            BorsCommand::Try { .. } => "try",
+            BorsCommand::TryCancel => "try_cancel",
+            BorsCommand::Retry => "retry",
+            BorsCommand::SetPriority(_) => "set_priority",
+            BorsCommand::Info => "info",
+            BorsCommand::Flaky => "flaky",
+            BorsCommand::ValidateConfig => "validate_config",
+            BorsCommand::SetDelegate(_) => "delegate",
+            BorsCommand::Undelegate => "undelegate",
+            BorsCommand::SetRollupMode(_) => "set_rollup",
+            BorsCommand::OpenTree => "tree_open",
+            BorsCommand::TreeClosed(_) => "tree_closed",
+            BorsCommand::CreateRollup => "create_rollup",
+        }
+    }
 }
@@ -55,19 +55,28 @@ impl CommandParser {
     }
 }
 
+/// Parses a single command written without the bot prefix, e.g. `r+ p=1`.
+pub fn parse_bare_command(input: &str) -> Result<BorsCommand, CommandParseError> {
+    parse_command(input).unwrap_or(Err(CommandParseError::MissingCommand))
+}
+
 type ParseResult<'a, T = BorsCommand> = Option<Result<T, CommandParseError<'a>>>;
 
 // The order of the parsers in the vector is important
 const PARSERS: &[for<'b> fn(&CommandPart<'b>, &[CommandPart<'b>]) -> ParseResult<'b>] = &[
     parser_approval,
     parser_unapprove,
+    parser_rollup_create,
     parser_rollup,
     parser_priority,
     parser_try_cancel,
     parser_try,
+    parser_retry,
     parser_delegate,
     parser_undelegate,
     parser_info,
+    parser_flaky,
+    parser_validate_config,
     parser_help,
     parser_ping,
     parser_tree_ops,
@@ -246,6 +255,36 @@ fn parser_try_cancel<'a>(command: &CommandPart<'a>, parts: &[CommandPart<'a>]) -
     }
 }
 
+/// Parses "@bors flaky".
+fn parser_flaky<'a>(command: &CommandPart<'a>, _parts: &[CommandPart<'a>]) -> ParseResult<'a> {
+    if let CommandPart::Bare("flaky") = command {
+        Some(Ok(BorsCommand::Flaky))
+    } else {
+        None
+    }
+}
+
+/// Parses "@bors validate-config".
+fn parser_validate_config<'a>(
+    command: &CommandPart<'a>,
+    _parts: &[CommandPart<'a>],
+) -> ParseResult<'a> {
+    if let CommandPart::Bare("validate-config") = command {
+        Some(Ok(BorsCommand::ValidateConfig))
+    } else {
+        None
+    }
+}
+
+/// Parses "@bors retry".
+fn parser_retry<'a>(command: &CommandPart<'a>, _parts: &[CommandPart<'a>]) -> ParseResult<'a> {
+    if let CommandPart::Bare("retry") = command {
+        Some(Ok(BorsCommand::Retry))
+    } else {
+        None
+    }
+}
+
 /// Parses `@bors delegate=<try|review>` or `@bors delegate+`.
 fn parser_delegate<'a>(command: &CommandPart<'a>, _parts: &[CommandPart<'a>]) -> ParseResult<'a> {
     match command {
@@ -324,6 +363,19 @@ fn parser_rollup<'a>(command: &CommandPart<'a>, _parts: &[CommandPart<'a>]) -> P
     parse_rollup(std::slice::from_ref(command)).map(|res| res.map(BorsCommand::SetRollupMode))
 }
 
+/// Parses "@bors rollup create"
+fn parser_rollup_create<'a>(
+    command: &CommandPart<'a>,
+    parts: &[CommandPart<'a>],
+) -> ParseResult<'a> {
+    match (command, parts) {
+        (CommandPart::Bare("rollup"), [CommandPart::Bare("create"), ..]) => {
+            Some(Ok(BorsCommand::CreateRollup))
+        }
+        _ => None,
+    }
+}
+
 /// Parses "@bors info"
 fn parser_info<'a>(command: &CommandPart<'a>, _parts: &[CommandPart<'a>]) -> ParseResult<'a> {
     if *command == CommandPart::Bare("info") {
@@ -752,6 +804,13 @@ mod tests {
         assert_eq!(cmds[0], Ok(BorsCommand::SetRollupMode(RollupMode::Always)));
     }
 
+    #[test]
+    fn parse_rollup_create() {
+        let cmds = parse_commands("@bors rollup create");
+        assert_eq!(cmds.len(), 1);
+        assert_eq!(cmds[0], Ok(BorsCommand::CreateRollup));
+    }
+
     #[test]
     fn parse_rollup_bare_maybe() {
         let cmds = parse_commands("@bors rollup-");
@@ -1030,6 +1089,27 @@ line two
         assert!(matches!(cmds[0], Ok(BorsCommand::TryCancel)));
     }
 
+    #[test]
+    fn parse_flaky() {
+        let cmds = parse_commands("@bors flaky");
+        assert_eq!(cmds.len(), 1);
+        assert!(matches!(cmds[0], Ok(BorsCommand::Flaky)));
+    }
+
+    #[test]
+    fn parse_validate_config() {
+        let cmds = parse_commands("@bors validate-config");
+        assert_eq!(cmds.len(), 1);
+        assert!(matches!(cmds[0], Ok(BorsCommand::ValidateConfig)));
+    }
+
+    #[test]
+    fn parse_retry() {
+        let cmds = parse_commands("@bors retry");
+        assert_eq!(cmds.len(), 1);
+        assert!(matches!(cmds[0], Ok(BorsCommand::Retry)));
+    }
+
     #[test]
     fn parse_delegate_author() {
         let cmds = parse_commands("@bors delegate+");
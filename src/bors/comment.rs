@@ -1,14 +1,22 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use serde::Serialize;
 
 use crate::{
-    database::{WorkflowModel, WorkflowStatus},
-    github::CommitSha,
+    config::{CONFIG_FILE_PATH, TemplateKind},
+    database::{WORKFLOW_STATS_DAYS, WorkflowModel, WorkflowStats, WorkflowStatus},
+    github::{CommitSha, PullRequestNumber},
+    utils::{template::Template, text::format_duration},
 };
 
 /// A comment that can be posted to a pull request.
 pub struct Comment {
     text: String,
     metadata: Option<CommentMetadata>,
+    /// Kind of the comment and the values of its variables, used when the repository overrides
+    /// the text of the comment with a template.
+    template: Option<(TemplateKind, HashMap<&'static str, String>)>,
 }
 
 #[derive(Serialize)]
@@ -22,7 +30,34 @@ impl Comment {
         Self {
             text,
             metadata: None,
+            template: None,
+        }
+    }
+
+    /// Allows the text of the comment to be overridden by a template of the given `kind`,
+    /// which can reference the given `variables`.
+    pub fn with_template<const N: usize>(
+        mut self,
+        kind: TemplateKind,
+        variables: [(&'static str, String); N],
+    ) -> Self {
+        self.template = Some((kind, HashMap::from(variables)));
+        self
+    }
+
+    /// Replaces the text of the comment if `templates` contain a template for its kind.
+    pub fn apply_templates(
+        mut self,
+        templates: &HashMap<TemplateKind, Template>,
+        pr: PullRequestNumber,
+    ) -> Self {
+        if let Some((kind, mut variables)) = self.template.take() {
+            if let Some(template) = templates.get(&kind) {
+                variables.insert("pr_number", pr.to_string());
+                self.text = template.render(&variables);
+            }
         }
+        self
     }
 
     pub fn render(&self) -> String {
@@ -37,9 +72,13 @@ impl Comment {
     }
 }
 
-pub fn try_build_succeeded_comment(workflows: &[WorkflowModel], commit_sha: CommitSha) -> Comment {
+pub fn try_build_succeeded_comment(
+    workflows: &[WorkflowModel],
+    commit_sha: CommitSha,
+    duration: Duration,
+) -> Comment {
     let workflows_status = list_workflows_status(workflows);
-    Comment {
+    let comment = Comment {
         text: format!(
             r#":sunny: Try build successful
 {}
@@ -49,46 +88,408 @@ Build commit: {} (`{}`)"#,
         metadata: Some(CommentMetadata::TryBuildCompleted {
             merge_sha: commit_sha.to_string(),
         }),
+        template: None,
+    };
+    comment.with_template(
+        TemplateKind::TryBuildSucceeded,
+        [
+            ("merge_sha", commit_sha.to_string()),
+            ("workflows", workflows_status),
+            ("workflow_urls", list_workflow_urls(workflows)),
+            ("duration", format_duration(duration)),
+        ],
+    )
+}
+
+pub fn build_timed_out_comment(missing_checks: &[String], duration: Duration) -> Comment {
+    let text = if missing_checks.is_empty() {
+        ":boom: Test timed out".to_string()
+    } else {
+        format!(
+            ":boom: Test timed out, required checks are missing: {}",
+            format_check_list(missing_checks)
+        )
+    };
+    Comment::new(text).with_template(
+        TemplateKind::BuildTimedOut,
+        [
+            ("missing_checks", format_check_list(missing_checks)),
+            ("duration", format_duration(duration)),
+        ],
+    )
+}
+
+fn format_check_list(checks: &[String]) -> String {
+    checks
+        .iter()
+        .map(|check| format!("`{check}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn auto_build_requeued_comment() -> Comment {
+    Comment::new(
+        ":hourglass_flowing_sand: Another auto build is running, so the failed workflows cannot be re-run now. The pull request has been put back into the merge queue and will be tested again.".to_string(),
+    )
+    .with_template(TemplateKind::AutoBuildRequeued, [])
+}
+
+pub fn auto_build_started_comment(head_sha: &CommitSha, merge_sha: &CommitSha) -> Comment {
+    Comment::new(format!(
+        ":hourglass: Testing commit {head_sha} with merge {merge_sha}…"
+    ))
+    .with_template(
+        TemplateKind::AutoBuildStarted,
+        [
+            ("head_sha", head_sha.to_string()),
+            ("merge_sha", merge_sha.to_string()),
+        ],
+    )
+}
+
+pub fn auto_build_succeeded_comment(
+    workflows: &[WorkflowModel],
+    approved_by: &str,
+    merge_sha: &CommitSha,
+    base_ref: &str,
+    duration: Duration,
+) -> Comment {
+    let workflows_status = list_workflows_status(workflows);
+    Comment::new(format!(
+        r#":sunny: Test successful
+{workflows_status}
+Approved by: {approved_by}
+Pushing {merge_sha} to {base_ref}…"#
+    ))
+    .with_template(
+        TemplateKind::AutoBuildSucceeded,
+        [
+            ("merge_sha", merge_sha.to_string()),
+            ("approver", approved_by.to_string()),
+            ("base_ref", base_ref.to_string()),
+            ("workflows", workflows_status),
+            ("workflow_urls", list_workflow_urls(workflows)),
+            ("duration", format_duration(duration)),
+        ],
+    )
+}
+
+pub fn auto_build_base_moved_comment(base_ref: &str) -> Comment {
+    Comment::new(format!(
+        ":exclamation: The `{base_ref}` branch has moved while the merge was being tested. The pull request will be tested again."
+    ))
+    .with_template(
+        TemplateKind::AutoBuildBaseMoved,
+        [("base_ref", base_ref.to_string())],
+    )
+}
+
+pub fn auto_build_push_failed_comment(base_ref: &str) -> Comment {
+    Comment::new(format!(
+        ":exclamation: The tested merge commit could not be pushed to `{base_ref}`."
+    ))
+    .with_template(
+        TemplateKind::AutoBuildPushFailed,
+        [("base_ref", base_ref.to_string())],
+    )
+}
+
+pub fn rollup_created_comment(
+    rollup: PullRequestNumber,
+    included: &[PullRequestNumber],
+    failed: &[PullRequestNumber],
+) -> Comment {
+    let mut text = format!(
+        ":package: Created rollup #{rollup} of {} pull request(s): {}",
+        included.len(),
+        format_pr_list(included)
+    );
+    if !failed.is_empty() {
+        text.push_str(&format!(
+            "\nFailed to merge (conflicts): {}",
+            format_pr_list(failed)
+        ));
     }
+    Comment::new(text).with_template(
+        TemplateKind::RollupCreated,
+        [
+            ("rollup", rollup.to_string()),
+            ("included_prs", format_pr_list(included)),
+            ("failed_prs", format_pr_list(failed)),
+        ],
+    )
+}
+
+pub fn no_rollup_candidates_comment() -> Comment {
+    Comment::new(
+        ":exclamation: There are no approved pull requests that could be included in a rollup."
+            .to_string(),
+    )
+    .with_template(TemplateKind::NoRollupCandidates, [])
+}
+
+pub fn rollup_in_progress_comment(rollup: PullRequestNumber) -> Comment {
+    Comment::new(format!(
+        ":exclamation: Rollup #{rollup} is still open. Merge or close it before creating a new rollup."
+    ))
+    .with_template(
+        TemplateKind::RollupInProgress,
+        [("rollup", rollup.to_string())],
+    )
+}
+
+pub fn rollup_merged_comment(rollup: PullRequestNumber) -> Comment {
+    Comment::new(format!(
+        ":sunny: This pull request was merged as a part of rollup #{rollup}."
+    ))
+    .with_template(TemplateKind::RollupMerged, [("rollup", rollup.to_string())])
+}
+
+pub fn rollup_failed_comment(rollup: PullRequestNumber) -> Comment {
+    Comment::new(format!(
+        ":broken_heart: Rollup #{rollup} was not merged. This pull request was removed from the rollup."
+    ))
+    .with_template(TemplateKind::RollupFailed, [("rollup", rollup.to_string())])
+}
+
+fn format_pr_list(prs: &[PullRequestNumber]) -> String {
+    prs.iter()
+        .map(|pr| format!("#{pr}"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 pub fn try_build_in_progress_comment() -> Comment {
     Comment::new(":exclamation: A try build is currently in progress. You can cancel it using @bors try cancel.".to_string())
+        .with_template(TemplateKind::TryBuildInProgress, [])
 }
 
 pub fn cant_find_last_parent_comment() -> Comment {
     Comment::new(":exclamation: There was no previous build. Please set an explicit parent or remove the `parent=last` argument to use the default parent.".to_string())
+        .with_template(TemplateKind::CantFindLastParent, [])
+}
+
+pub fn try_build_queued_comment(position: usize) -> Comment {
+    Comment::new(format!(
+        ":hourglass_flowing_sand: All try branches are busy, the try build has been queued at position {position}. It will start once a try branch becomes free."
+    ))
+    .with_template(
+        TemplateKind::TryBuildQueued,
+        [("position", position.to_string())],
+    )
+}
+
+pub fn queued_try_build_cancelled_comment() -> Comment {
+    Comment::new("Queued try build cancelled.".to_string())
+        .with_template(TemplateKind::QueuedTryBuildCancelled, [])
+}
+
+pub fn queued_try_build_failed_comment() -> Comment {
+    Comment::new(
+        ":x: The queued try build could not be started and has been removed from the queue. Use @bors try to request it again.".to_string(),
+    )
+    .with_template(TemplateKind::QueuedTryBuildFailed, [])
 }
 
 pub fn no_try_build_in_progress_comment() -> Comment {
     Comment::new(":exclamation: There is currently no try build in progress.".to_string())
+        .with_template(TemplateKind::NoTryBuildInProgress, [])
+}
+
+pub fn no_failed_build_comment() -> Comment {
+    Comment::new(":exclamation: There is no failed build that could be retried.".to_string())
+        .with_template(TemplateKind::NoFailedBuild, [])
+}
+
+pub fn no_failed_workflows_comment() -> Comment {
+    Comment::new(
+        ":exclamation: The last failed build has no failed GitHub Actions workflows that could be re-run.".to_string(),
+    )
+    .with_template(TemplateKind::NoFailedWorkflows, [])
+}
+
+pub fn retrying_build_comment(
+    is_auto_build: bool,
+    merge_sha: &str,
+    workflow_urls: impl Iterator<Item = String>,
+) -> Comment {
+    let (build, template) = if is_auto_build {
+        ("auto build", TemplateKind::AutoBuildRetried)
+    } else {
+        ("try build", TemplateKind::TryBuildRetried)
+    };
+    let workflow_urls = workflow_urls.collect::<Vec<_>>();
+    let mut text = format!(
+        r#":repeat: Retrying {build} {merge_sha}.
+Re-running failed workflows:"#
+    );
+    for url in &workflow_urls {
+        text += format!("\n- {}", url).as_str();
+    }
+    Comment::new(text).with_template(
+        template,
+        [
+            ("merge_sha", merge_sha.to_string()),
+            ("workflow_urls", workflow_urls.join("\n")),
+        ],
+    )
 }
 
 pub fn unclean_try_build_cancelled_comment() -> Comment {
     Comment::new(
         "Try build was cancelled. It was not possible to cancel some workflows.".to_string(),
     )
+    .with_template(TemplateKind::UncleanTryBuildCancelled, [])
 }
 
 pub fn try_build_cancelled_comment(workflow_urls: impl Iterator<Item = String>) -> Comment {
+    let workflow_urls = workflow_urls.collect::<Vec<_>>();
     let mut try_build_cancelled_comment = r#"Try build cancelled.
 Cancelled workflows:"#
         .to_string();
-    for url in workflow_urls {
+    for url in &workflow_urls {
         try_build_cancelled_comment += format!("\n- {}", url).as_str();
     }
-    Comment::new(try_build_cancelled_comment)
+    Comment::new(try_build_cancelled_comment).with_template(
+        TemplateKind::TryBuildCancelled,
+        [("workflow_urls", workflow_urls.join("\n"))],
+    )
 }
 
-pub fn workflow_failed_comment(workflows: &[WorkflowModel]) -> Comment {
+/// `stats` of recent workflow runs are used to point out failed workflows that fail often.
+pub fn workflow_failed_comment(
+    workflows: &[WorkflowModel],
+    failed_checks: &[String],
+    stats: &[WorkflowStats],
+    duration: Duration,
+) -> Comment {
     let workflows_status = list_workflows_status(workflows);
-    Comment::new(format!(
+    let mut text = format!(
         r#":broken_heart: Test failed
 {}"#,
         workflows_status
+    );
+    if !failed_checks.is_empty() {
+        text.push_str(&format!(
+            "\nFailed required checks: {}",
+            format_check_list(failed_checks)
+        ));
+    }
+    let flaky_warnings = flaky_warnings(workflows, stats);
+    if !flaky_warnings.is_empty() {
+        text.push('\n');
+        text.push_str(&flaky_warnings);
+    }
+    Comment::new(text).with_template(
+        TemplateKind::BuildFailed,
+        [
+            ("workflows", workflows_status),
+            ("workflow_urls", list_workflow_urls(workflows)),
+            ("failed_checks", format_check_list(failed_checks)),
+            ("flaky_warnings", flaky_warnings),
+            ("duration", format_duration(duration)),
+        ],
+    )
+}
+
+/// Lists failed workflows that have failed often in recent runs.
+fn flaky_warnings(workflows: &[WorkflowModel], stats: &[WorkflowStats]) -> String {
+    let mut warnings = vec![];
+    for workflow in workflows
+        .iter()
+        .filter(|w| w.status == WorkflowStatus::Failure)
+    {
+        // A single run does not say anything about flakiness
+        if let Some(stats) = stats
+            .iter()
+            .find(|stats| stats.name == workflow.name && stats.attempts > 1)
+        {
+            warnings.push(format!(
+                ":warning: `{}` has failed {:.0}% of recent runs ({} of {} in the last {WORKFLOW_STATS_DAYS} days)",
+                workflow.name,
+                stats.failure_rate() * 100.0,
+                stats.failed_attempts,
+                stats.attempts
+            ));
+        }
+    }
+    warnings.join("\n")
+}
+
+pub fn workflow_stats_comment(stats: &[WorkflowStats]) -> Comment {
+    if stats.is_empty() {
+        return Comment::new(format!(
+            ":sparkles: No workflow has failed in the last {WORKFLOW_STATS_DAYS} days."
+        ));
+    }
+
+    let mut text = format!(
+        r#":bar_chart: Workflows that have failed in the last {WORKFLOW_STATS_DAYS} days:
+
+| Workflow | Failure rate | Failed attempts | Passed on retry |
+|----------|--------------|-----------------|-----------------|"#
+    );
+    for stats in stats {
+        text.push_str(&format!(
+            "\n| {} | {:.0}% | {}/{} | {} |",
+            stats.name,
+            stats.failure_rate() * 100.0,
+            stats.failed_attempts,
+            stats.attempts,
+            stats.passed_on_retry
+        ));
+    }
+    Comment::new(text)
+}
+
+pub fn config_valid_comment() -> Comment {
+    Comment::new(format!(":white_check_mark: `{CONFIG_FILE_PATH}` is valid."))
+}
+
+pub fn config_invalid_comment(error: &str) -> Comment {
+    Comment::new(format!(
+        r#":x: `{CONFIG_FILE_PATH}` is invalid, bors will not be able to load the configuration of this repository after this PR is merged:
+```
+{}
+```"#,
+        error.trim_end()
+    ))
+}
+
+pub fn config_removed_comment() -> Comment {
+    Comment::new(format!(
+        ":warning: This PR removes `{CONFIG_FILE_PATH}`, bors will stop working on this repository after it is merged."
     ))
 }
 
+/// Describes the settings that have changed when the configuration was reloaded after `sha`
+/// was pushed. Each change contains the name of the setting and its previous and new value.
+pub fn config_reloaded_comment(sha: &CommitSha, changes: &[(&str, String, String)]) -> Comment {
+    if changes.is_empty() {
+        return Comment::new(format!(
+            ":gear: `{CONFIG_FILE_PATH}` was modified in {sha}, the effective configuration has not changed."
+        ));
+    }
+    let diff = changes
+        .iter()
+        .map(|(name, previous, new)| format!("- {name} = {previous}\n+ {name} = {new}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Comment::new(format!(
+        r#":gear: `{CONFIG_FILE_PATH}` was modified in {sha}, the configuration has been reloaded:
+```diff
+{diff}
+```"#
+    ))
+}
+
+pub fn manual_merge_comment() -> Comment {
+    Comment::new(
+        ":warning: This PR was merged without a successful bors build, its changes were not tested together with the base branch by bors."
+            .to_string(),
+    )
+}
+
 fn list_workflows_status(workflows: &[WorkflowModel]) -> String {
     workflows
         .iter()
@@ -107,3 +508,11 @@ fn list_workflows_status(workflows: &[WorkflowModel]) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+fn list_workflow_urls(workflows: &[WorkflowModel]) -> String {
+    workflows
+        .iter()
+        .map(|w| w.url.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
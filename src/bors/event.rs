@@ -23,6 +23,8 @@ pub enum BorsRepositoryEvent {
     PullRequestConvertedToDraft(PullRequestConvertedToDraft),
     // When a pull request is ready for review
     PullRequestReadyForReview(PullRequestReadyForReview),
+    /// When a label is added to or removed from a pull request.
+    PullRequestLabelChanged(PullRequestLabelChanged),
     /// When there is a push to a branch. This includes when a commit is pushed, when a commit tag is pushed,
     /// when a branch is deleted or when a tag is deleted.
     PushToBranch(PushToBranch),
@@ -47,6 +49,7 @@ impl BorsRepositoryEvent {
             BorsRepositoryEvent::PullRequestReopened(payload) => &payload.repository,
             BorsRepositoryEvent::PullRequestConvertedToDraft(payload) => &payload.repository,
             BorsRepositoryEvent::PullRequestReadyForReview(payload) => &payload.repository,
+            BorsRepositoryEvent::PullRequestLabelChanged(payload) => &payload.repository,
             BorsRepositoryEvent::PushToBranch(payload) => &payload.repository,
             BorsRepositoryEvent::WorkflowStarted(workflow) => &workflow.repository,
             BorsRepositoryEvent::WorkflowCompleted(workflow) => &workflow.repository,
@@ -131,10 +134,31 @@ pub struct PullRequestReadyForReview {
     pub pull_request: PullRequest,
 }
 
+#[derive(Debug)]
+pub struct PullRequestLabelChanged {
+    pub repository: GithubRepoName,
+    pub pull_request: PullRequest,
+    /// User who has added or removed the label.
+    pub author: GithubUser,
+    pub label: String,
+    pub change: LabelChange,
+    pub html_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelChange {
+    Added,
+    Removed,
+}
+
 #[derive(Debug)]
 pub struct PushToBranch {
     pub repository: GithubRepoName,
     pub branch: String,
+    /// SHA of the branch after the push.
+    pub sha: CommitSha,
+    /// Files that were added, removed or modified by the pushed commits.
+    pub changed_files: Vec<String>,
 }
 
 #[derive(Debug)]
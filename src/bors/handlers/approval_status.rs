@@ -0,0 +1,119 @@
+//! Publishes the approval state of PRs as a `bors/approval` commit status on their head commit,
+//! so that branch protection can require a bors approval before a PR is merged manually.
+
+use crate::bors::RepositoryState;
+use crate::database::ApprovalStatus;
+use crate::github::{CommitSha, CommitStatusState};
+
+/// Context of the commit status that reports the approval state of a PR.
+pub(super) const APPROVAL_STATUS_CONTEXT: &str = "bors/approval";
+
+/// Sets the approval commit status of the given head commit.
+/// Failures are only logged, as the status is not essential for the approval itself.
+pub(super) async fn publish_approval_status(
+    repo: &RepositoryState,
+    head_sha: &CommitSha,
+    status: &ApprovalStatus,
+) {
+    let (state, description) = match status {
+        ApprovalStatus::Approved(info) => (
+            CommitStatusState::Success,
+            format!("Approved by {}", info.approver),
+        ),
+        ApprovalStatus::NotApproved => (
+            CommitStatusState::Pending,
+            "Waiting for approval".to_string(),
+        ),
+    };
+    if let Err(error) = repo
+        .client
+        .create_commit_status(head_sha, APPROVAL_STATUS_CONTEXT, state, &description)
+        .await
+    {
+        tracing::error!("Could not publish approval status for {head_sha}: {error:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::handlers::approval_status::APPROVAL_STATUS_CONTEXT;
+    use crate::tests::mocks::{CommitStatus, default_pr_number, default_repo_name, run_test};
+
+    fn approval_status(state: &str, sha: &str, description: &str) -> CommitStatus {
+        CommitStatus {
+            sha: sha.to_string(),
+            context: APPROVAL_STATUS_CONTEXT.to_string(),
+            state: state.to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    #[sqlx::test]
+    async fn approval_status_approve_unapprove(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            tester.post_comment("@bors r-").await?;
+            tester.expect_comments(1).await;
+
+            assert_eq!(
+                tester.default_repo().lock().commit_statuses,
+                vec![
+                    approval_status("success", "pr-1-sha", "Approved by default-user"),
+                    approval_status("pending", "pr-1-sha", "Waiting for approval"),
+                ]
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn approval_status_push_while_approved(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            tester
+                .push_to_pr(default_repo_name(), default_pr_number())
+                .await?;
+            tester.expect_comments(1).await;
+
+            assert_eq!(
+                tester.default_repo().lock().commit_statuses.last(),
+                Some(&approval_status(
+                    "pending",
+                    "pr-1-commit-1",
+                    "Waiting for approval"
+                ))
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn approval_status_base_edited(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            let branch = tester.create_branch("beta").clone();
+            tester
+                .edit_pr(default_repo_name(), default_pr_number(), |pr| {
+                    pr.base_branch = branch;
+                })
+                .await?;
+            tester.expect_comments(1).await;
+
+            assert_eq!(
+                tester.default_repo().lock().commit_statuses.last(),
+                Some(&approval_status(
+                    "pending",
+                    "pr-1-sha",
+                    "Waiting for approval"
+                ))
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+}
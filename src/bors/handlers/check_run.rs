@@ -0,0 +1,190 @@
+//! Reports the state of builds through a `bors` check run on the head commit of the PR, so that
+//! it is visible in the checks tab of the PR and branch protection can depend on it.
+
+use crate::PgDbClient;
+use crate::bors::RepositoryState;
+use crate::bors::handlers::merge_queue::AUTO_BRANCH_NAME;
+use crate::database::{BuildModel, WorkflowModel, WorkflowStatus};
+use crate::github::{CheckRunState, CommitSha};
+
+/// Name of the check run that reports the state of bors builds.
+pub(super) const BORS_CHECK_RUN_NAME: &str = "bors";
+
+/// Creates a pending check run for a build that has just started.
+/// Failures are only logged, as the check run is not essential for the build itself.
+pub(super) async fn start_build_check_run(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    build_id: i32,
+    build_kind: &str,
+    head_sha: &CommitSha,
+    merge_sha: &CommitSha,
+) {
+    let title = format!("{build_kind} build in progress");
+    let summary = format!("Testing merge commit `{merge_sha}`.");
+    let result = async {
+        let check_run_id = repo
+            .client
+            .create_check_run(
+                BORS_CHECK_RUN_NAME,
+                head_sha,
+                CheckRunState::InProgress,
+                &title,
+                &summary,
+            )
+            .await?;
+        db.set_build_check_run_id(build_id, check_run_id).await
+    }
+    .await;
+    if let Err(error) = result {
+        tracing::error!("Could not create check run for {head_sha}: {error:?}");
+    }
+}
+
+/// Updates the check run of the build to the given state and lists the workflows of the build
+/// in its summary. Failures are only logged.
+pub(super) async fn update_build_check_run(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    build: &BuildModel,
+    state: CheckRunState,
+) {
+    // The build has been started before check runs were reported
+    let Some(check_run_id) = build.check_run_id else {
+        return;
+    };
+    let result = async {
+        let workflows = db.get_workflows_for_build(build).await?;
+        repo.client
+            .update_check_run(
+                check_run_id as u64,
+                state,
+                &check_run_title(build, state),
+                &check_run_summary(build, &workflows),
+            )
+            .await
+    }
+    .await;
+    if let Err(error) = result {
+        tracing::error!("Could not update check run {check_run_id}: {error:?}");
+    }
+}
+
+fn check_run_title(build: &BuildModel, state: CheckRunState) -> String {
+    let kind = if build.branch == AUTO_BRANCH_NAME {
+        "Merge"
+    } else {
+        "Try"
+    };
+    let outcome = match state {
+        CheckRunState::InProgress => "in progress",
+        CheckRunState::Success => "succeeded",
+        CheckRunState::Failure => "failed",
+        CheckRunState::Cancelled => "was cancelled",
+        CheckRunState::TimedOut => "timed out",
+    };
+    format!("{kind} build {outcome}")
+}
+
+fn check_run_summary(build: &BuildModel, workflows: &[WorkflowModel]) -> String {
+    let mut lines = vec![format!("Merge commit: `{}`", build.commit_sha)];
+    if !workflows.is_empty() {
+        lines.extend([
+            String::new(),
+            "| Workflow | Status |".to_string(),
+            "|----------|--------|".to_string(),
+        ]);
+        let mut workflows = workflows.iter().collect::<Vec<_>>();
+        workflows.sort_by(|a, b| a.name.cmp(&b.name));
+        lines.extend(workflows.into_iter().map(|workflow| {
+            let status = match workflow.status {
+                WorkflowStatus::Pending => ":hourglass: pending",
+                WorkflowStatus::Success => ":white_check_mark: success",
+                WorkflowStatus::Failure => ":x: failure",
+            };
+            format!("| [{}]({}) | {status} |", workflow.name, workflow.url)
+        }));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::handlers::check_run::BORS_CHECK_RUN_NAME;
+    use crate::tests::mocks::{BorsBuilder, GitHubState, run_test};
+
+    #[sqlx::test]
+    async fn try_build_check_run_success(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+            {
+                let repo = tester.default_repo();
+                let repo = repo.lock();
+                let check_run = &repo.created_check_runs[0];
+                assert_eq!(check_run.name, BORS_CHECK_RUN_NAME);
+                assert_eq!(check_run.head_sha, "pr-1-sha");
+                assert_eq!(check_run.status, "in_progress");
+                assert_eq!(check_run.title, "Try build in progress");
+            }
+
+            tester.workflow_success(tester.try_branch()).await?;
+            tester.expect_comments(1).await;
+            let repo = tester.default_repo();
+            let repo = repo.lock();
+            let check_run = &repo.created_check_runs[0];
+            assert_eq!(repo.created_check_runs.len(), 1);
+            assert_eq!(check_run.status, "completed");
+            assert_eq!(check_run.conclusion.as_deref(), Some("success"));
+            assert_eq!(check_run.title, "Try build succeeded");
+            insta::assert_snapshot!(check_run.summary, @r"
+            Merge commit: `merge-main-sha1-pr-1-sha-0`
+
+            | Workflow | Status |
+            |----------|--------|
+            | [Workflow1](https://github.com/workflows/Workflow1/1) | :white_check_mark: success |
+            ");
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_check_run_cancelled(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+            tester.post_comment("@bors try cancel").await?;
+            tester.expect_comments(1).await;
+
+            let repo = tester.default_repo();
+            let repo = repo.lock();
+            let check_run = &repo.created_check_runs[0];
+            assert_eq!(check_run.status, "completed");
+            assert_eq!(check_run.conclusion.as_deref(), Some("cancelled"));
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn auto_build_check_run_failure(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config("merge_queue_enabled = true"))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_failure(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+
+                let repo = tester.default_repo();
+                let repo = repo.lock();
+                let check_run = &repo.created_check_runs[0];
+                assert_eq!(check_run.head_sha, "pr-1-sha");
+                assert_eq!(check_run.conclusion.as_deref(), Some("failure"));
+                assert_eq!(check_run.title, "Merge build failed");
+                Ok(tester)
+            })
+            .await;
+    }
+}
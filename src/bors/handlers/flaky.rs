@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use crate::PgDbClient;
+use crate::bors::RepositoryState;
+use crate::bors::comment::workflow_stats_comment;
+use crate::database::{WORKFLOW_STATS_DAYS, WorkflowStats};
+use crate::github::PullRequest;
+
+/// Maximum number of workflows listed by the `flaky` command.
+const MAX_LISTED_WORKFLOWS: usize = 10;
+
+/// Posts a summary of the workflows that fail most often in the repository.
+pub(super) async fn command_flaky(
+    repo: Arc<RepositoryState>,
+    db: Arc<PgDbClient>,
+    pr: &PullRequest,
+) -> anyhow::Result<()> {
+    let mut stats = db
+        .get_workflow_stats(repo.repository(), WORKFLOW_STATS_DAYS)
+        .await?
+        .into_iter()
+        .filter(|stats| stats.failed_attempts > 0)
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| {
+        b.failure_rate()
+            .total_cmp(&a.failure_rate())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    stats.truncate(MAX_LISTED_WORKFLOWS);
+
+    repo.post_comment(pr.number, workflow_stats_comment(&stats))
+        .await?;
+    Ok(())
+}
+
+/// Loads statistics of recent workflow runs that are used to annotate build failure comments.
+/// Returns no statistics if the annotations are disabled.
+pub(super) async fn load_flaky_annotations(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+) -> anyhow::Result<Vec<WorkflowStats>> {
+    if !repo.config.load().flaky_annotations {
+        return Ok(vec![]);
+    }
+    db.get_workflow_stats(repo.repository(), WORKFLOW_STATS_DAYS)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::handlers::trybuild::TRY_BRANCH_NAME;
+    use crate::tests::mocks::{BorsBuilder, GitHubState, Workflow, run_test};
+
+    #[sqlx::test]
+    async fn flaky_no_failures(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors flaky").await?;
+            insta::assert_snapshot!(
+                tester.get_comment().await?,
+                @":sparkles: No workflow has failed in the last 30 days."
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn flaky_failed_then_passed_on_retry(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.create_branch(TRY_BRANCH_NAME).expect_suites(2);
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+
+            let branch = tester.try_branch();
+            tester
+                .workflow_success(Workflow::from(branch.clone()).with_run_id(1))
+                .await?;
+            tester
+                .workflow_failure(Workflow::from(branch.clone()).with_run_id(2))
+                .await?;
+            tester.expect_comments(1).await;
+
+            tester.post_comment("@bors retry").await?;
+            tester.expect_comments(1).await;
+            tester.get_branch_mut(TRY_BRANCH_NAME).expect_suites(1);
+            tester
+                .workflow_success(Workflow::from(branch).with_run_id(2))
+                .await?;
+            tester.expect_comments(1).await;
+
+            tester.post_comment("@bors flaky").await?;
+            insta::assert_snapshot!(tester.get_comment().await?, @r"
+            :bar_chart: Workflows that have failed in the last 30 days:
+
+            | Workflow | Failure rate | Failed attempts | Passed on retry |
+            |----------|--------------|-----------------|-----------------|
+            | Workflow1 | 33% | 1/3 | 1 |
+            ");
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn annotate_failure_comment(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config("flaky_annotations = true"))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_BRANCH_NAME).expect_suites(1);
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .workflow_success(Workflow::from(tester.try_branch()).with_run_id(1))
+                    .await?;
+                tester.expect_comments(1).await;
+
+                tester.get_branch_mut(TRY_BRANCH_NAME).reset_suites();
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .workflow_failure(Workflow::from(tester.try_branch()).with_run_id(2))
+                    .await?;
+                insta::assert_snapshot!(tester.get_comment().await?, @r"
+                :broken_heart: Test failed
+                - [Workflow1](https://github.com/workflows/Workflow1/2) :x:
+                :warning: `Workflow1` has failed 50% of recent runs (1 of 2 in the last 30 days)
+                ");
+                Ok(tester)
+            })
+            .await;
+    }
+}
@@ -29,8 +29,12 @@ pub(super) async fn command_help(
             jobs: vec![],
         },
         BorsCommand::TryCancel,
+        BorsCommand::Retry,
         BorsCommand::SetRollupMode(RollupMode::Always),
+        BorsCommand::CreateRollup,
         BorsCommand::Info,
+        BorsCommand::Flaky,
+        BorsCommand::ValidateConfig,
         BorsCommand::Ping,
         BorsCommand::Help,
         BorsCommand::OpenTree,
@@ -41,9 +45,7 @@ pub(super) async fn command_help(
     .collect::<Vec<_>>()
     .join("\n");
 
-    repo.client
-        .post_comment(pr.number, Comment::new(help))
-        .await?;
+    repo.post_comment(pr.number, Comment::new(help)).await?;
     Ok(())
 }
 
@@ -74,12 +76,24 @@ fn get_command_help(command: BorsCommand) -> String {
             "`try [parent=<parent>] [jobs=<jobs>]`: Start a try build. Optionally, you can specify a `<parent>` SHA or a list of `<jobs>` to run"
         }
         BorsCommand::TryCancel => "`try cancel`: Cancel a running try build",
+        BorsCommand::Retry => {
+            "`retry`: Re-run the failed workflows of the last failed try or auto build"
+        }
         BorsCommand::SetRollupMode(_) => {
             "`rollup=<never|iffy|maybe|always>`: Mark the rollup status of the PR"
         }
+        BorsCommand::CreateRollup => {
+            "`rollup create`: Create a rollup PR from approved PRs marked with `rollup=always` or `rollup=maybe`"
+        }
         BorsCommand::Info => {
             "`info`: Get information about the current PR including delegation, priority, merge status, and try build status"
         }
+        BorsCommand::Flaky => {
+            "`flaky`: List workflows that have failed most often in the last 30 days"
+        }
+        BorsCommand::ValidateConfig => {
+            "`validate-config`: Check that bors can load the `rust-bors.toml` file of this PR"
+        }
         BorsCommand::OpenTree => "`treeclosed-`, `treeopen`: Open the repository tree for merging",
         BorsCommand::TreeClosed(_) => {
             "`treeclosed=<priority>`: Close the tree for PRs with priority less than `<priority>`"
@@ -106,8 +120,12 @@ mod tests {
             - `delegate-`: Remove any previously granted delegation
             - `try [parent=<parent>] [jobs=<jobs>]`: Start a try build. Optionally, you can specify a `<parent>` SHA or a list of `<jobs>` to run
             - `try cancel`: Cancel a running try build
+            - `retry`: Re-run the failed workflows of the last failed try or auto build
             - `rollup=<never|iffy|maybe|always>`: Mark the rollup status of the PR
+            - `rollup create`: Create a rollup PR from approved PRs marked with `rollup=always` or `rollup=maybe`
             - `info`: Get information about the current PR including delegation, priority, merge status, and try build status
+            - `flaky`: List workflows that have failed most often in the last 30 days
+            - `validate-config`: Check that bors can load the `rust-bors.toml` file of this PR
             - `ping`: Check if the bot is alive
             - `help`: Print this help message
             - `treeclosed-`, `treeopen`: Open the repository tree for merging
@@ -54,9 +54,7 @@ pub(super) async fn command_info(
     let info = info_lines.join("\n");
 
     // Post the comment
-    repo.client
-        .post_comment(pr.number, Comment::new(info))
-        .await?;
+    repo.post_comment(pr.number, Comment::new(info)).await?;
 
     Ok(())
 }
@@ -0,0 +1,248 @@
+//! Detects merges and pushes that bypass the merge queue and reacts to them according to the
+//! `manual_merge_policy` of the repository.
+
+use crate::PgDbClient;
+use crate::bors::RepositoryState;
+use crate::bors::comment::manual_merge_comment;
+use crate::bors::handlers::merge_queue::AUTO_BRANCH_NAME;
+use crate::config::ManualMergePolicy;
+use crate::database::{AuditEntryKind, BuildStatus, PullRequestModel};
+use crate::github::{CommitSha, PullRequest};
+
+/// Warns about a PR that was merged without a successful bors build.
+pub(super) async fn handle_manual_merge(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    pr: &PullRequest,
+) -> anyhow::Result<()> {
+    if repo.config.load().manual_merge_policy == ManualMergePolicy::Ignore {
+        return Ok(());
+    }
+
+    let pr_model = db.get_pull_request(repo.repository(), pr.number).await?;
+    // PRs included in a rollup are merged by the auto build of the rollup, but only once that
+    // build has succeeded
+    let rollup = match pr_model.as_ref().and_then(|pr_model| pr_model.rollup_pr_id) {
+        Some(rollup_id) => db.get_pull_request_by_id(rollup_id).await?,
+        None => None,
+    };
+    if pr_model.iter().chain(&rollup).any(merged_by_bors) {
+        return Ok(());
+    }
+
+    tracing::warn!("PR {} was merged outside of bors", pr.number);
+    db.create_audit_entry(
+        repo.repository(),
+        AuditEntryKind::ManualMerge,
+        &pr.base.name,
+        &pr.head.sha,
+        Some(pr.number),
+    )
+    .await?;
+    repo.post_comment(pr.number, manual_merge_comment()).await
+}
+
+/// Was the PR merged by a successful auto build?
+fn merged_by_bors(pr: &PullRequestModel) -> bool {
+    pr.auto_build
+        .as_ref()
+        .is_some_and(|build| build.status == BuildStatus::Success)
+}
+
+/// Reports a push to a base branch that has moved it to a commit that was not produced by
+/// a bors build.
+pub(super) async fn handle_push_to_base_branch(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    branch: &str,
+    sha: &CommitSha,
+) -> anyhow::Result<()> {
+    let policy = repo.config.load().manual_merge_policy;
+    if policy == ManualMergePolicy::Ignore {
+        return Ok(());
+    }
+    if db
+        .find_build(repo.repository(), AUTO_BRANCH_NAME.to_string(), sha.clone())
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    tracing::warn!("Branch {branch} was moved to {sha} outside of bors");
+    if policy == ManualMergePolicy::Issue {
+        repo.client
+            .create_issue(
+                &format!("`{branch}` was modified outside of bors"),
+                &format!(
+                    ":warning: `{branch}` was moved to {sha}, which was not produced by a bors build. Changes should be merged through the merge queue, so that they are tested together with the latest `{branch}`."
+                ),
+            )
+            .await?;
+    }
+    db.create_audit_entry(
+        repo.repository(),
+        AuditEntryKind::DirectPush,
+        branch,
+        sha,
+        None,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::PullRequestStatus;
+    use crate::database::{AuditEntryKind, MergeableState};
+    use crate::tests::mocks::{
+        BorsBuilder, BorsTester, GitHubState, default_branch_name, default_pr_number,
+        default_repo_name,
+    };
+
+    async fn wait_for_audit_entries(tester: &BorsTester, count: usize) -> anyhow::Result<()> {
+        let db = tester.db();
+        tester
+            .wait_for(|| {
+                let db = db.clone();
+                async move { Ok(db.get_audit_entries(&default_repo_name()).await?.len() == count) }
+            })
+            .await
+    }
+
+    #[sqlx::test]
+    async fn manual_merge_warn(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"manual_merge_policy = "warn""#))
+            .run_test(|mut tester| async {
+                tester
+                    .merge_pr(default_repo_name(), default_pr_number())
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":warning: This PR was merged without a successful bors build, its changes were not tested together with the base branch by bors."
+                );
+
+                let entries = tester.db().get_audit_entries(&default_repo_name()).await?;
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].kind, AuditEntryKind::ManualMerge);
+                assert_eq!(
+                    entries[0].pr_number.map(|pr| pr.0),
+                    Some(default_pr_number())
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn manual_merge_of_pr_in_open_rollup(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"manual_merge_policy = "warn""#))
+            .run_test(|mut tester| async {
+                let db = tester.db();
+                let pr = db
+                    .get_or_create_pull_request(
+                        &default_repo_name(),
+                        default_pr_number().into(),
+                        default_branch_name(),
+                        MergeableState::Unknown,
+                        &PullRequestStatus::Open,
+                    )
+                    .await?;
+                let rollup = db
+                    .get_or_create_pull_request(
+                        &default_repo_name(),
+                        10u64.into(),
+                        default_branch_name(),
+                        MergeableState::Unknown,
+                        &PullRequestStatus::Open,
+                    )
+                    .await?;
+                db.set_rollup_pr(&[pr], &rollup).await?;
+
+                // The rollup has not been merged yet, so the PR was merged by hand
+                tester
+                    .merge_pr(default_repo_name(), default_pr_number())
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":warning: This PR was merged without a successful bors build, its changes were not tested together with the base branch by bors."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn direct_push_to_pr_base_branch(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"manual_merge_policy = "warn""#))
+            .run_test(|mut tester| async {
+                let branch = tester.create_branch("beta").clone();
+                tester
+                    .edit_pr(default_repo_name(), default_pr_number(), |pr| {
+                        pr.base_branch = branch;
+                    })
+                    .await?;
+                tester.get_branch_mut("beta").set_to_sha("direct-push-sha");
+                tester.push_to_branch("beta", &[]).await?;
+                wait_for_audit_entries(&tester, 1).await?;
+
+                let entries = tester.db().get_audit_entries(&default_repo_name()).await?;
+                assert_eq!(entries[0].kind, AuditEntryKind::DirectPush);
+                assert_eq!(entries[0].branch, "beta");
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn direct_push_opens_issue(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"manual_merge_policy = "issue""#))
+            .run_test(|mut tester| async {
+                tester
+                    .get_branch_mut(default_branch_name())
+                    .set_to_sha("direct-push-sha");
+                tester.push_to_branch(default_branch_name(), &[]).await?;
+                wait_for_audit_entries(&tester, 1).await?;
+
+                let entries = tester.db().get_audit_entries(&default_repo_name()).await?;
+                assert_eq!(entries[0].kind, AuditEntryKind::DirectPush);
+                assert_eq!(entries[0].sha, "direct-push-sha");
+                assert_eq!(tester.default_repo().lock().created_issues.len(), 1);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn ignore_push_of_auto_build(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+merge_queue_enabled = true
+manual_merge_policy = "warn"
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_success(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+                tester.push_to_branch(default_branch_name(), &[]).await?;
+
+                // Only the second push is reported
+                tester
+                    .get_branch_mut(default_branch_name())
+                    .set_to_sha("direct-push-sha");
+                tester.push_to_branch(default_branch_name(), &[]).await?;
+                wait_for_audit_entries(&tester, 1).await?;
+
+                let entries = tester.db().get_audit_entries(&default_repo_name()).await?;
+                assert_eq!(entries[0].sha, "direct-push-sha");
+                Ok(tester)
+            })
+            .await;
+    }
+}
@@ -0,0 +1,694 @@
+use std::cmp::Reverse;
+
+use anyhow::anyhow;
+
+use crate::PgDbClient;
+use crate::bors::comment::{
+    auto_build_base_moved_comment, auto_build_push_failed_comment, auto_build_started_comment,
+    auto_build_succeeded_comment, workflow_failed_comment,
+};
+use crate::bors::handlers::check_run::{start_build_check_run, update_build_check_run};
+use crate::bors::handlers::flaky::load_flaky_annotations;
+use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::refresh::elapsed_time;
+use crate::bors::handlers::rollup::handle_rollup_finished;
+use crate::bors::handlers::trybuild::{
+    MergeResult, attempt_merge, cancel_build_workflows, merge_conflict_comment,
+};
+use crate::bors::{PullRequestStatus, RepositoryState, RollupMode};
+use crate::database::{
+    BuildModel, BuildStatus, MergeableState, PullRequestModel, TreeState, WorkflowModel,
+};
+use crate::github::{BranchUpdateError, CheckRunState, CommitSha, LabelTrigger};
+
+// This branch serves for preparing the merge commit of the auto build.
+// Same as with the try merge branch, it should not run CI checks.
+pub(super) const AUTO_MERGE_BRANCH_NAME: &str = "automation/bors/auto-merge";
+
+// This branch should run CI checks. Once they pass, its commit is pushed to the base branch.
+pub(super) const AUTO_BRANCH_NAME: &str = "automation/bors/auto";
+
+/// Sorts pull requests in the order in which they will be picked up by the merge queue.
+///
+/// Approved PRs go first, then PRs with a higher priority. PRs that are marked with
+/// `rollup=always` go after PRs that should not be rolled up. Ties are broken by the PR number.
+pub fn sort_merge_queue(prs: &mut [PullRequestModel]) {
+    prs.sort_by_key(|pr| {
+        (
+            !pr.is_approved(),
+            Reverse(pr.priority.unwrap_or(0)),
+            rollup_order(pr.rollup),
+            pr.number.0,
+        )
+    });
+}
+
+fn rollup_order(rollup: Option<RollupMode>) -> u8 {
+    match rollup {
+        Some(RollupMode::Never) => 0,
+        Some(RollupMode::Iffy) => 1,
+        Some(RollupMode::Maybe) | None => 2,
+        Some(RollupMode::Always) => 3,
+    }
+}
+
+/// Returns true if the PR can be picked up by the merge queue.
+fn is_merge_queue_candidate(pr: &PullRequestModel, tree_state: &TreeState) -> bool {
+    let tree_allows_merge = match tree_state {
+        TreeState::Open => true,
+        TreeState::Closed { priority, .. } => pr.priority.unwrap_or(0) >= *priority as i32,
+    };
+    let has_auto_build = pr
+        .auto_build
+        .as_ref()
+        .is_some_and(|build| build.status != BuildStatus::Cancelled);
+
+    pr.pr_status == PullRequestStatus::Open
+        && pr.is_approved()
+        && pr.rollup_pr_id.is_none()
+        && !has_auto_build
+        && pr.mergeable_state != MergeableState::HasConflicts
+        && tree_allows_merge
+}
+
+/// Starts an auto build for the next PR in the merge queue, unless an auto build is already
+/// running in the given repository.
+pub(super) async fn process_merge_queue(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+) -> anyhow::Result<()> {
+    if !repo.config.load().merge_queue_enabled {
+        return Ok(());
+    }
+
+    let running_builds = db.get_running_builds(repo.repository()).await?;
+    if running_builds
+        .iter()
+        .any(|build| build.branch == AUTO_BRANCH_NAME)
+    {
+        tracing::debug!("Auto build already in progress");
+        return Ok(());
+    }
+
+    let tree_state = db
+        .repo_db(repo.repository())
+        .await?
+        .map(|repo| repo.tree_state)
+        .unwrap_or(TreeState::Open);
+
+    let mut prs = db.get_open_pull_requests(repo.repository()).await?;
+    sort_merge_queue(&mut prs);
+
+    for pr_model in prs
+        .into_iter()
+        .filter(|pr| is_merge_queue_candidate(pr, &tree_state))
+    {
+        let pr = repo.client.get_pull_request(pr_model.number).await?;
+        let Some(approver) = pr_model.approver().map(|approver| approver.to_string()) else {
+            continue;
+        };
+        if pr_model.approved_sha() != Some(pr.head.sha.as_ref()) {
+            tracing::warn!(
+                "PR {} was approved at a different commit than {}, skipping",
+                pr.number,
+                pr.head.sha
+            );
+            continue;
+        }
+
+        let base_sha = repo
+            .client
+            .get_branch_sha(&pr.base.name)
+            .await
+            .map_err(|error| anyhow!("Cannot get SHA for branch {}: {error:?}", pr.base.name))?;
+
+        match attempt_merge(
+            &repo.client,
+            &repo.config.load(),
+            AUTO_MERGE_BRANCH_NAME,
+            &pr,
+            &base_sha,
+            &approver,
+            vec![],
+        )
+        .await?
+        {
+            MergeResult::Success(merge_sha) => {
+                repo.client
+                    .set_branch_to_sha(AUTO_BRANCH_NAME, &merge_sha)
+                    .await
+                    .map_err(|error| anyhow!("Cannot set auto branch to {merge_sha}: {error:?}"))?;
+                let build_id = db
+                    .attach_auto_build(
+                        pr_model,
+                        AUTO_BRANCH_NAME.to_string(),
+                        merge_sha.clone(),
+                        base_sha,
+                    )
+                    .await?;
+                start_build_check_run(repo, db, build_id, "Merge", &pr.head.sha, &merge_sha).await;
+
+                tracing::info!("Auto build started for PR {}", pr.number);
+                return repo
+                    .post_comment(
+                        pr.number,
+                        auto_build_started_comment(&pr.head.sha, &merge_sha),
+                    )
+                    .await;
+            }
+            MergeResult::Conflict => {
+                db.set_mergeable_state(&pr_model, MergeableState::HasConflicts)
+                    .await?;
+                handle_label_trigger(repo, pr.number, LabelTrigger::MergeConflict).await?;
+                repo.post_comment(pr.number, merge_conflict_comment(&pr.head.name))
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Cancels the running auto build of the given PR, if there is one.
+/// It has to be called whenever the approval of the PR changes, because the build then no longer
+/// tests the approved commit.
+pub(super) async fn cancel_auto_build(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    pr: &PullRequestModel,
+) -> anyhow::Result<()> {
+    let Some(build) = pr
+        .auto_build
+        .as_ref()
+        .filter(|build| build.status == BuildStatus::Pending)
+    else {
+        return Ok(());
+    };
+
+    tracing::info!("Cancelling auto build of PR {}", pr.number);
+    if let Err(error) = cancel_build_workflows(&repo.client, db, build).await {
+        tracing::error!(
+            "Could not cancel workflows for SHA {}: {error:?}",
+            build.commit_sha
+        );
+    }
+    db.update_build_status(build, BuildStatus::Cancelled)
+        .await?;
+    update_build_check_run(repo, db, build, CheckRunState::Cancelled).await;
+    handle_label_trigger(repo, pr.number, LabelTrigger::BuildCancelled).await
+}
+
+/// Finishes an auto build whose workflows have all completed.
+/// If the build has succeeded, its merge commit is pushed to the base branch of the PR.
+pub(super) async fn complete_auto_build(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    build: BuildModel,
+    pr: PullRequestModel,
+    workflows: &[WorkflowModel],
+    has_failure: bool,
+    failed_checks: &[String],
+) -> anyhow::Result<()> {
+    if has_failure {
+        tracing::info!("Auto build failed");
+        db.update_build_status(&build, BuildStatus::Failure).await?;
+        update_build_check_run(repo, db, &build, CheckRunState::Failure).await;
+        handle_label_trigger(repo, pr.number, LabelTrigger::AutoBuildFailed).await?;
+        let stats = load_flaky_annotations(repo, db).await?;
+        repo.post_comment(
+            pr.number,
+            workflow_failed_comment(
+                workflows,
+                failed_checks,
+                &stats,
+                elapsed_time(build.started_at()),
+            ),
+        )
+        .await?;
+        handle_rollup_finished(repo, db, pr.number, false).await?;
+        return process_merge_queue(repo, db).await;
+    }
+
+    // The PR might have been unapproved or pushed to while the build was running, before bors has
+    // handled the corresponding event. Only push the merge commit if it still contains the
+    // approved commit.
+    let head_sha = repo.client.get_pull_request(pr.number).await?.head.sha;
+    if pr.approved_sha() != Some(head_sha.as_ref()) {
+        tracing::warn!(
+            "PR {} is no longer approved at {head_sha}, cancelling auto build",
+            pr.number
+        );
+        cancel_auto_build(repo, db, &pr).await?;
+        return process_merge_queue(repo, db).await;
+    }
+
+    // The merge commit is pushed only if it fast-forwards the base branch. If the base branch has
+    // moved since the build has started, the tested commit is outdated and the push is rejected.
+    let merge_sha = CommitSha(build.commit_sha.clone());
+    match repo
+        .client
+        .fast_forward_branch(&pr.base_branch, &merge_sha)
+        .await
+    {
+        Ok(()) => {
+            tracing::info!(
+                "Auto build succeeded, pushed {merge_sha} to {}",
+                pr.base_branch
+            );
+            db.update_build_status(&build, BuildStatus::Success).await?;
+            update_build_check_run(repo, db, &build, CheckRunState::Success).await;
+            repo.post_comment(
+                pr.number,
+                auto_build_succeeded_comment(
+                    workflows,
+                    pr.approver().unwrap_or("<unknown>"),
+                    &merge_sha,
+                    &pr.base_branch,
+                    elapsed_time(build.started_at()),
+                ),
+            )
+            .await?;
+        }
+        Err(BranchUpdateError::NotFastForward(_)) => {
+            tracing::warn!(
+                "Base branch {} has moved from {}, cannot push {merge_sha}",
+                pr.base_branch,
+                build.parent
+            );
+            db.update_build_status(&build, BuildStatus::Cancelled)
+                .await?;
+            update_build_check_run(repo, db, &build, CheckRunState::Cancelled).await;
+            handle_label_trigger(repo, pr.number, LabelTrigger::BuildCancelled).await?;
+            repo.post_comment(pr.number, auto_build_base_moved_comment(&pr.base_branch))
+                .await?;
+        }
+        Err(error) => {
+            tracing::error!("Cannot push {merge_sha} to {}: {error:?}", pr.base_branch);
+            db.update_build_status(&build, BuildStatus::Failure).await?;
+            update_build_check_run(repo, db, &build, CheckRunState::Failure).await;
+            repo.post_comment(pr.number, auto_build_push_failed_comment(&pr.base_branch))
+                .await?;
+        }
+    }
+    process_merge_queue(repo, db).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::handlers::merge_queue::{AUTO_BRANCH_NAME, AUTO_MERGE_BRANCH_NAME};
+    use crate::database::BuildStatus;
+    use crate::github::CommitSha;
+    use crate::tests::mocks::{
+        BorsBuilder, BorsTester, GitHubState, Workflow, default_pr_number, default_repo_name,
+        run_test,
+    };
+
+    fn merge_queue_state() -> GitHubState {
+        GitHubState::default().with_default_config("merge_queue_enabled = true")
+    }
+
+    async fn auto_build_status(tester: &BorsTester, sha: &str) -> anyhow::Result<BuildStatus> {
+        let build = tester
+            .db()
+            .find_build(
+                &default_repo_name(),
+                AUTO_BRANCH_NAME.to_string(),
+                CommitSha(sha.to_string()),
+            )
+            .await?
+            .expect("Auto build not found");
+        Ok(build.status)
+    }
+
+    #[sqlx::test]
+    async fn approve_without_merge_queue(pool: sqlx::PgPool) {
+        let gh = run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            Ok(tester)
+        })
+        .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1"]);
+    }
+
+    #[sqlx::test]
+    async fn approve_starts_auto_build(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Testing commit pr-1-sha with merge merge-main-sha1-pr-1-sha-0…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            AUTO_MERGE_BRANCH_NAME,
+            &["main-sha1", "merge-main-sha1-pr-1-sha-0"],
+        );
+        gh.check_sha_history(
+            default_repo_name(),
+            AUTO_BRANCH_NAME,
+            &["merge-main-sha1-pr-1-sha-0"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn auto_build_success_pushes_to_base(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_success(tester.auto_branch()).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @r"
+                :sunny: Test successful
+                - [Workflow1](https://github.com/workflows/Workflow1/1) :white_check_mark:
+                Approved by: default-user
+                Pushing merge-main-sha1-pr-1-sha-0 to main…
+                "
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            "main",
+            &["main-sha1", "merge-main-sha1-pr-1-sha-0"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn retry_failed_auto_build(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                let branch = tester.auto_branch();
+                tester
+                    .workflow_failure(Workflow::from(branch.clone()).with_run_id(1))
+                    .await?;
+                tester.expect_comments(1).await;
+
+                tester.post_comment("@bors retry").await?;
+                insta::assert_snapshot!(tester.get_comment().await?, @r"
+                :repeat: Retrying auto build merge-main-sha1-pr-1-sha-0.
+                Re-running failed workflows:
+                - https://github.com/rust-lang/borstest/actions/runs/1
+                ");
+                assert_eq!(
+                    auto_build_status(&tester, "merge-main-sha1-pr-1-sha-0").await?,
+                    BuildStatus::Pending
+                );
+
+                tester.get_branch_mut(AUTO_BRANCH_NAME).expect_suites(1);
+                tester
+                    .workflow_success(Workflow::from(branch).with_run_id(1))
+                    .await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":sunny: Test successful"));
+                Ok(tester)
+            })
+            .await;
+        gh.check_rerun_workflows(default_repo_name(), &[1]);
+        gh.check_sha_history(
+            default_repo_name(),
+            "main",
+            &["main-sha1", "merge-main-sha1-pr-1-sha-0"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn auto_build_base_moved(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.get_branch_mut("main").set_to_sha("main-sha2");
+                tester.workflow_success(tester.auto_branch()).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":exclamation: The `main` branch has moved while the merge was being tested. The pull request will be tested again."
+                );
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Testing commit pr-1-sha with merge merge-main-sha2-pr-1-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1", "main-sha2"]);
+    }
+
+    #[sqlx::test]
+    async fn auto_build_base_moved_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+merge_queue_enabled = true
+
+[labels]
+build_cancelled = ["+cancelled"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.get_branch_mut("main").set_to_sha("main-sha2");
+                tester.workflow_success(tester.auto_branch()).await?;
+                tester.expect_comments(2).await;
+
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .check_added_labels(&["cancelled"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn auto_build_squash_pushes_squashed_commit(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+merge_queue_enabled = true
+merge_strategy = "squash"
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Testing commit pr-1-sha with merge commit-1…"
+                );
+                tester.workflow_success(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1", "commit-1"]);
+
+        let commits = gh.default_repo().lock().created_commits.clone();
+        assert_eq!(commits[0].parents, vec!["main-sha1"]);
+        insta::assert_snapshot!(commits[0].message, @r"
+        PR #1 (#1)
+
+        Description of PR #1
+
+        Approved-by: default-user
+        ");
+    }
+
+    #[sqlx::test]
+    async fn auto_build_failure(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_failure(tester.auto_branch()).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @r"
+                :broken_heart: Test failed
+                - [Workflow1](https://github.com/workflows/Workflow1/1) :x:
+                "
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1"]);
+    }
+
+    #[sqlx::test]
+    async fn auto_build_failure_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+merge_queue_enabled = true
+
+[labels]
+auto_build_failed = ["+failed"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_failure(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .check_added_labels(&["failed"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn auto_build_merge_conflict(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.create_branch(AUTO_MERGE_BRANCH_NAME).merge_conflict = true;
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":lock: Merge conflict"));
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn unapprove_cancels_auto_build(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.post_comment("@bors r-").await?;
+                tester.expect_comments(1).await;
+                assert_eq!(
+                    auto_build_status(&tester, "merge-main-sha1-pr-1-sha-0").await?,
+                    BuildStatus::Cancelled
+                );
+
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Testing commit pr-1-sha with merge merge-main-sha1-pr-1-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1"]);
+    }
+
+    #[sqlx::test]
+    async fn push_cancels_auto_build(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester
+                    .push_to_pr(default_repo_name(), default_pr_number())
+                    .await?;
+                tester.expect_comments(1).await;
+                assert_eq!(
+                    auto_build_status(&tester, "merge-main-sha1-pr-1-sha-0").await?,
+                    BuildStatus::Cancelled
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1"]);
+    }
+
+    #[sqlx::test]
+    async fn reapprove_restarts_running_auto_build(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.post_comment("@bors r+ p=1").await?;
+                tester.expect_comments(2).await;
+                assert_eq!(
+                    auto_build_status(&tester, "merge-main-sha1-pr-1-sha-0").await?,
+                    BuildStatus::Cancelled
+                );
+
+                tester.workflow_success(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            "main",
+            &["main-sha1", "merge-main-sha1-pr-1-sha-1"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn auto_build_not_pushed_after_unhandled_push(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                // The push webhook has not been delivered yet
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr_mut(default_pr_number())
+                    .head_sha = "pr-1-commit-1".to_string();
+                tester.workflow_success(tester.auto_branch()).await?;
+                tester
+                    .wait_for_default_pr(|pr| {
+                        pr.auto_build
+                            .as_ref()
+                            .is_some_and(|build| build.status == BuildStatus::Cancelled)
+                    })
+                    .await?;
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), "main", &["main-sha1"]);
+    }
+
+    #[sqlx::test]
+    async fn reapprove_after_failure_restarts_auto_build(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(merge_queue_state())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_failure(tester.auto_branch()).await?;
+                tester.expect_comments(1).await;
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Testing commit pr-1-sha with merge merge-main-sha1-pr-1-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+}
@@ -1,15 +1,24 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use crate::bors::command::{BorsCommand, CommandParseError};
-use crate::bors::event::{BorsGlobalEvent, BorsRepositoryEvent, PullRequestComment};
+use crate::bors::command::{BorsCommand, CommandParseError, parse_bare_command};
+use crate::bors::event::{
+    BorsGlobalEvent, BorsRepositoryEvent, LabelChange, PullRequestComment, PullRequestLabelChanged,
+};
+use crate::bors::handlers::flaky::command_flaky;
 use crate::bors::handlers::help::command_help;
 use crate::bors::handlers::info::command_info;
+use crate::bors::handlers::merge_queue::AUTO_BRANCH_NAME;
 use crate::bors::handlers::ping::command_ping;
-use crate::bors::handlers::refresh::refresh_repository;
+use crate::bors::handlers::refresh::{prune_event_queue, refresh_repository};
 use crate::bors::handlers::review::{
     command_approve, command_close_tree, command_open_tree, command_unapprove,
 };
-use crate::bors::handlers::trybuild::{TRY_BRANCH_NAME, command_try_build, command_try_cancel};
+use crate::bors::handlers::rollup::command_create_rollup;
+use crate::bors::handlers::trybuild::{
+    command_retry, command_try_build, command_try_cancel, is_try_branch,
+};
+use crate::bors::handlers::validate_config::command_validate_config;
 use crate::bors::handlers::workflow::{
     handle_check_suite_completed, handle_workflow_completed, handle_workflow_started,
 };
@@ -17,6 +26,7 @@ use crate::bors::{BorsContext, Comment, RepositoryState};
 use crate::database::DelegatedPermission;
 use crate::github::{GithubUser, PullRequest};
 use crate::permissions::PermissionType;
+use crate::utils::metrics::{COMMANDS_RECEIVED, PERMISSION_DENIALS};
 use crate::{PgDbClient, TeamApiClient, load_repositories};
 use anyhow::Context;
 use octocrab::Octocrab;
@@ -28,17 +38,27 @@ use pr_events::{
 use review::{command_delegate, command_set_priority, command_set_rollup, command_undelegate};
 use tracing::Instrument;
 
+pub use merge_queue::sort_merge_queue;
+pub(crate) use refresh::{report_config_error, report_config_valid};
+
 #[cfg(test)]
 use crate::tests::util::TestSyncMarker;
 
+mod approval_status;
+mod check_run;
+mod flaky;
 mod help;
 mod info;
 mod labels;
+mod manual_merge;
+mod merge_queue;
 mod ping;
 mod pr_events;
 mod refresh;
 mod review;
+mod rollup;
 mod trybuild;
+mod validate_config;
 mod workflow;
 
 #[cfg(test)]
@@ -81,15 +101,12 @@ pub async fn handle_bors_repository_event(
                 .instrument(span.clone())
                 .await
             {
-                repo.client
-                    .post_comment(
-                        pr_number,
-                        Comment::new(
-                            ":x: Encountered an error while executing command".to_string(),
-                        ),
-                    )
-                    .await
-                    .context("Cannot send comment reacting to an error")?;
+                repo.post_comment(
+                    pr_number,
+                    Comment::new(":x: Encountered an error while executing command".to_string()),
+                )
+                .await
+                .context("Cannot send comment reacting to an error")?;
                 return Err(error.context("Cannot perform command"));
             }
         }
@@ -195,6 +212,36 @@ pub async fn handle_bors_repository_event(
                 .instrument(span.clone())
                 .await?;
         }
+        BorsRepositoryEvent::PullRequestLabelChanged(payload) => {
+            // Labels are also modified by the bot itself, these changes should not trigger
+            // any commands
+            if repo.client.is_user_internal(&payload.author) {
+                tracing::trace!(
+                    "Ignoring label change {payload:?} because it was made by this bot"
+                );
+                return Ok(());
+            }
+
+            let span = tracing::info_span!(
+                "Pull request label changed",
+                pr = format!("{}#{}", payload.repository, payload.pull_request.number),
+                label = payload.label,
+                author = payload.author.username
+            );
+            let pr_number = payload.pull_request.number;
+            if let Err(error) = handle_label_changed(Arc::clone(&repo), db, payload)
+                .instrument(span.clone())
+                .await
+            {
+                repo.post_comment(
+                    pr_number,
+                    Comment::new(":x: Encountered an error while executing command".to_string()),
+                )
+                .await
+                .context("Cannot send comment reacting to an error")?;
+                return Err(error.context("Cannot perform command"));
+            }
+        }
         BorsRepositoryEvent::PushToBranch(payload) => {
             let span =
                 tracing::info_span!("Pushed to branch", repo = payload.repository.to_string());
@@ -238,8 +285,11 @@ pub async fn handle_bors_global_event(
                         .await
                 }
             }))
-            .instrument(span)
+            .instrument(span.clone())
             .await;
+            if let Err(error) = prune_event_queue(&db).instrument(span).await {
+                tracing::error!("Cannot prune the event queue: {error:?}");
+            }
 
             #[cfg(test)]
             WAIT_FOR_REFRESH.mark();
@@ -274,125 +324,16 @@ async fn handle_comment(
     for command in commands {
         match command {
             Ok(command) => {
-                let repo = Arc::clone(&repo);
-                let database = Arc::clone(&database);
-                let result = match command {
-                    BorsCommand::Approve {
-                        approver,
-                        priority,
-                        rollup,
-                    } => {
-                        let span = tracing::info_span!("Approve");
-                        command_approve(
-                            repo,
-                            database,
-                            &pull_request,
-                            &comment.author,
-                            &approver,
-                            priority,
-                            rollup,
-                        )
-                        .instrument(span)
-                        .await
-                    }
-                    BorsCommand::OpenTree => {
-                        let span = tracing::info_span!("TreeOpen");
-                        command_open_tree(repo, database, &pull_request, &comment.author)
-                            .instrument(span)
-                            .await
-                    }
-                    BorsCommand::TreeClosed(priority) => {
-                        let span = tracing::info_span!("TreeClosed");
-                        command_close_tree(
-                            repo,
-                            database,
-                            &pull_request,
-                            &comment.author,
-                            priority,
-                            &comment.html_url,
-                        )
-                        .instrument(span)
-                        .await
-                    }
-                    BorsCommand::Unapprove => {
-                        let span = tracing::info_span!("Unapprove");
-                        command_unapprove(repo, database, &pull_request, &comment.author)
-                            .instrument(span)
-                            .await
-                    }
-                    BorsCommand::SetPriority(priority) => {
-                        let span = tracing::info_span!("Priority");
-                        command_set_priority(
-                            repo,
-                            database,
-                            &pull_request,
-                            &comment.author,
-                            priority,
-                        )
-                        .instrument(span)
-                        .await
-                    }
-                    BorsCommand::SetDelegate(delegate_type) => {
-                        let span = tracing::info_span!("Delegate");
-                        command_delegate(
-                            repo,
-                            database,
-                            &pull_request,
-                            &comment.author,
-                            delegate_type,
-                        )
-                        .instrument(span)
-                        .await
-                    }
-                    BorsCommand::Undelegate => {
-                        let span = tracing::info_span!("Undelegate");
-                        command_undelegate(repo, database, &pull_request, &comment.author)
-                            .instrument(span)
-                            .await
-                    }
-                    BorsCommand::Help => {
-                        let span = tracing::info_span!("Help");
-                        command_help(repo, &pull_request).instrument(span).await
-                    }
-                    BorsCommand::Ping => {
-                        let span = tracing::info_span!("Ping");
-                        command_ping(repo, &pull_request).instrument(span).await
-                    }
-                    BorsCommand::Try { parent, jobs } => {
-                        let span = tracing::info_span!("Try");
-                        command_try_build(
-                            repo,
-                            database,
-                            &pull_request,
-                            &comment.author,
-                            parent,
-                            jobs,
-                        )
-                        .instrument(span)
-                        .await
-                    }
-                    BorsCommand::TryCancel => {
-                        let span = tracing::info_span!("Cancel try");
-                        command_try_cancel(repo, database, &pull_request, &comment.author)
-                            .instrument(span)
-                            .await
-                    }
-                    BorsCommand::Info => {
-                        let span = tracing::info_span!("Info");
-                        command_info(repo, &pull_request, database)
-                            .instrument(span)
-                            .await
-                    }
-                    BorsCommand::SetRollupMode(rollup) => {
-                        let span = tracing::info_span!("Rollup");
-                        command_set_rollup(repo, database, &pull_request, &comment.author, rollup)
-                            .instrument(span)
-                            .await
-                    }
-                };
-                if result.is_err() {
-                    return result.context("Cannot execute Bors command");
-                }
+                execute_command(
+                    Arc::clone(&repo),
+                    Arc::clone(&database),
+                    &pull_request,
+                    &comment.author,
+                    &comment.html_url,
+                    command,
+                )
+                .await
+                .context("Cannot execute Bors command")?;
             }
             Err(error) => {
                 let message = match error {
@@ -414,8 +355,7 @@ async fn handle_comment(
                     }
                 };
                 tracing::warn!("{}", message);
-                repo.client
-                    .post_comment(pull_request.number, Comment::new(message))
+                repo.post_comment(pull_request.number, Comment::new(message))
                     .await
                     .context("Could not reply to PR comment")?;
             }
@@ -424,12 +364,180 @@ async fn handle_comment(
     Ok(())
 }
 
+/// Executes the command that the repository configuration maps to the added or removed label,
+/// with the permissions of the user who has changed the label.
+async fn handle_label_changed(
+    repo: Arc<RepositoryState>,
+    database: Arc<PgDbClient>,
+    payload: PullRequestLabelChanged,
+) -> anyhow::Result<()> {
+    let text = {
+        let config = repo.config.load();
+        let Some(commands) = config.label_commands.get(&payload.label) else {
+            return Ok(());
+        };
+        match payload.change {
+            LabelChange::Added => commands.added.clone(),
+            LabelChange::Removed => commands.removed.clone(),
+        }
+    };
+    let Some(text) = text else {
+        return Ok(());
+    };
+    let command = parse_bare_command(&text)
+        .map_err(|error| anyhow::anyhow!("Invalid label command `{text}`: {error:?}"))?;
+    tracing::debug!("Command: {command:?}");
+
+    execute_command(
+        repo,
+        database,
+        &payload.pull_request,
+        &payload.author,
+        &payload.html_url,
+        command,
+    )
+    .await
+    .context("Cannot execute Bors command")
+}
+
+/// Executes a single command on behalf of `author`. `html_url` links to the place where the
+/// command was issued.
+async fn execute_command(
+    repo: Arc<RepositoryState>,
+    database: Arc<PgDbClient>,
+    pull_request: &PullRequest,
+    author: &GithubUser,
+    html_url: &str,
+    command: BorsCommand,
+) -> anyhow::Result<()> {
+    COMMANDS_RECEIVED.with_label_values(&[command.name()]).inc();
+    match command {
+        BorsCommand::Approve {
+            approver,
+            priority,
+            rollup,
+        } => {
+            let span = tracing::info_span!("Approve");
+            command_approve(
+                repo,
+                database,
+                pull_request,
+                author,
+                &approver,
+                priority,
+                rollup,
+            )
+            .instrument(span)
+            .await
+        }
+        BorsCommand::OpenTree => {
+            let span = tracing::info_span!("TreeOpen");
+            command_open_tree(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::TreeClosed(priority) => {
+            let span = tracing::info_span!("TreeClosed");
+            command_close_tree(repo, database, pull_request, author, priority, html_url)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Unapprove => {
+            let span = tracing::info_span!("Unapprove");
+            command_unapprove(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::SetPriority(priority) => {
+            let span = tracing::info_span!("Priority");
+            command_set_priority(repo, database, pull_request, author, priority)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::SetDelegate(delegate_type) => {
+            let span = tracing::info_span!("Delegate");
+            command_delegate(repo, database, pull_request, author, delegate_type)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Undelegate => {
+            let span = tracing::info_span!("Undelegate");
+            command_undelegate(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Help => {
+            let span = tracing::info_span!("Help");
+            command_help(repo, pull_request).instrument(span).await
+        }
+        BorsCommand::Ping => {
+            let span = tracing::info_span!("Ping");
+            command_ping(repo, pull_request).instrument(span).await
+        }
+        BorsCommand::Try { parent, jobs } => {
+            let span = tracing::info_span!("Try");
+            command_try_build(repo, database, pull_request, author, parent, jobs)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::TryCancel => {
+            let span = tracing::info_span!("Cancel try");
+            command_try_cancel(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Retry => {
+            let span = tracing::info_span!("Retry");
+            command_retry(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Info => {
+            let span = tracing::info_span!("Info");
+            command_info(repo, pull_request, database)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::Flaky => {
+            let span = tracing::info_span!("Flaky");
+            command_flaky(repo, database, pull_request)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::ValidateConfig => {
+            let span = tracing::info_span!("Validate config");
+            command_validate_config(repo, pull_request)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::SetRollupMode(rollup) => {
+            let span = tracing::info_span!("Rollup");
+            command_set_rollup(repo, database, pull_request, author, rollup)
+                .instrument(span)
+                .await
+        }
+        BorsCommand::CreateRollup => {
+            let span = tracing::info_span!("Create rollup");
+            command_create_rollup(repo, database, pull_request, author)
+                .instrument(span)
+                .await
+        }
+    }
+}
+
 async fn reload_repos(
     ctx: Arc<BorsContext>,
     gh_client: &Octocrab,
     team_api_client: &TeamApiClient,
 ) -> anyhow::Result<()> {
-    let reloaded_repos = load_repositories(gh_client, team_api_client).await?;
+    let reloaded_repos = load_repositories(gh_client, team_api_client, &ctx.db).await?;
+    let invalid_configs = ctx
+        .db
+        .get_config_errors()
+        .await?
+        .into_iter()
+        .map(|error| error.repository)
+        .collect::<HashSet<_>>();
     let mut repositories = ctx.repositories.write().unwrap();
     for repo in repositories.values() {
         if !reloaded_repos.contains_key(repo.repository()) {
@@ -440,10 +548,19 @@ async fn reload_repos(
         let repo = match repo {
             Ok(repo) => repo,
             Err(error) => {
+                // Keep serving the repository with its previous state, if it has been loaded
+                // before.
                 tracing::error!("Failed to reload repository {name}: {error:?}");
                 continue;
             }
         };
+        // The repository was loaded with the default configuration, keep its last valid one
+        if let Some(previous) = repositories
+            .get(&name)
+            .filter(|_| invalid_configs.contains(&name))
+        {
+            repo.config.store(previous.config.load_full());
+        }
 
         if repositories.insert(name.clone(), Arc::new(repo)).is_some() {
             tracing::info!("Repository {name} was reloaded");
@@ -456,7 +573,7 @@ async fn reload_repos(
 
 /// Is this branch interesting for the bot?
 fn is_bors_observed_branch(branch: &str) -> bool {
-    branch == TRY_BRANCH_NAME
+    is_try_branch(branch) || branch == AUTO_BRANCH_NAME
 }
 
 /// Deny permission for a request.
@@ -470,15 +587,17 @@ async fn deny_request(
         "Permission denied for request command by {}",
         author.username
     );
-    repo.client
-        .post_comment(
-            pr.number,
-            Comment::new(format!(
-                "@{}: :key: Insufficient privileges: not in {} users",
-                author.username, permission_type
-            )),
-        )
-        .await
+    PERMISSION_DENIALS
+        .with_label_values(&[&permission_type.to_string()])
+        .inc();
+    repo.post_comment(
+        pr.number,
+        Comment::new(format!(
+            "@{}: :key: Insufficient privileges: not in {} users",
+            author.username, permission_type
+        )),
+    )
+    .await
 }
 
 /// Check if a user has specified permission or has been delegated.
@@ -525,7 +644,14 @@ async fn has_permission(
 
 #[cfg(test)]
 mod tests {
-    use crate::tests::mocks::{Comment, User, run_test};
+    use crate::tests::mocks::{
+        BorsBuilder, Comment, GitHubState, User, default_pr_number, default_repo_name, run_test,
+    };
+
+    const LABEL_COMMANDS_CONFIG: &str = r#"
+[label_commands]
+S-waiting-on-bors = { added = "r+", removed = "r-" }
+"#;
 
     #[sqlx::test]
     async fn ignore_bot_comment(pool: sqlx::PgPool) {
@@ -539,6 +665,100 @@ mod tests {
         .await;
     }
 
+    #[sqlx::test]
+    async fn label_approves_pr(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(LABEL_COMMANDS_CONFIG))
+            .run_test(|mut tester| async {
+                tester
+                    .label_pr(
+                        default_repo_name(),
+                        default_pr_number(),
+                        "S-waiting-on-bors",
+                        User::reviewer(),
+                    )
+                    .await?;
+                tester.expect_comments(1).await;
+                tester
+                    .default_pr()
+                    .await
+                    .expect_approved_by(&User::reviewer().name);
+
+                tester
+                    .unlabel_pr(
+                        default_repo_name(),
+                        default_pr_number(),
+                        "S-waiting-on-bors",
+                        User::reviewer(),
+                    )
+                    .await?;
+                tester.expect_comments(1).await;
+                tester.default_pr().await.expect_unapproved();
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn label_command_insufficient_permission(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(LABEL_COMMANDS_CONFIG))
+            .run_test(|mut tester| async {
+                tester
+                    .label_pr(
+                        default_repo_name(),
+                        default_pr_number(),
+                        "S-waiting-on-bors",
+                        User::unprivileged(),
+                    )
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"@unprivileged-user: :key: Insufficient privileges: not in review users"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn ignore_unmapped_label(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(LABEL_COMMANDS_CONFIG))
+            .run_test(|mut tester| async {
+                tester
+                    .label_pr(
+                        default_repo_name(),
+                        default_pr_number(),
+                        "T-compiler",
+                        User::reviewer(),
+                    )
+                    .await?;
+                // No comment should be posted
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn ignore_bot_label(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(LABEL_COMMANDS_CONFIG))
+            .run_test(|mut tester| async {
+                tester
+                    .label_pr(
+                        default_repo_name(),
+                        default_pr_number(),
+                        "S-waiting-on-bors",
+                        User::bors_bot(),
+                    )
+                    .await?;
+                // No comment should be posted
+                Ok(tester)
+            })
+            .await;
+    }
+
     #[sqlx::test]
     async fn do_not_load_pr_on_unrelated_comment(pool: sqlx::PgPool) {
         run_test(pool, |mut tester| async {
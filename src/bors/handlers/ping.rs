@@ -8,8 +8,7 @@ pub(super) async fn command_ping(
     repo: Arc<RepositoryState>,
     pr: &PullRequest,
 ) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(pr.number, Comment::new("Pong 🏓!".to_string()))
+    repo.post_comment(pr.number, Comment::new("Pong 🏓!".to_string()))
         .await?;
     Ok(())
 }
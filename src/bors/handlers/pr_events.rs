@@ -1,12 +1,20 @@
 use crate::PgDbClient;
+use crate::bors::comment::config_reloaded_comment;
 use crate::bors::event::{
     PullRequestClosed, PullRequestConvertedToDraft, PullRequestEdited, PullRequestMerged,
     PullRequestOpened, PullRequestPushed, PullRequestReadyForReview, PullRequestReopened,
     PushToBranch,
 };
+use crate::bors::handlers::approval_status::publish_approval_status;
 use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::manual_merge::{handle_manual_merge, handle_push_to_base_branch};
+use crate::bors::handlers::merge_queue::{cancel_auto_build, process_merge_queue};
+use crate::bors::handlers::refresh::reload_config;
+use crate::bors::handlers::rollup::handle_rollup_finished;
+use crate::bors::handlers::validate_config::check_config_change;
 use crate::bors::{Comment, PullRequestStatus, RepositoryState};
-use crate::database::MergeableState;
+use crate::config::CONFIG_FILE_PATH;
+use crate::database::{ApprovalStatus, MergeableState};
 use crate::github::{CommitSha, LabelTrigger, PullRequestNumber};
 use std::sync::Arc;
 
@@ -17,6 +25,10 @@ pub(super) async fn handle_pull_request_edited(
 ) -> anyhow::Result<()> {
     let pr = &payload.pull_request;
     let pr_number = pr.number;
+    let previous_mergeable_state = db
+        .get_pull_request(repo_state.repository(), pr_number)
+        .await?
+        .map(|pr| pr.mergeable_state);
     let pr_model = db
         .get_or_create_pull_request(
             repo_state.repository(),
@@ -26,6 +38,13 @@ pub(super) async fn handle_pull_request_edited(
             &pr.status,
         )
         .await?;
+    handle_mergeable_state_change(
+        &repo_state,
+        pr_number,
+        previous_mergeable_state,
+        &pr_model.mergeable_state,
+    )
+    .await?;
 
     // If the base branch has changed, unapprove the PR
     let Some(_) = payload.from_base_sha else {
@@ -37,8 +56,11 @@ pub(super) async fn handle_pull_request_edited(
     }
 
     db.unapprove(&pr_model).await?;
+    cancel_auto_build(&repo_state, &db, &pr_model).await?;
+    publish_approval_status(&repo_state, &pr.head.sha, &ApprovalStatus::NotApproved).await;
     handle_label_trigger(&repo_state, pr_number, LabelTrigger::Unapproved).await?;
-    notify_of_edited_pr(&repo_state, pr_number, &payload.pull_request.base.name).await
+    notify_of_edited_pr(&repo_state, pr_number, &payload.pull_request.base.name).await?;
+    process_merge_queue(&repo_state, &db).await
 }
 
 pub(super) async fn handle_push_to_pull_request(
@@ -48,6 +70,10 @@ pub(super) async fn handle_push_to_pull_request(
 ) -> anyhow::Result<()> {
     let pr = &payload.pull_request;
     let pr_number = pr.number;
+    let previous_mergeable_state = db
+        .get_pull_request(repo_state.repository(), pr_number)
+        .await?
+        .map(|pr| pr.mergeable_state);
     let pr_model = db
         .get_or_create_pull_request(
             repo_state.repository(),
@@ -57,14 +83,48 @@ pub(super) async fn handle_push_to_pull_request(
             &pr.status,
         )
         .await?;
+    handle_mergeable_state_change(
+        &repo_state,
+        pr_number,
+        previous_mergeable_state,
+        &pr_model.mergeable_state,
+    )
+    .await?;
+
+    if pr_model.is_approved() {
+        db.unapprove(&pr_model).await?;
+        cancel_auto_build(&repo_state, &db, &pr_model).await?;
+        publish_approval_status(&repo_state, &pr.head.sha, &ApprovalStatus::NotApproved).await;
+        handle_label_trigger(&repo_state, pr_number, LabelTrigger::Unapproved).await?;
+        handle_label_trigger(&repo_state, pr_number, LabelTrigger::PushedWhileApproved).await?;
+        notify_of_pushed_pr(&repo_state, pr_number, pr.head.sha.clone()).await?;
+        process_merge_queue(&repo_state, &db).await?;
+    }
 
-    if !pr_model.is_approved() {
-        return Ok(());
+    // The check is only advisory, so its failure should not cause the push to be handled again
+    if let Err(error) = check_config_change(&repo_state, pr_number, &pr.head.sha).await {
+        tracing::error!("Cannot check configuration change of PR {pr_number}: {error:?}");
     }
+    Ok(())
+}
 
-    db.unapprove(&pr_model).await?;
-    handle_label_trigger(&repo_state, pr_number, LabelTrigger::Unapproved).await?;
-    notify_of_pushed_pr(&repo_state, pr_number, pr.head.sha.clone()).await
+/// Applies the merge conflict label triggers when GitHub reports that the mergeability of a PR
+/// has changed.
+async fn handle_mergeable_state_change(
+    repo: &RepositoryState,
+    pr_number: PullRequestNumber,
+    previous: Option<MergeableState>,
+    current: &MergeableState,
+) -> anyhow::Result<()> {
+    let trigger = match (previous, current) {
+        (Some(MergeableState::HasConflicts), MergeableState::HasConflicts) => return Ok(()),
+        (_, MergeableState::HasConflicts) => LabelTrigger::MergeConflict,
+        (Some(MergeableState::HasConflicts), MergeableState::Mergeable) => {
+            LabelTrigger::MergeConflictResolved
+        }
+        _ => return Ok(()),
+    };
+    handle_label_trigger(repo, pr_number, trigger).await
 }
 
 pub(super) async fn handle_pull_request_opened(
@@ -83,7 +143,14 @@ pub(super) async fn handle_pull_request_opened(
         &payload.pull_request.base.name,
         pr_status,
     )
-    .await
+    .await?;
+    let pr_number = payload.pull_request.number;
+    if let Err(error) =
+        check_config_change(&repo_state, pr_number, &payload.pull_request.head.sha).await
+    {
+        tracing::error!("Cannot check configuration change of PR {pr_number}: {error:?}");
+    }
+    Ok(())
 }
 
 pub(super) async fn handle_pull_request_closed(
@@ -96,7 +163,8 @@ pub(super) async fn handle_pull_request_closed(
         payload.pull_request.number,
         PullRequestStatus::Closed,
     )
-    .await
+    .await?;
+    handle_rollup_finished(&repo_state, &db, payload.pull_request.number, false).await
 }
 
 pub(super) async fn handle_pull_request_merged(
@@ -109,7 +177,9 @@ pub(super) async fn handle_pull_request_merged(
         payload.pull_request.number,
         PullRequestStatus::Merged,
     )
-    .await
+    .await?;
+    handle_manual_merge(&repo_state, &db, &payload.pull_request).await?;
+    handle_rollup_finished(&repo_state, &db, payload.pull_request.number, true).await
 }
 
 pub(super) async fn handle_pull_request_reopened(
@@ -166,23 +236,67 @@ pub(super) async fn handle_push_to_branch(
 
     tracing::info!("Updated mergeable_state to `unknown` for {} PR(s)", rows);
 
+    let is_default_branch = payload.branch == repo_state.client.default_branch();
+    if is_default_branch
+        && payload
+            .changed_files
+            .iter()
+            .any(|file| file == CONFIG_FILE_PATH)
+    {
+        reload_pushed_config(&repo_state, &db, &payload.sha).await?;
+    }
+    // Besides the default branch, PRs can also target other branches, which are then merged
+    // through bors as well
+    if is_default_branch || rows > 0 {
+        handle_push_to_base_branch(&repo_state, &db, &payload.branch, &payload.sha).await?;
+    }
     Ok(())
 }
 
+/// Reloads the configuration after it was modified by a push to the default branch, and logs
+/// the changed settings to the configured log issue.
+async fn reload_pushed_config(
+    repo_state: &RepositoryState,
+    db: &PgDbClient,
+    sha: &CommitSha,
+) -> anyhow::Result<()> {
+    let Some(previous) = reload_config(repo_state, db, Some(sha)).await? else {
+        return Ok(());
+    };
+    tracing::info!("Reloaded configuration after push of {sha}");
+
+    let config = repo_state.config.load_full();
+    let Some(issue) = config.config_log_issue else {
+        return Ok(());
+    };
+    let changes = previous
+        .settings()
+        .into_iter()
+        .zip(config.settings())
+        .filter(|((_, previous), (_, new))| previous != new)
+        .map(|((name, previous), (_, new))| (name, previous, new))
+        .collect::<Vec<_>>();
+    repo_state
+        .post_comment(
+            PullRequestNumber(issue),
+            config_reloaded_comment(sha, &changes),
+        )
+        .await
+}
+
 async fn notify_of_edited_pr(
     repo: &RepositoryState,
     pr_number: PullRequestNumber,
     base_name: &str,
 ) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr_number,
-            Comment::new(format!(
-                r#":warning: The base branch changed to `{base_name}`, and the
+    repo.post_comment(
+        pr_number,
+        Comment::new(format!(
+            r#":warning: The base branch changed to `{base_name}`, and the
 PR will need to be re-approved."#,
-            )),
-        )
-        .await
+        )),
+    )
+    .await
 }
 
 async fn notify_of_pushed_pr(
@@ -190,16 +304,15 @@ async fn notify_of_pushed_pr(
     pr_number: PullRequestNumber,
     head_sha: CommitSha,
 ) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr_number,
-            Comment::new(format!(
-                r#":warning: A new commit `{}` was pushed to the branch, the
+    repo.post_comment(
+        pr_number,
+        Comment::new(format!(
+            r#":warning: A new commit `{}` was pushed to the branch, the
 PR will need to be re-approved."#,
-                head_sha
-            )),
-        )
-        .await
+            head_sha
+        )),
+    )
+    .await
 }
 
 #[cfg(test)]
@@ -208,7 +321,9 @@ mod tests {
     use crate::tests::mocks::default_pr_number;
     use crate::{
         database::MergeableState,
-        tests::mocks::{User, default_branch_name, default_repo_name, run_test},
+        tests::mocks::{
+            BorsBuilder, GitHubState, User, default_branch_name, default_repo_name, run_test,
+        },
     };
 
     #[sqlx::test]
@@ -355,6 +470,73 @@ mod tests {
         .await;
     }
 
+    #[sqlx::test]
+    async fn merge_conflict_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[labels]
+merge_conflict = ["+has-conflict"]
+merge_conflict_resolved = ["-has-conflict"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester
+                    .edit_pr(default_repo_name(), default_pr_number(), |pr| {
+                        pr.mergeable_state = octocrab::models::pulls::MergeableState::Dirty;
+                    })
+                    .await?;
+                tester
+                    .wait_for_default_pr(|pr| pr.mergeable_state == MergeableState::HasConflicts)
+                    .await?;
+                tester
+                    .edit_pr(default_repo_name(), default_pr_number(), |pr| {
+                        pr.mergeable_state = octocrab::models::pulls::MergeableState::Clean;
+                    })
+                    .await?;
+                tester
+                    .wait_for_default_pr(|pr| pr.mergeable_state == MergeableState::Mergeable)
+                    .await?;
+
+                let pr = tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .clone();
+                pr.check_added_labels(&["has-conflict"]);
+                pr.check_removed_labels(&["has-conflict"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn push_while_approved_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[labels]
+push_while_approved = ["+pushed"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .push_to_pr(default_repo_name(), default_pr_number())
+                    .await?;
+                tester.expect_comments(1).await;
+
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .check_added_labels(&["pushed"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
     #[sqlx::test]
     async fn open_close_and_reopen_pr(pool: sqlx::PgPool) {
         run_test(pool, |mut tester| async {
@@ -444,4 +626,67 @@ mod tests {
         })
         .await;
     }
+
+    // The log issue shares its number, and thus its comments, with the default PR.
+    const CONFIG_WITH_LOG_ISSUE: &str = "timeout = 3600\nconfig_log_issue = 1";
+
+    #[sqlx::test]
+    async fn reload_config_on_push_to_default_branch(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(CONFIG_WITH_LOG_ISSUE))
+            .run_test(|mut tester| async {
+                tester.default_repo().lock().config =
+                    "timeout = 7200\nconfig_log_issue = 1".to_string();
+                tester
+                    .push_to_branch(default_branch_name(), &["rust-bors.toml"])
+                    .await?;
+                insta::assert_snapshot!(tester.get_comment().await?, @r"
+                :gear: `rust-bors.toml` was modified in main-sha1, the configuration has been reloaded:
+                ```diff
+                - timeout = 3600s
+                + timeout = 7200s
+                ```
+                ");
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn reload_unchanged_config_on_push(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(CONFIG_WITH_LOG_ISSUE))
+            .run_test(|mut tester| async {
+                tester.default_repo().lock().config =
+                    format!("# A comment\n{CONFIG_WITH_LOG_ISSUE}");
+                tester
+                    .push_to_branch(default_branch_name(), &["rust-bors.toml"])
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":gear: `rust-bors.toml` was modified in main-sha1, the effective configuration has not changed."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn ignore_push_without_config_change(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(CONFIG_WITH_LOG_ISSUE))
+            .run_test(|mut tester| async {
+                tester.default_repo().lock().config =
+                    "timeout = 7200\nconfig_log_issue = 1".to_string();
+                tester
+                    .push_to_branch(default_branch_name(), &["src/lib.rs"])
+                    .await?;
+                tester.create_branch("beta");
+                tester.push_to_branch("beta", &["rust-bors.toml"]).await?;
+                tester.post_comment("@bors ping").await?;
+                assert_eq!(tester.get_comment().await?, "Pong 🏓!");
+                Ok(tester)
+            })
+            .await;
+    }
 }
@@ -4,10 +4,18 @@ use std::time::Duration;
 use anyhow::Context;
 use chrono::{DateTime, Utc};
 
-use crate::bors::Comment;
 use crate::bors::RepositoryState;
-use crate::bors::handlers::trybuild::cancel_build_workflows;
+use crate::bors::comment::build_timed_out_comment;
+use crate::bors::handlers::check_run::update_build_check_run;
+use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::merge_queue::process_merge_queue;
+use crate::bors::handlers::trybuild::{cancel_build_workflows, is_try_branch, process_try_queue};
+use crate::bors::handlers::workflow::evaluate_required_checks;
+use crate::config::{CONFIG_FILE_PATH, RepositoryConfig};
 use crate::database::BuildStatus;
+use crate::github::{CheckRunState, CommitSha, GithubRepoName, LabelTrigger};
+use crate::permissions::load_permissions;
+use crate::utils::metrics::{REPOSITORY_CONFIG_INVALID, TRY_BUILDS};
 use crate::{PgDbClient, TeamApiClient};
 
 pub async fn refresh_repository(
@@ -17,9 +25,13 @@ pub async fn refresh_repository(
 ) -> anyhow::Result<()> {
     let repo = repo.as_ref();
     if let (Ok(_), _, Ok(_)) = tokio::join!(
-        cancel_timed_out_builds(repo, db.as_ref()),
+        async {
+            cancel_timed_out_builds(repo, db.as_ref()).await?;
+            process_try_queue(repo, db.as_ref()).await?;
+            process_merge_queue(repo, db.as_ref()).await
+        },
         reload_permission(repo, team_api_client),
-        reload_config(repo)
+        reload_config(repo, db.as_ref(), None)
     ) {
         Ok(())
     } else {
@@ -28,18 +40,61 @@ pub async fn refresh_repository(
     }
 }
 
+/// How long events that will not be handled anymore are kept in the event queue, so that they
+/// can still be inspected and replayed.
+const EVENT_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Deletes events that are done, ignored or dead and older than the retention period.
+pub async fn prune_event_queue(db: &PgDbClient) -> anyhow::Result<()> {
+    let received_before = Utc::now() - chrono::Duration::from_std(EVENT_RETENTION)?;
+    let deleted = db.delete_finished_events(received_before).await?;
+    tracing::info!("Deleted {deleted} finished event(s) from the event queue");
+    Ok(())
+}
+
 async fn cancel_timed_out_builds(repo: &RepositoryState, db: &PgDbClient) -> anyhow::Result<()> {
     let running_builds = db.get_running_builds(repo.repository()).await?;
     tracing::info!("Found {} running build(s)", running_builds.len());
 
     let timeout = repo.config.load().timeout;
     for build in running_builds {
-        if elapsed_time(build.created_at) >= timeout {
+        if elapsed_time(build.started_at()) >= timeout {
             tracing::info!("Cancelling build {}", build.commit_sha);
 
-            db.update_build_status(&build, BuildStatus::Cancelled)
-                .await?;
-            if let Some(pr) = db.find_pr_by_build(&build).await? {
+            let pr = db.find_pr_by_build(&build).await?;
+            // A build whose required checks have not been reported in time has failed,
+            // rather than just timed out.
+            let missing_checks = match &pr {
+                Some(pr) => match evaluate_required_checks(
+                    repo,
+                    &pr.base_branch,
+                    &CommitSha(build.commit_sha.clone()),
+                )
+                .await
+                {
+                    Ok(required_checks) => required_checks.missing,
+                    Err(error) => {
+                        tracing::error!(
+                            "Could not evaluate required checks for SHA {}: {error:?}",
+                            build.commit_sha
+                        );
+                        vec![]
+                    }
+                },
+                None => vec![],
+            };
+            let status = if missing_checks.is_empty() {
+                BuildStatus::Cancelled
+            } else {
+                BuildStatus::Failure
+            };
+
+            db.update_build_status(&build, status).await?;
+            update_build_check_run(repo, db, &build, CheckRunState::TimedOut).await;
+            if is_try_branch(&build.branch) {
+                TRY_BUILDS.with_label_values(&["timed_out"]).inc();
+            }
+            if let Some(pr) = pr {
                 if let Err(error) = cancel_build_workflows(&repo.client, db, &build).await {
                     tracing::error!(
                         "Could not cancel workflows for SHA {}: {error:?}",
@@ -48,12 +103,19 @@ async fn cancel_timed_out_builds(repo: &RepositoryState, db: &PgDbClient) -> any
                 }
 
                 if let Err(error) = repo
-                    .client
-                    .post_comment(pr.number, Comment::new(":boom: Test timed out".to_string()))
+                    .post_comment(
+                        pr.number,
+                        build_timed_out_comment(&missing_checks, elapsed_time(build.started_at())),
+                    )
                     .await
                 {
                     tracing::error!("Could not send comment to PR {}: {error:?}", pr.number);
                 }
+                if let Err(error) =
+                    handle_label_trigger(repo, pr.number, LabelTrigger::BuildTimedOut).await
+                {
+                    tracing::error!("Could not update labels of PR {}: {error:?}", pr.number);
+                }
             } else {
                 tracing::warn!("No PR found for build {}", build.commit_sha);
             }
@@ -66,22 +128,97 @@ async fn reload_permission(
     repo: &RepositoryState,
     team_api_client: &TeamApiClient,
 ) -> anyhow::Result<()> {
-    let permissions = team_api_client
-        .load_permissions(repo.repository())
-        .await
-        .with_context(|| {
-            format!(
-                "Could not load permissions for repository {}",
-                repo.repository()
-            )
-        })?;
+    let permissions = load_permissions(
+        &repo.client,
+        &repo.config.load().permissions,
+        team_api_client,
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "Could not load permissions for repository {}",
+            repo.repository()
+        )
+    })?;
     repo.permissions.store(Arc::new(permissions));
     Ok(())
 }
 
-async fn reload_config(repo: &RepositoryState) -> anyhow::Result<()> {
-    let config = repo.client.load_config().await?;
-    repo.config.store(Arc::new(config));
+/// Reloads the configuration of the repository from the given commit, or from its main branch.
+/// If the configuration file is invalid, the repository keeps using its previous configuration,
+/// and the error is reported in an issue once per revision of the file.
+///
+/// Returns the previous configuration if the configuration was replaced.
+pub(super) async fn reload_config(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    sha: Option<&CommitSha>,
+) -> anyhow::Result<Option<Arc<RepositoryConfig>>> {
+    let file = repo.client.get_config_file(sha).await?;
+    match toml::from_str::<RepositoryConfig>(&file.content) {
+        Ok(config) => {
+            let previous = repo.config.swap(Arc::new(config));
+            report_config_valid(repo.repository(), db).await?;
+            Ok(Some(previous))
+        }
+        Err(error) => {
+            let error = error.to_string();
+            tracing::error!(
+                "Could not reload configuration of {} (revision {}), keeping the previous configuration: {error}",
+                repo.repository(),
+                file.sha
+            );
+            report_config_error(repo, db, &file.sha, &error, "the previous configuration").await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Marks the configuration of the repository as valid, clearing a previously reported error.
+pub(crate) async fn report_config_valid(
+    name: &GithubRepoName,
+    db: &PgDbClient,
+) -> anyhow::Result<()> {
+    REPOSITORY_CONFIG_INVALID
+        .with_label_values(&[&name.to_string()])
+        .set(0);
+    if db.get_config_error(name).await?.is_some() {
+        tracing::info!("Configuration of {name} is valid again");
+        db.clear_config_error(name).await?;
+    }
+    Ok(())
+}
+
+/// Marks the configuration of the repository as invalid and reports the error in an issue,
+/// once per revision of the configuration file. `fallback` describes the configuration that is
+/// used instead of the invalid one.
+pub(crate) async fn report_config_error(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    revision: &str,
+    error: &str,
+    fallback: &str,
+) -> anyhow::Result<()> {
+    let name = repo.repository();
+    REPOSITORY_CONFIG_INVALID
+        .with_label_values(&[&name.to_string()])
+        .set(1);
+    let previous_error = db.get_config_error(name).await?;
+    if previous_error.is_none_or(|previous| previous.revision != revision) {
+        repo.client
+            .create_issue(
+                &format!("Invalid `{CONFIG_FILE_PATH}` configuration"),
+                &format!(
+                    r#":x: bors could not load revision `{revision}` of `{CONFIG_FILE_PATH}`, {fallback} will be used until the file is fixed:
+```
+{}
+```"#,
+                    error.trim_end()
+                ),
+            )
+            .await?;
+        db.set_config_error(name, revision, error).await?;
+    }
     Ok(())
 }
 
@@ -100,7 +237,7 @@ fn now() -> DateTime<Utc> {
     MOCK_TIME.with(|time| time.borrow_mut().unwrap_or_else(Utc::now))
 }
 
-fn elapsed_time(date: DateTime<Utc>) -> Duration {
+pub(super) fn elapsed_time(date: DateTime<Utc>) -> Duration {
     let time: DateTime<Utc> = now();
     (time - date).to_std().unwrap_or(Duration::ZERO)
 }
@@ -207,6 +344,116 @@ timeout = 3600
         gh.check_cancelled_workflows(default_repo_name(), &[1]);
     }
 
+    #[sqlx::test]
+    async fn refresh_fail_build_with_missing_required_checks(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+timeout = 3600
+required_checks = ["build", "test"]
+"#,
+            ))
+            .run_test(|mut tester| async move {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_success(tester.try_branch()).await?;
+                with_mocked_time(Duration::from_secs(4000), async {
+                    tester.refresh().await;
+                })
+                .await;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":boom: Test timed out, required checks are missing: `build`, `test`"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn refresh_invalid_config_reported_once(pool: sqlx::PgPool) {
+        run_test(pool, |tester| async move {
+            tester.default_repo().lock().config = "timeout = \"foo\"".to_string();
+            tester.refresh().await;
+            tester.refresh().await;
+            {
+                let repo = tester.default_repo();
+                let repo = repo.lock();
+                assert_eq!(repo.created_issues.len(), 1);
+                assert_eq!(
+                    repo.created_issues[0].title,
+                    "Invalid `rust-bors.toml` configuration"
+                );
+            }
+
+            // A new revision of the file is reported again
+            tester.default_repo().lock().config = "timeout = \"bar\"".to_string();
+            tester.refresh().await;
+            assert_eq!(tester.default_repo().lock().created_issues.len(), 2);
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn refresh_invalid_config_in_health(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async move {
+            let config = tester.default_repo().lock().config.clone();
+            tester.default_repo().lock().config = "try_branches = 0".to_string();
+            tester.refresh().await;
+            assert!(
+                tester
+                    .get_page("/health")
+                    .await?
+                    .starts_with(&format!("Invalid configuration of {}", default_repo_name()))
+            );
+
+            tester.default_repo().lock().config = config;
+            tester.refresh().await;
+            assert_eq!(tester.get_page("/health").await?, "");
+            assert!(
+                tester
+                    .db()
+                    .get_config_error(&default_repo_name())
+                    .await?
+                    .is_none()
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn invalid_config_at_startup(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config("try_branches = 0"))
+            .run_test(|mut tester| async move {
+                assert!(
+                    tester
+                        .get_page("/health")
+                        .await?
+                        .starts_with(&format!("Invalid configuration of {}", default_repo_name()))
+                );
+                assert!(
+                    tester
+                        .db()
+                        .get_config_error(&default_repo_name())
+                        .await?
+                        .is_some()
+                );
+
+                // The same revision is not reported again by a refresh
+                tester.refresh().await;
+                assert_eq!(tester.default_repo().lock().created_issues.len(), 1);
+
+                // The repository works with the default configuration
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                Ok(tester)
+            })
+            .await;
+    }
+
     async fn with_mocked_time<Fut: Future<Output = ()>>(in_future: Duration, future: Fut) {
         // It is important to use this function only with a single threaded runtime,
         // otherwise the `MOCK_TIME` variable might get mixed up between different threads.
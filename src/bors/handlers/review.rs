@@ -5,10 +5,13 @@ use crate::bors::Comment;
 use crate::bors::RepositoryState;
 use crate::bors::command::Approver;
 use crate::bors::command::RollupMode;
+use crate::bors::handlers::approval_status::publish_approval_status;
 use crate::bors::handlers::deny_request;
 use crate::bors::handlers::has_permission;
 use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::merge_queue::{cancel_auto_build, process_merge_queue};
 use crate::database::ApprovalInfo;
+use crate::database::ApprovalStatus;
 use crate::database::DelegatedPermission;
 use crate::database::TreeState;
 use crate::github::GithubUser;
@@ -50,10 +53,25 @@ pub(super) async fn command_approve(
         )
         .await?;
 
-    db.approve(&pr_model, approval_info, priority, rollup)
+    // A re-approval detaches the running auto build from the PR, so it has to be cancelled first
+    cancel_auto_build(&repo_state, &db, &pr_model).await?;
+    db.approve(&pr_model, approval_info.clone(), priority, rollup)
         .await?;
+    publish_approval_status(
+        &repo_state,
+        &pr.head.sha,
+        &ApprovalStatus::Approved(approval_info),
+    )
+    .await;
     handle_label_trigger(&repo_state, pr.number, LabelTrigger::Approved).await?;
-    notify_of_approval(&repo_state, pr, approver.as_str()).await
+    if priority.is_some() {
+        handle_label_trigger(&repo_state, pr.number, LabelTrigger::PriorityChanged).await?;
+    }
+    if rollup.is_some() {
+        handle_label_trigger(&repo_state, pr.number, LabelTrigger::RollupModeChanged).await?;
+    }
+    notify_of_approval(&repo_state, pr, approver.as_str()).await?;
+    process_merge_queue(&repo_state, &db).await
 }
 
 /// Unapprove a pull request.
@@ -80,8 +98,11 @@ pub(super) async fn command_unapprove(
         .await?;
 
     db.unapprove(&pr_model).await?;
+    cancel_auto_build(&repo_state, &db, &pr_model).await?;
+    publish_approval_status(&repo_state, &pr.head.sha, &ApprovalStatus::NotApproved).await;
     handle_label_trigger(&repo_state, pr.number, LabelTrigger::Unapproved).await?;
-    notify_of_unapproval(&repo_state, pr).await
+    notify_of_unapproval(&repo_state, pr).await?;
+    process_merge_queue(&repo_state, &db).await
 }
 
 /// Set the priority of a pull request.
@@ -107,7 +128,8 @@ pub(super) async fn command_set_priority(
         )
         .await?;
 
-    db.set_priority(&pr_model, priority).await
+    db.set_priority(&pr_model, priority).await?;
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::PriorityChanged).await
 }
 
 /// Delegate permissions of a pull request to its author.
@@ -139,6 +161,7 @@ pub(super) async fn command_delegate(
         .await?;
 
     db.delegate(&pr_model, delegated_permission).await?;
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::Delegated).await?;
     notify_of_delegation(&repo_state, pr, &pr.author.username, delegated_permission).await
 }
 
@@ -164,7 +187,8 @@ pub(super) async fn command_undelegate(
         )
         .await?;
 
-    db.undelegate(&pr_model).await
+    db.undelegate(&pr_model).await?;
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::Undelegated).await
 }
 
 /// Set the rollup of a pull request.
@@ -190,7 +214,8 @@ pub(super) async fn command_set_rollup(
         )
         .await?;
 
-    db.set_rollup(&pr_model, rollup).await
+    db.set_rollup(&pr_model, rollup).await?;
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::RollupModeChanged).await
 }
 
 pub(super) async fn command_close_tree(
@@ -213,6 +238,7 @@ pub(super) async fn command_close_tree(
         },
     )
     .await?;
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::TreeClosed).await?;
     notify_of_tree_closed(&repo_state, pr, priority).await
 }
 
@@ -229,7 +255,9 @@ pub(super) async fn command_open_tree(
 
     db.upsert_repository(repo_state.repository(), TreeState::Open)
         .await?;
-    notify_of_tree_open(&repo_state, pr).await
+    handle_label_trigger(&repo_state, pr.number, LabelTrigger::TreeOpened).await?;
+    notify_of_tree_open(&repo_state, pr).await?;
+    process_merge_queue(&repo_state, &db).await
 }
 
 fn sufficient_approve_permission(repo: Arc<RepositoryState>, author: &GithubUser) -> bool {
@@ -249,33 +277,30 @@ async fn notify_of_tree_closed(
     pr: &PullRequest,
     priority: u32,
 ) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr.number,
-            Comment::new(format!(
-                "Tree closed for PRs with priority less than {}",
-                priority
-            )),
-        )
-        .await
+    repo.post_comment(
+        pr.number,
+        Comment::new(format!(
+            "Tree closed for PRs with priority less than {}",
+            priority
+        )),
+    )
+    .await
 }
 
 async fn notify_of_tree_open(repo: &RepositoryState, pr: &PullRequest) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr.number,
-            Comment::new("Tree is now open for merging".to_string()),
-        )
-        .await
+    repo.post_comment(
+        pr.number,
+        Comment::new("Tree is now open for merging".to_string()),
+    )
+    .await
 }
 
 async fn notify_of_unapproval(repo: &RepositoryState, pr: &PullRequest) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr.number,
-            Comment::new(format!("Commit {} has been unapproved", pr.head.sha)),
-        )
-        .await
+    repo.post_comment(
+        pr.number,
+        Comment::new(format!("Commit {} has been unapproved", pr.head.sha)),
+    )
+    .await
 }
 
 async fn notify_of_approval(
@@ -283,15 +308,14 @@ async fn notify_of_approval(
     pr: &PullRequest,
     approver: &str,
 ) -> anyhow::Result<()> {
-    repo.client
-        .post_comment(
-            pr.number,
-            Comment::new(format!(
-                "Commit {} has been approved by `{}`",
-                pr.head.sha, approver
-            )),
-        )
-        .await
+    repo.post_comment(
+        pr.number,
+        Comment::new(format!(
+            "Commit {} has been approved by `{}`",
+            pr.head.sha, approver
+        )),
+    )
+    .await
 }
 
 async fn notify_of_delegation(
@@ -308,9 +332,7 @@ async fn notify_of_delegation(
         DelegatedPermission::Review => format!("@{} can now approve this pull request", delegatee),
     };
 
-    repo.client
-        .post_comment(pr.number, Comment::new(message))
-        .await
+    repo.post_comment(pr.number, Comment::new(message)).await
 }
 
 #[cfg(test)]
@@ -319,7 +341,7 @@ mod tests {
     use crate::{
         bors::{
             RollupMode,
-            handlers::{TRY_BRANCH_NAME, trybuild::TRY_MERGE_BRANCH_NAME},
+            handlers::trybuild::{TRY_BRANCH_NAME, TRY_MERGE_BRANCH_NAME},
         },
         tests::mocks::{
             BorsBuilder, Comment, GitHubState, Permissions, User, default_pr_number,
@@ -562,6 +584,68 @@ mod tests {
             .await;
     }
 
+    #[sqlx::test]
+    async fn approve_with_priority_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[labels]
+approve = ["+approved"]
+priority = ["+priority-changed"]
+rollup = ["+rollup-changed"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ p=5").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .check_added_labels(&["approved", "priority-changed"]);
+
+                tester.post_comment("@bors rollup=never").await?;
+                tester
+                    .wait_for_default_pr(|pr| pr.rollup == Some(RollupMode::Never))
+                    .await?;
+                tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .check_added_labels(&["approved", "priority-changed", "rollup-changed"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn tree_closed_modify_labels(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[labels]
+tree_closed = ["+tree-closed"]
+tree_open = ["-tree-closed"]
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors treeclosed=5").await?;
+                tester.expect_comments(1).await;
+                tester.post_comment("@bors treeopen").await?;
+                tester.expect_comments(1).await;
+
+                let pr = tester
+                    .default_repo()
+                    .lock()
+                    .get_pr(default_pr_number())
+                    .clone();
+                pr.check_added_labels(&["tree-closed"]);
+                pr.check_removed_labels(&["tree-closed"]);
+                Ok(tester)
+            })
+            .await;
+    }
+
     #[sqlx::test]
     async fn delegate_author(pool: sqlx::PgPool) {
         BorsBuilder::new(pool)
@@ -0,0 +1,395 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+
+use crate::PgDbClient;
+use crate::bors::comment::{
+    no_rollup_candidates_comment, rollup_created_comment, rollup_failed_comment,
+    rollup_in_progress_comment, rollup_merged_comment,
+};
+use crate::bors::handlers::merge_queue::sort_merge_queue;
+use crate::bors::handlers::{deny_request, has_permission};
+use crate::bors::{PullRequestStatus, RepositoryState, RollupMode};
+use crate::database::{BuildStatus, MergeableState, PullRequestModel};
+use crate::github::{GithubUser, MergeError, PullRequest, PullRequestNumber};
+use crate::permissions::PermissionType;
+use crate::utils::text::suppress_github_mentions;
+
+// This branch contains the merged commits of all PRs included in the current rollup.
+// It serves as the head branch of the rollup PR.
+pub(super) const ROLLUP_BRANCH_NAME: &str = "automation/bors/rollup";
+
+/// Creates a rollup PR that contains all approved PRs marked with `rollup=always` or
+/// `rollup=maybe` that target the same base branch as `pr`.
+///
+/// PRs that cannot be merged into the rollup branch are reported back and left out.
+pub(super) async fn command_create_rollup(
+    repo: Arc<RepositoryState>,
+    db: Arc<PgDbClient>,
+    pr: &PullRequest,
+    author: &GithubUser,
+) -> anyhow::Result<()> {
+    let repo = repo.as_ref();
+    if !has_permission(repo, author, pr, &db, PermissionType::Review).await? {
+        deny_request(repo, pr, author, PermissionType::Review).await?;
+        return Ok(());
+    }
+
+    let mut prs = db.get_open_pull_requests(repo.repository()).await?;
+    if let Some(rollup) = prs
+        .iter()
+        .find(|rollup| prs.iter().any(|pr| pr.rollup_pr_id == Some(rollup.id)))
+    {
+        return repo
+            .post_comment(pr.number, rollup_in_progress_comment(rollup.number))
+            .await;
+    }
+
+    sort_merge_queue(&mut prs);
+    let candidates: Vec<PullRequestModel> = prs
+        .into_iter()
+        .filter(|candidate| is_rollup_candidate(candidate, &pr.base.name))
+        .collect();
+    if candidates.is_empty() {
+        return repo
+            .post_comment(pr.number, no_rollup_candidates_comment())
+            .await;
+    }
+
+    let base_sha = repo
+        .client
+        .get_branch_sha(&pr.base.name)
+        .await
+        .map_err(|error| anyhow!("Cannot get SHA for branch {}: {error:?}", pr.base.name))?;
+    repo.client
+        .set_branch_to_sha(ROLLUP_BRANCH_NAME, &base_sha)
+        .await
+        .map_err(|error| anyhow!("Cannot set rollup branch to {base_sha}: {error:?}"))?;
+
+    let mut included = vec![];
+    let mut included_prs = vec![];
+    let mut failed_prs = vec![];
+    for candidate in candidates {
+        let candidate_pr = repo.client.get_pull_request(candidate.number).await?;
+        let Some(approver) = candidate.approver() else {
+            continue;
+        };
+        if candidate.approved_sha() != Some(candidate_pr.head.sha.as_ref()) {
+            tracing::warn!(
+                "PR {} was approved at a different commit than {}, skipping",
+                candidate.number,
+                candidate_pr.head.sha
+            );
+            continue;
+        }
+
+        match repo
+            .client
+            .merge_branches(
+                ROLLUP_BRANCH_NAME,
+                &candidate_pr.head.sha,
+                &rollup_merge_commit_message(&candidate_pr, approver),
+            )
+            .await
+        {
+            Ok(_) => {
+                included.push(candidate);
+                included_prs.push(candidate_pr);
+            }
+            Err(MergeError::Conflict) => {
+                tracing::warn!("PR {} conflicts with the rollup", candidate.number);
+                failed_prs.push(candidate_pr);
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let failed: Vec<PullRequestNumber> = failed_prs.iter().map(|pr| pr.number).collect();
+    if included.is_empty() {
+        tracing::warn!("No PRs could be merged into the rollup");
+        return repo
+            .post_comment(pr.number, no_rollup_candidates_comment())
+            .await;
+    }
+
+    let rollup_pr = repo
+        .client
+        .create_pull_request(
+            &format!("Rollup of {} pull requests", included.len()),
+            ROLLUP_BRANCH_NAME,
+            &pr.base.name,
+            &rollup_description(&included_prs, &failed_prs),
+        )
+        .await?;
+    let rollup_model = db
+        .get_or_create_pull_request(
+            repo.repository(),
+            rollup_pr.number,
+            &rollup_pr.base.name,
+            rollup_pr.mergeable_state.clone().into(),
+            &rollup_pr.status,
+        )
+        .await?;
+    db.set_rollup_pr(&included, &rollup_model).await?;
+
+    tracing::info!("Created rollup PR {}", rollup_pr.number);
+    let included: Vec<PullRequestNumber> = included.iter().map(|pr| pr.number).collect();
+    repo.post_comment(
+        pr.number,
+        rollup_created_comment(rollup_pr.number, &included, &failed),
+    )
+    .await
+}
+
+/// Updates the PRs included in the rollup PR with the given number after the rollup was merged,
+/// closed or its auto build has failed.
+pub(super) async fn handle_rollup_finished(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    rollup_number: PullRequestNumber,
+    merged: bool,
+) -> anyhow::Result<()> {
+    let Some(rollup) = db
+        .get_pull_request(repo.repository(), rollup_number)
+        .await?
+    else {
+        return Ok(());
+    };
+    let included: Vec<PullRequestModel> = db
+        .get_open_pull_requests(repo.repository())
+        .await?
+        .into_iter()
+        .filter(|pr| pr.rollup_pr_id == Some(rollup.id))
+        .collect();
+    if included.is_empty() {
+        return Ok(());
+    }
+
+    if merged {
+        tracing::info!("Rollup {rollup_number} was merged");
+        for pr in included {
+            db.set_pr_status(repo.repository(), pr.number, PullRequestStatus::Merged)
+                .await?;
+            repo.post_comment(pr.number, rollup_merged_comment(rollup_number))
+                .await?;
+        }
+    } else {
+        tracing::info!("Rollup {rollup_number} has failed");
+        db.clear_rollup_pr(&rollup).await?;
+        for pr in included {
+            repo.post_comment(pr.number, rollup_failed_comment(rollup_number))
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn is_rollup_candidate(pr: &PullRequestModel, base_branch: &str) -> bool {
+    let has_running_build = pr
+        .auto_build
+        .as_ref()
+        .is_some_and(|build| build.status == BuildStatus::Pending);
+
+    pr.pr_status == PullRequestStatus::Open
+        && pr.is_approved()
+        && matches!(pr.rollup, Some(RollupMode::Always | RollupMode::Maybe))
+        && pr.rollup_pr_id.is_none()
+        && pr.mergeable_state != MergeableState::HasConflicts
+        && pr.base_branch == base_branch
+        && !has_running_build
+}
+
+fn rollup_merge_commit_message(pr: &PullRequest, reviewer: &str) -> String {
+    format!(
+        r#"Rollup merge of #{pr_number} - {pr_label}, r={reviewer}
+
+{pr_title}"#,
+        pr_number = pr.number,
+        pr_label = pr.head_label,
+        pr_title = pr.title,
+    )
+}
+
+fn rollup_description(included: &[PullRequest], failed: &[PullRequest]) -> String {
+    let format_prs = |prs: &[PullRequest]| {
+        prs.iter()
+            .map(|pr| {
+                format!(
+                    " - #{} ({})",
+                    pr.number,
+                    suppress_github_mentions(&pr.title)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut description = format!("Successful merges:\n\n{}\n", format_prs(included));
+    if !failed.is_empty() {
+        description.push_str(&format!("\nFailed merges:\n\n{}\n", format_prs(failed)));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bors::handlers::rollup::ROLLUP_BRANCH_NAME;
+    use crate::tests::mocks::{
+        BorsBuilder, Comment, GitHubState, PullRequest, Repo, User, default_pr_number,
+        default_repo_name, run_test,
+    };
+
+    fn state_with_second_pr() -> GitHubState {
+        let repo = Repo::default().with_pr(PullRequest::new(
+            default_repo_name(),
+            2,
+            User::default_pr_author(),
+            false,
+        ));
+        GitHubState::default().with_repo(repo)
+    }
+
+    #[sqlx::test]
+    async fn create_rollup_no_candidates(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            tester.post_comment("@bors rollup create").await?;
+            insta::assert_snapshot!(
+                tester.get_comment().await?,
+                @":exclamation: There are no approved pull requests that could be included in a rollup."
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn create_rollup(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(state_with_second_pr())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ rollup").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(
+                        default_repo_name(),
+                        2,
+                        "@bors r+ rollup=maybe",
+                    ))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+
+                tester.post_comment("@bors rollup create").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":package: Created rollup #3 of 2 pull request(s): #2, #1"
+                );
+
+                let rollup = tester.pr_db(default_repo_name(), 3).await?.unwrap();
+                let pr = tester
+                    .pr_db(default_repo_name(), default_pr_number())
+                    .await?
+                    .unwrap();
+                assert_eq!(pr.rollup_pr_id, Some(rollup.id));
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            ROLLUP_BRANCH_NAME,
+            &[
+                "main-sha1",
+                "merge-main-sha1-pr-2-sha-0",
+                "merge-merge-main-sha1-pr-2-sha-0-pr-1-sha-1",
+            ],
+        );
+    }
+
+    #[sqlx::test]
+    async fn create_rollup_ignores_unmarked_prs(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_second_pr())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ rollup").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors r+"))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+
+                tester.post_comment("@bors rollup create").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":package: Created rollup #3 of 1 pull request(s): #1"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn rollup_merged_updates_included_prs(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_second_pr())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ rollup").await?;
+                tester.expect_comments(1).await;
+                tester.post_comment("@bors rollup create").await?;
+                tester.expect_comments(1).await;
+
+                tester.merge_pr(default_repo_name(), 3).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":sunny: This pull request was merged as a part of rollup #3."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn rollup_closed_removes_included_prs(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_second_pr())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ rollup").await?;
+                tester.expect_comments(1).await;
+                tester.post_comment("@bors rollup create").await?;
+                tester.expect_comments(1).await;
+
+                tester.close_pr(default_repo_name(), 3).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":broken_heart: Rollup #3 was not merged. This pull request was removed from the rollup."
+                );
+                let pr = tester
+                    .pr_db(default_repo_name(), default_pr_number())
+                    .await?
+                    .unwrap();
+                assert_eq!(pr.rollup_pr_id, None);
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn create_rollup_while_rollup_open(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_second_pr())
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+ rollup").await?;
+                tester.expect_comments(1).await;
+                tester.post_comment("@bors rollup create").await?;
+                tester.expect_comments(1).await;
+
+                tester.post_comment("@bors rollup create").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":exclamation: Rollup #3 is still open. Merge or close it before creating a new rollup."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+}
@@ -1,25 +1,42 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
 use std::sync::Arc;
 
 use anyhow::{Context, anyhow};
 
 use crate::PgDbClient;
 use crate::bors::Comment;
-use crate::bors::RepositoryState;
 use crate::bors::command::Parent;
+use crate::bors::comment::auto_build_requeued_comment;
 use crate::bors::comment::cant_find_last_parent_comment;
+use crate::bors::comment::no_failed_build_comment;
+use crate::bors::comment::no_failed_workflows_comment;
 use crate::bors::comment::no_try_build_in_progress_comment;
+use crate::bors::comment::queued_try_build_cancelled_comment;
+use crate::bors::comment::queued_try_build_failed_comment;
+use crate::bors::comment::retrying_build_comment;
 use crate::bors::comment::try_build_cancelled_comment;
 use crate::bors::comment::try_build_in_progress_comment;
+use crate::bors::comment::try_build_queued_comment;
 use crate::bors::comment::unclean_try_build_cancelled_comment;
+use crate::bors::handlers::check_run::{start_build_check_run, update_build_check_run};
 use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::merge_queue::AUTO_BRANCH_NAME;
+use crate::bors::{PullRequestStatus, RepositoryState};
+use crate::config::{MergeStrategy, RepositoryConfig, TemplateKind, TryQueueOrder};
 use crate::database::RunId;
-use crate::database::{BuildModel, BuildStatus, PullRequestModel};
+use crate::database::{
+    BuildModel, BuildStatus, PullRequestModel, TryRequestModel, WorkflowStatus, WorkflowType,
+};
 use crate::github::GithubRepoName;
 use crate::github::api::client::GithubRepositoryClient;
 use crate::github::{
-    CommitSha, GithubUser, LabelTrigger, MergeError, PullRequest, PullRequestNumber,
+    CheckRunState, CommitSha, GithubUser, LabelTrigger, MergeError, PullRequest, PullRequestCommit,
+    PullRequestNumber,
 };
 use crate::permissions::PermissionType;
+use crate::utils::metrics::TRY_BUILDS;
+use crate::utils::template::Template;
 use crate::utils::text::suppress_github_mentions;
 
 use super::deny_request;
@@ -32,8 +49,35 @@ use super::has_permission;
 pub(super) const TRY_MERGE_BRANCH_NAME: &str = "automation/bors/try-merge";
 
 // This branch should run CI checks.
+// If a repository uses more try branches, they are named `automation/bors/try-<n>`.
 pub(super) const TRY_BRANCH_NAME: &str = "automation/bors/try";
 
+/// How many times bors attempts to start a queued try build before the request is dropped.
+const MAX_TRY_REQUEST_ATTEMPTS: i32 = 3;
+
+/// Returns the names of the try branches of a repository that uses `count` try branches.
+/// A single try branch keeps its original name, so that existing CI configurations keep working.
+pub(super) fn try_branch_names(count: NonZeroU32) -> Vec<String> {
+    if count.get() == 1 {
+        vec![TRY_BRANCH_NAME.to_string()]
+    } else {
+        (1..=count.get())
+            .map(|index| format!("{TRY_BRANCH_NAME}-{index}"))
+            .collect()
+    }
+}
+
+/// Is the branch a try branch, regardless of how many try branches the repository uses?
+pub(super) fn is_try_branch(branch: &str) -> bool {
+    match branch.strip_prefix(TRY_BRANCH_NAME) {
+        Some("") => true,
+        Some(suffix) => suffix
+            .strip_prefix('-')
+            .is_some_and(|index| index.parse::<u32>().is_ok()),
+        None => false,
+    }
+}
+
 /// Performs a so-called try build - merges the PR branch into a special branch designed
 /// for running CI checks.
 ///
@@ -69,19 +113,50 @@ pub(super) async fn command_try_build(
     if let Some(build) = &pr_model.try_build {
         if build.status == BuildStatus::Pending {
             tracing::warn!("Try build already in progress");
-            repo.client
-                .post_comment(pr.number, try_build_in_progress_comment())
+            repo.post_comment(pr.number, try_build_in_progress_comment())
                 .await?;
             return Ok(());
         }
     } else if Some(Parent::Last) == parent {
         tracing::warn!("try build was requested with parent=last but no previous build was found");
-        repo.client
-            .post_comment(pr.number, cant_find_last_parent_comment())
+        repo.post_comment(pr.number, cant_find_last_parent_comment())
             .await?;
         return Ok(());
     }
 
+    let Some(try_branch) = find_free_try_branch(repo, &db).await? else {
+        tracing::info!("All try branches are busy, queueing the try build");
+        db.enqueue_try_request(
+            &pr_model,
+            &author.username,
+            parent.as_ref().map(parent_to_string).as_deref(),
+            &jobs,
+        )
+        .await?;
+        let position = load_try_queue(repo, &db)
+            .await?
+            .iter()
+            .position(|request| request.pr_number == pr.number)
+            .map(|index| index + 1)
+            .ok_or_else(|| anyhow!("Queued try request of PR {} not found", pr.number))?;
+        repo.post_comment(pr.number, try_build_queued_comment(position))
+            .await?;
+        return Ok(());
+    };
+
+    start_try_build(repo, &db, pr, pr_model, &try_branch, parent, jobs).await
+}
+
+/// Merges the PR into the given try branch and starts a try build on it.
+async fn start_try_build(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+    pr: &PullRequest,
+    pr_model: PullRequestModel,
+    try_branch: &str,
+    parent: Option<Parent>,
+    jobs: Vec<String>,
+) -> anyhow::Result<()> {
     let base_sha = match get_base_sha(&pr_model, parent) {
         Some(base_sha) => base_sha,
         None => repo
@@ -93,88 +168,375 @@ pub(super) async fn command_try_build(
 
     match attempt_merge(
         &repo.client,
-        &pr.head.sha,
+        &repo.config.load(),
+        TRY_MERGE_BRANCH_NAME,
+        pr,
         &base_sha,
-        &auto_merge_commit_message(pr, repo.client.repository(), "<try>", jobs),
+        "<try>",
+        jobs,
     )
     .await?
     {
         MergeResult::Success(merge_sha) => {
             // If the merge was succesful, run CI with merged commit
-            run_try_build(&repo.client, &db, pr_model, merge_sha.clone(), base_sha).await?;
+            let build_id = run_try_build(
+                &repo.client,
+                db,
+                &pr_model,
+                try_branch,
+                merge_sha.clone(),
+                base_sha,
+            )
+            .await?;
+            // The build supersedes any try build of the PR that was waiting in the queue.
+            // The request is only removed once the build has started, so that it is not lost
+            // if starting the build fails.
+            db.delete_try_request_for_pr(&pr_model).await?;
+            TRY_BUILDS.with_label_values(&["started"]).inc();
+            start_build_check_run(repo, db, build_id, "Try", &pr.head.sha, &merge_sha).await;
 
             handle_label_trigger(repo, pr.number, LabelTrigger::TryBuildStarted).await?;
 
-            repo.client
-                .post_comment(pr.number, trying_build_comment(&pr.head.sha, &merge_sha))
+            repo.post_comment(pr.number, trying_build_comment(&pr.head.sha, &merge_sha))
                 .await
         }
         MergeResult::Conflict => {
-            repo.client
-                .post_comment(pr.number, merge_conflict_comment(&pr.head.name))
+            db.delete_try_request_for_pr(&pr_model).await?;
+            repo.post_comment(pr.number, merge_conflict_comment(&pr.head.name))
                 .await
         }
     }
 }
 
-async fn attempt_merge(
+/// Returns a try branch that is not used by any running build, if there is one.
+async fn find_free_try_branch(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+) -> anyhow::Result<Option<String>> {
+    let running_builds = db.get_running_builds(repo.repository()).await?;
+    let try_branches = try_branch_names(repo.config.load().try_branches);
+    Ok(try_branches
+        .into_iter()
+        .find(|branch| !running_builds.iter().any(|build| &build.branch == branch)))
+}
+
+/// Loads the queued try requests of the repository, in the order in which they should be started.
+async fn load_try_queue(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+) -> anyhow::Result<Vec<TryRequestModel>> {
+    let mut requests = db.get_try_requests(repo.repository()).await?;
+    order_try_requests(&mut requests, repo.config.load().try_queue_order);
+    Ok(requests)
+}
+
+/// Orders try requests that are sorted by the time of the request.
+///
+/// With [`TryQueueOrder::Priority`], requests of PRs with a higher priority go first.
+/// Requests with the same priority are interleaved by requester, so that a user who queues
+/// many try builds at once does not hold back the try builds of everyone else.
+fn order_try_requests(requests: &mut [TryRequestModel], order: TryQueueOrder) {
+    let mut queued_by_requester: HashMap<(i32, String), usize> = HashMap::new();
+    let mut keys = HashMap::with_capacity(requests.len());
+    for request in requests.iter() {
+        let priority = match order {
+            TryQueueOrder::Fifo => 0,
+            TryQueueOrder::Priority => request.priority,
+        };
+        let earlier = queued_by_requester
+            .entry((priority, request.requester.clone()))
+            .or_default();
+        keys.insert(request.id, (-priority, *earlier));
+        *earlier += 1;
+    }
+    // The sort is stable, so requests with the same key keep their original order
+    requests.sort_by_key(|request| keys[&request.id]);
+}
+
+/// Starts queued try builds, in the order given by the configured [`TryQueueOrder`],
+/// until all try branches are busy.
+pub(super) async fn process_try_queue(
+    repo: &RepositoryState,
+    db: &PgDbClient,
+) -> anyhow::Result<()> {
+    for request in load_try_queue(repo, db).await? {
+        let Some(try_branch) = find_free_try_branch(repo, db).await? else {
+            return Ok(());
+        };
+
+        let Some(pr_model) = db
+            .get_pull_request(repo.repository(), request.pr_number)
+            .await?
+        else {
+            db.delete_try_request(&request).await?;
+            continue;
+        };
+        let has_pending_build = pr_model
+            .try_build
+            .as_ref()
+            .is_some_and(|build| build.status == BuildStatus::Pending);
+        if matches!(
+            pr_model.pr_status,
+            PullRequestStatus::Closed | PullRequestStatus::Merged
+        ) || has_pending_build
+        {
+            tracing::info!("Dropping queued try build of PR {}", request.pr_number);
+            db.delete_try_request(&request).await?;
+            continue;
+        }
+
+        tracing::info!(
+            "Starting queued try build of PR {} on {try_branch}",
+            request.pr_number
+        );
+        let parent = request.parent.as_deref().map(parent_from_string);
+        let result = async {
+            let pr = repo.client.get_pull_request(request.pr_number).await?;
+            start_try_build(
+                repo,
+                db,
+                &pr,
+                pr_model,
+                &try_branch,
+                parent,
+                request.jobs.clone(),
+            )
+            .await
+        }
+        .await;
+        if let Err(error) = result {
+            // A request that cannot be started must not block the requests behind it
+            tracing::error!(
+                "Cannot start queued try build of PR {}: {error:?}",
+                request.pr_number
+            );
+            if db.record_try_request_failure(&request).await? >= MAX_TRY_REQUEST_ATTEMPTS {
+                tracing::info!("Dropping queued try build of PR {}", request.pr_number);
+                db.delete_try_request(&request).await?;
+                repo.post_comment(request.pr_number, queued_try_build_failed_comment())
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parent_to_string(parent: &Parent) -> String {
+    match parent {
+        Parent::CommitSha(sha) => sha.to_string(),
+        Parent::Last => "last".to_string(),
+    }
+}
+
+fn parent_from_string(parent: &str) -> Parent {
+    match parent {
+        "last" => Parent::Last,
+        sha => Parent::CommitSha(CommitSha(sha.to_string())),
+    }
+}
+
+/// Combines the PR with `base_sha` using the given (non-CI) `merge_branch`, according to the
+/// merge strategy and commit message templates of the repository `config`.
+pub(super) async fn attempt_merge(
     client: &GithubRepositoryClient,
-    head_sha: &CommitSha,
+    config: &RepositoryConfig,
+    merge_branch: &str,
+    pr: &PullRequest,
     base_sha: &CommitSha,
-    merge_message: &str,
+    reviewer: &str,
+    jobs: Vec<String>,
 ) -> anyhow::Result<MergeResult> {
-    tracing::debug!("Attempting to merge with base SHA {base_sha}");
+    let strategy = config.merge_strategy;
+    tracing::debug!("Attempting to merge with base SHA {base_sha} using {strategy:?} strategy");
 
-    // First set the try branch to our base commit (either the selected parent or the main branch).
+    // First set the merge branch to our base commit (either the selected parent or the main branch).
     client
-        .set_branch_to_sha(TRY_MERGE_BRANCH_NAME, base_sha)
+        .set_branch_to_sha(merge_branch, base_sha)
         .await
-        .map_err(|error| anyhow!("Cannot set try merge branch to {}: {error:?}", base_sha.0))?;
+        .map_err(|error| anyhow!("Cannot set {merge_branch} to {}: {error:?}", base_sha.0))?;
+
+    let result = match strategy {
+        MergeStrategy::Merge => {
+            let message = auto_merge_commit_message(
+                pr,
+                client.repository(),
+                reviewer,
+                jobs,
+                &config.templates,
+            );
+            merge_commit(client, merge_branch, &pr.head.sha, &message).await?
+        }
+        MergeStrategy::Squash => {
+            squash_pr(client, config, merge_branch, pr, base_sha, reviewer, jobs).await?
+        }
+        MergeStrategy::Rebase => rebase_pr(client, merge_branch, pr, base_sha, jobs).await?,
+    };
+    match &result {
+        MergeResult::Success(merge_sha) => tracing::debug!("Merge successful, SHA: {merge_sha}"),
+        MergeResult::Conflict => tracing::warn!("Merge conflict"),
+    }
+    Ok(result)
+}
 
-    // Then merge the PR commit into the try branch
+/// Merges `head_sha` into the current commit of `merge_branch`.
+async fn merge_commit(
+    client: &GithubRepositoryClient,
+    merge_branch: &str,
+    head_sha: &CommitSha,
+    merge_message: &str,
+) -> anyhow::Result<MergeResult> {
     match client
-        .merge_branches(TRY_MERGE_BRANCH_NAME, head_sha, merge_message)
+        .merge_branches(merge_branch, head_sha, merge_message)
+        .await
+    {
+        Ok(merge_sha) => Ok(MergeResult::Success(merge_sha)),
+        Err(MergeError::Conflict) => Ok(MergeResult::Conflict),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Creates a single commit with the changes of the PR on top of `base_sha`.
+///
+/// The PR is first merged normally, and then the tree of the merge commit is committed again
+/// with `base_sha` as its only parent.
+async fn squash_pr(
+    client: &GithubRepositoryClient,
+    config: &RepositoryConfig,
+    merge_branch: &str,
+    pr: &PullRequest,
+    base_sha: &CommitSha,
+    reviewer: &str,
+    jobs: Vec<String>,
+) -> anyhow::Result<MergeResult> {
+    let message =
+        auto_merge_commit_message(pr, client.repository(), reviewer, vec![], &config.templates);
+    let merge_sha = match merge_commit(client, merge_branch, &pr.head.sha, &message).await? {
+        MergeResult::Success(merge_sha) => merge_sha,
+        MergeResult::Conflict => return Ok(MergeResult::Conflict),
+    };
+    let tree = client.get_commit_tree(&merge_sha).await?;
+    let squash_sha = client
+        .create_commit(
+            &tree,
+            &[base_sha.clone()],
+            &squash_commit_message(pr, client.repository(), reviewer, jobs, &config.templates),
+            None,
+        )
+        .await?;
+    client
+        .set_branch_to_sha(merge_branch, &squash_sha)
         .await
+        .map_err(|error| anyhow!("Cannot set {merge_branch} to {squash_sha}: {error:?}"))?;
+    Ok(MergeResult::Success(squash_sha))
+}
+
+/// Recreates the commits of the PR one by one on top of `base_sha`, keeping their messages
+/// and authors.
+///
+/// Each PR commit is merged into the current tip of the rebased commits, and the tree of the
+/// merge commit is then committed with the previous tip as its only parent. The `try-job` lines
+/// of all PR commits and the requested jobs are appended to the last rebased commit.
+async fn rebase_pr(
+    client: &GithubRepositoryClient,
+    merge_branch: &str,
+    pr: &PullRequest,
+    base_sha: &CommitSha,
+    jobs: Vec<String>,
+) -> anyhow::Result<MergeResult> {
+    let commits = client.get_pull_request_commits(pr.number).await?;
+    let mut try_jobs: Vec<String> = vec![];
+    for job in commits
+        .iter()
+        .flat_map(|commit| parse_try_jobs(&commit.message))
+        .chain(jobs)
     {
-        Ok(merge_sha) => {
-            tracing::debug!("Merge successful, SHA: {merge_sha}");
+        if !try_jobs.contains(&job) {
+            try_jobs.push(job);
+        }
+    }
 
-            Ok(MergeResult::Success(merge_sha))
+    let mut tip = base_sha.clone();
+    let mut last_rebased = None;
+    for (index, commit) in commits.iter().enumerate() {
+        let message = format!("Rebase {} onto {tip}", commit.sha);
+        let merge_sha = match client
+            .merge_branches(merge_branch, &commit.sha, &message)
+            .await
+        {
+            Ok(merge_sha) => merge_sha,
+            Err(MergeError::Conflict) => return Ok(MergeResult::Conflict),
+            // The commit is already contained in the base commit
+            Err(MergeError::AlreadyMerged) => continue,
+            Err(error) => return Err(error.into()),
+        };
+        let tree = client.get_commit_tree(&merge_sha).await?;
+
+        let mut message = commit.message.clone();
+        if index == commits.len() - 1 {
+            append_missing_try_jobs(&mut message, &try_jobs);
         }
-        Err(MergeError::Conflict) => {
-            tracing::warn!("Merge conflict");
+        let parent = tip.clone();
+        tip = commit_to_branch(client, merge_branch, &tree, &parent, &message, commit).await?;
+        last_rebased = Some((index, tree, parent));
+    }
 
-            Ok(MergeResult::Conflict)
+    // If the last commits of the PR were skipped, their `try-job` lines would be lost, so the
+    // last rebased commit is recreated with them
+    if let Some((index, tree, parent)) =
+        last_rebased.filter(|(index, ..)| *index < commits.len() - 1)
+    {
+        let commit = &commits[index];
+        let mut message = commit.message.clone();
+        append_missing_try_jobs(&mut message, &try_jobs);
+        if message != commit.message {
+            tip = commit_to_branch(client, merge_branch, &tree, &parent, &message, commit).await?;
         }
-        Err(error) => Err(error.into()),
     }
+    Ok(MergeResult::Success(tip))
+}
+
+/// Creates a commit with the given tree on top of `parent`, with the author of the PR `commit`,
+/// and moves `merge_branch` to it.
+async fn commit_to_branch(
+    client: &GithubRepositoryClient,
+    merge_branch: &str,
+    tree: &str,
+    parent: &CommitSha,
+    message: &str,
+    commit: &PullRequestCommit,
+) -> anyhow::Result<CommitSha> {
+    let sha = client
+        .create_commit(tree, &[parent.clone()], message, commit.author.as_ref())
+        .await?;
+    client
+        .set_branch_to_sha(merge_branch, &sha)
+        .await
+        .map_err(|error| anyhow!("Cannot set {merge_branch} to {sha}: {error:?}"))?;
+    Ok(sha)
 }
 
 async fn run_try_build(
     client: &GithubRepositoryClient,
     db: &PgDbClient,
-    pr_model: PullRequestModel,
+    pr_model: &PullRequestModel,
+    try_branch: &str,
     commit_sha: CommitSha,
     parent_sha: CommitSha,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i32> {
     client
-        .set_branch_to_sha(TRY_BRANCH_NAME, &commit_sha)
+        .set_branch_to_sha(try_branch, &commit_sha)
         .await
-        .map_err(|error| anyhow!("Cannot set try branch to main branch: {error:?}"))?;
+        .map_err(|error| anyhow!("Cannot set {try_branch} to main branch: {error:?}"))?;
 
-    db.attach_try_build(
-        pr_model,
-        TRY_BRANCH_NAME.to_string(),
-        commit_sha,
-        parent_sha,
-    )
-    .await?;
+    let build_id = db
+        .attach_try_build(pr_model, try_branch.to_string(), commit_sha, parent_sha)
+        .await?;
 
     tracing::info!("Try build started");
-    Ok(())
+    Ok(build_id)
 }
 
-enum MergeResult {
+pub(super) enum MergeResult {
     Success(CommitSha),
     Conflict,
 }
@@ -217,14 +579,21 @@ pub(super) async fn command_try_cancel(
         )
         .await?;
 
+    if db.delete_try_request_for_pr(&pr).await? {
+        tracing::info!("Queued try build cancelled");
+        repo.post_comment(pr_number, queued_try_build_cancelled_comment())
+            .await?;
+        return Ok(());
+    }
+
     let Some(build) = get_pending_build(pr) else {
         tracing::warn!("No build found");
-        repo.client
-            .post_comment(pr_number, no_try_build_in_progress_comment())
+        repo.post_comment(pr_number, no_try_build_in_progress_comment())
             .await?;
         return Ok(());
     };
 
+    TRY_BUILDS.with_label_values(&["cancelled"]).inc();
     match cancel_build_workflows(&repo.client, db.as_ref(), &build).await {
         Err(error) => {
             tracing::error!(
@@ -233,8 +602,7 @@ pub(super) async fn command_try_cancel(
             );
             db.update_build_status(&build, BuildStatus::Cancelled)
                 .await?;
-            repo.client
-                .post_comment(pr_number, unclean_try_build_cancelled_comment())
+            repo.post_comment(pr_number, unclean_try_build_cancelled_comment())
                 .await?
         }
         Ok(workflow_ids) => {
@@ -242,18 +610,123 @@ pub(super) async fn command_try_cancel(
                 .await?;
             tracing::info!("Try build cancelled");
 
-            repo.client
-                .post_comment(
-                    pr_number,
-                    try_build_cancelled_comment(
-                        repo.client.get_workflow_urls(workflow_ids.into_iter()),
-                    ),
-                )
-                .await?
+            repo.post_comment(
+                pr_number,
+                try_build_cancelled_comment(
+                    repo.client.get_workflow_urls(workflow_ids.into_iter()),
+                ),
+            )
+            .await?
         }
     };
+    update_build_check_run(repo, &db, &build, CheckRunState::Cancelled).await;
+    handle_label_trigger(repo, pr_number, LabelTrigger::BuildCancelled).await?;
 
-    Ok(())
+    process_try_queue(repo, &db).await
+}
+
+/// Re-runs the failed workflows of the latest failed try or auto build of the PR.
+///
+/// The build keeps its merge commit, so unlike with a new try build, the PR is not merged again.
+/// A retried auto build is finished by the merge queue like any other auto build. If another
+/// auto build has been started in the meantime, the PR is put back into the merge queue instead.
+pub(super) async fn command_retry(
+    repo: Arc<RepositoryState>,
+    db: Arc<PgDbClient>,
+    pr: &PullRequest,
+    author: &GithubUser,
+) -> anyhow::Result<()> {
+    let repo = repo.as_ref();
+    if !has_permission(repo, author, pr, &db, PermissionType::Try).await? {
+        deny_request(repo, pr, author, PermissionType::Try).await?;
+        return Ok(());
+    }
+
+    let pr_model = db
+        .get_or_create_pull_request(
+            repo.client.repository(),
+            pr.number,
+            &pr.base.name,
+            pr.mergeable_state.clone().into(),
+            &pr.status,
+        )
+        .await?;
+
+    // An auto build is only worth retrying if the PR is still approved
+    let auto_build = pr_model
+        .auto_build
+        .clone()
+        .filter(|_| pr_model.is_approved());
+    let Some(build) = pr_model
+        .try_build
+        .clone()
+        .into_iter()
+        .chain(auto_build)
+        .filter(|build| build.status == BuildStatus::Failure)
+        .max_by_key(|build| build.started_at())
+    else {
+        tracing::warn!("No failed build found");
+        repo.post_comment(pr.number, no_failed_build_comment())
+            .await?;
+        return Ok(());
+    };
+    let is_auto_build = build.branch == AUTO_BRANCH_NAME;
+
+    // Only workflows from GitHub Actions can be re-run, external CI systems have to be
+    // restarted on their own.
+    let failed_workflows = db
+        .get_workflows_for_build(&build)
+        .await?
+        .into_iter()
+        .filter(|w| w.status == WorkflowStatus::Failure && w.workflow_type == WorkflowType::Github)
+        .map(|w| w.run_id)
+        .collect::<Vec<_>>();
+    if failed_workflows.is_empty() {
+        repo.post_comment(pr.number, no_failed_workflows_comment())
+            .await?;
+        return Ok(());
+    }
+
+    if is_auto_build
+        && db
+            .get_running_builds(repo.repository())
+            .await?
+            .iter()
+            .any(|build| build.branch == AUTO_BRANCH_NAME)
+    {
+        // Only one auto build can run at a time, so the PR waits for a new auto build instead
+        tracing::info!(
+            "Auto branch is busy, putting PR {} back into the merge queue",
+            pr.number
+        );
+        db.update_build_status(&build, BuildStatus::Cancelled)
+            .await?;
+        return repo
+            .post_comment(pr.number, auto_build_requeued_comment())
+            .await;
+    }
+
+    tracing::info!("Re-running failed workflows {failed_workflows:?}");
+    repo.client
+        .rerun_failed_jobs(&failed_workflows)
+        .await
+        .context("Cannot re-run failed workflows")?;
+    db.retry_build(&build, &failed_workflows).await?;
+    update_build_check_run(repo, &db, &build, CheckRunState::InProgress).await;
+    if !is_auto_build {
+        TRY_BUILDS.with_label_values(&["retried"]).inc();
+        handle_label_trigger(repo, pr.number, LabelTrigger::TryBuildStarted).await?;
+    }
+
+    repo.post_comment(
+        pr.number,
+        retrying_build_comment(
+            is_auto_build,
+            &build.commit_sha,
+            repo.client.get_workflow_urls(failed_workflows.into_iter()),
+        ),
+    )
+    .await
 }
 
 pub async fn cancel_build_workflows(
@@ -278,34 +751,110 @@ fn auto_merge_commit_message(
     name: &GithubRepoName,
     reviewer: &str,
     jobs: Vec<String>,
+    templates: &HashMap<TemplateKind, Template>,
 ) -> String {
     let pr_number = pr.number;
-    let mut message = format!(
-        r#"Auto merge of {repo_owner}/{repo_name}#{pr_number} - {pr_label}, r={reviewer}
+    let mut message = match templates.get(&TemplateKind::MergeCommit) {
+        Some(template) => template.render(&commit_message_variables(pr, name, reviewer)),
+        None => format!(
+            r#"Auto merge of {repo_owner}/{repo_name}#{pr_number} - {pr_label}, r={reviewer}
 {pr_title}
 
 {pr_message}"#,
-        pr_label = pr.head_label,
-        pr_title = pr.title,
-        pr_message = suppress_github_mentions(&pr.message),
-        repo_owner = name.owner(),
-        repo_name = name.name()
-    );
+            pr_label = pr.head_label,
+            pr_title = pr.title,
+            pr_message = suppress_github_mentions(&pr.message),
+            repo_owner = name.owner(),
+            repo_name = name.name()
+        ),
+    };
+
+    append_try_jobs(&mut message, &jobs);
+    message
+}
+
+/// Creates the message of a commit that squashes all commits of a PR.
+fn squash_commit_message(
+    pr: &PullRequest,
+    name: &GithubRepoName,
+    reviewer: &str,
+    jobs: Vec<String>,
+    templates: &HashMap<TemplateKind, Template>,
+) -> String {
+    let mut message = match templates.get(&TemplateKind::SquashCommit) {
+        Some(template) => template.render(&commit_message_variables(pr, name, reviewer)),
+        None => format!(
+            r#"{pr_title} (#{pr_number})
+
+{pr_message}
+
+Approved-by: {reviewer}"#,
+            pr_title = pr.title,
+            pr_number = pr.number,
+            pr_message = suppress_github_mentions(&pr.message),
+        ),
+    };
+    append_try_jobs(&mut message, &jobs);
+    message
+}
 
+/// Values of the variables that can be used in templates of commit messages.
+fn commit_message_variables(
+    pr: &PullRequest,
+    name: &GithubRepoName,
+    reviewer: &str,
+) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("pr_number", pr.number.to_string()),
+        ("pr_title", pr.title.clone()),
+        ("pr_body", suppress_github_mentions(&pr.message)),
+        ("pr_author", pr.author.username.clone()),
+        ("pr_label", pr.head_label.clone()),
+        ("approver", reviewer.to_string()),
+        ("repo", name.to_string()),
+    ])
+}
+
+fn append_try_jobs(message: &mut String, jobs: &[String]) {
     // if jobs is empty, try-job won't be added to the message
     for job in jobs {
         message.push_str(&format!("\ntry-job: {}", job));
     }
+}
+
+/// Returns the jobs of the `try-job` lines of a commit message.
+fn parse_try_jobs(message: &str) -> impl Iterator<Item = String> + '_ {
     message
+        .lines()
+        .filter_map(|line| line.strip_prefix("try-job:"))
+        .map(|job| job.trim().to_string())
+}
+
+/// Appends the jobs that do not have a `try-job` line in the message yet.
+fn append_missing_try_jobs(message: &mut String, jobs: &[String]) {
+    let present: Vec<String> = parse_try_jobs(message).collect();
+    let missing: Vec<String> = jobs
+        .iter()
+        .filter(|job| !present.contains(job))
+        .cloned()
+        .collect();
+    append_try_jobs(message, &missing);
 }
 
 fn trying_build_comment(head_sha: &CommitSha, merge_sha: &CommitSha) -> Comment {
     Comment::new(format!(
         ":hourglass: Trying commit {head_sha} with merge {merge_sha}…"
     ))
+    .with_template(
+        TemplateKind::TryBuildStarted,
+        [
+            ("head_sha", head_sha.to_string()),
+            ("merge_sha", merge_sha.to_string()),
+        ],
+    )
 }
 
-fn merge_conflict_comment(branch: &str) -> Comment {
+pub(super) fn merge_conflict_comment(branch: &str) -> Comment {
     let message = format!(
         r#":lock: Merge conflict
 
@@ -337,19 +886,343 @@ handled during merge and rebase. This is normal, and you should still perform st
 </details>
 "#
     );
-    Comment::new(message)
+    Comment::new(message).with_template(
+        TemplateKind::MergeConflict,
+        [("branch", branch.to_string())],
+    )
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::bors::handlers::trybuild::{TRY_BRANCH_NAME, TRY_MERGE_BRANCH_NAME};
+    use std::num::NonZeroU32;
+
+    use chrono::Utc;
+
+    use crate::bors::handlers::trybuild::{
+        MAX_TRY_REQUEST_ATTEMPTS, TRY_BRANCH_NAME, TRY_MERGE_BRANCH_NAME, is_try_branch,
+        order_try_requests, try_branch_names,
+    };
+    use crate::config::TryQueueOrder;
     use crate::database::operations::get_all_workflows;
+    use crate::database::{BuildStatus, TryRequestModel};
     use crate::github::CommitSha;
     use crate::tests::mocks::{
-        BorsBuilder, Comment, GitHubState, User, Workflow, WorkflowEvent, default_pr_number,
-        default_repo_name, run_test,
+        BorsBuilder, Comment, GitHubState, PullRequest, Repo, User, Workflow, WorkflowEvent,
+        default_pr_number, default_repo_name, run_test,
     };
 
+    fn state_with_more_prs(config: &str) -> GitHubState {
+        let repo = [2, 3].into_iter().fold(Repo::default(), |repo, number| {
+            repo.with_pr(PullRequest::new(
+                default_repo_name(),
+                number,
+                User::default_pr_author(),
+                false,
+            ))
+        });
+        GitHubState::default()
+            .with_repo(repo)
+            .with_default_config(config)
+    }
+
+    fn try_request(id: i32, requester: &str, priority: i32) -> TryRequestModel {
+        TryRequestModel {
+            id,
+            repository: default_repo_name(),
+            pr_number: (id as u64).into(),
+            requester: requester.to_string(),
+            parent: None,
+            jobs: vec![],
+            failed_attempts: 0,
+            priority,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn ordered_ids(mut requests: Vec<TryRequestModel>, order: TryQueueOrder) -> Vec<i32> {
+        order_try_requests(&mut requests, order);
+        requests.into_iter().map(|request| request.id).collect()
+    }
+
+    #[test]
+    fn order_try_requests_fifo() {
+        let requests = vec![
+            try_request(1, "a", 0),
+            try_request(2, "b", 10),
+            try_request(3, "c", 0),
+        ];
+        assert_eq!(ordered_ids(requests, TryQueueOrder::Fifo), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn order_try_requests_priority() {
+        let requests = vec![
+            try_request(1, "a", 0),
+            try_request(2, "b", 10),
+            try_request(3, "c", 0),
+            try_request(4, "d", 5),
+        ];
+        assert_eq!(
+            ordered_ids(requests, TryQueueOrder::Priority),
+            vec![2, 4, 1, 3]
+        );
+    }
+
+    #[test]
+    fn order_try_requests_interleave_requesters() {
+        let requests = vec![
+            try_request(1, "a", 0),
+            try_request(2, "a", 0),
+            try_request(3, "a", 0),
+            try_request(4, "b", 0),
+            try_request(5, "b", 0),
+            try_request(6, "c", 0),
+        ];
+        assert_eq!(
+            ordered_ids(requests, TryQueueOrder::Fifo),
+            vec![1, 4, 6, 2, 5, 3]
+        );
+    }
+
+    #[test]
+    fn try_branch_pool_names() {
+        assert_eq!(try_branch_names(NonZeroU32::MIN), vec![TRY_BRANCH_NAME]);
+        assert_eq!(
+            try_branch_names(NonZeroU32::new(2).unwrap()),
+            vec!["automation/bors/try-1", "automation/bors/try-2"]
+        );
+    }
+
+    #[test]
+    fn recognize_try_branches() {
+        assert!(is_try_branch("automation/bors/try"));
+        assert!(is_try_branch("automation/bors/try-3"));
+        assert!(!is_try_branch(TRY_MERGE_BRANCH_NAME));
+        assert!(!is_try_branch("automation/bors/auto"));
+    }
+
+    #[sqlx::test]
+    async fn try_builds_use_free_try_branches(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(state_with_more_prs("try_branches = 2"))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @":hourglass: Trying commit pr-2-sha with merge merge-main-sha1-pr-2-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            "automation/bors/try-1",
+            &["merge-main-sha1-pr-1-sha-0"],
+        );
+        gh.check_sha_history(
+            default_repo_name(),
+            "automation/bors/try-2",
+            &["merge-main-sha1-pr-2-sha-1"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn try_build_queued_when_try_branches_are_busy(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(""))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_BRANCH_NAME).expect_suites(1);
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @":hourglass_flowing_sand: All try branches are busy, the try build has been queued at position 1. It will start once a try branch becomes free."
+                );
+
+                tester.workflow_success(tester.try_branch()).await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @":hourglass: Trying commit pr-2-sha with merge merge-main-sha1-pr-2-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn queued_try_build_kept_when_start_fails(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(""))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+
+                // Free the try branch without handling any event
+                let db = tester.db();
+                for build in db.get_running_builds(&default_repo_name()).await? {
+                    db.update_build_status(&build, BuildStatus::Cancelled)
+                        .await?;
+                }
+                tester.default_repo().lock().pull_request_error = true;
+                tester.refresh().await;
+                assert_eq!(db.get_try_requests(&default_repo_name()).await?.len(), 1);
+
+                tester.default_repo().lock().pull_request_error = false;
+                tester.refresh().await;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @":hourglass: Trying commit pr-2-sha with merge merge-main-sha1-pr-2-sha-1…"
+                );
+                assert!(db.get_try_requests(&default_repo_name()).await?.is_empty());
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn failing_queued_try_build_does_not_block_queue(pool: sqlx::PgPool) {
+        const BAD_SHA: &str = "ea9c1b050cc8b420c2c211d2177811e564a4dc60";
+
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(""))
+            .run_test(|mut tester| async {
+                tester
+                    .default_repo()
+                    .lock()
+                    .unknown_shas
+                    .insert(BAD_SHA.to_string());
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(
+                        default_repo_name(),
+                        2,
+                        &format!("@bors try parent={BAD_SHA}"),
+                    ))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 3, "@bors try"))
+                    .await?;
+                tester.get_comment_on_pr(3).await?;
+
+                let db = tester.db();
+                for attempt in 1..=MAX_TRY_REQUEST_ATTEMPTS {
+                    // Free the try branch without handling any event
+                    for build in db.get_running_builds(&default_repo_name()).await? {
+                        db.update_build_status(&build, BuildStatus::Cancelled)
+                            .await?;
+                    }
+                    tester.refresh().await;
+                    if attempt == 1 {
+                        insta::assert_snapshot!(
+                            tester.get_comment_on_pr(3).await?,
+                            @":hourglass: Trying commit pr-3-sha with merge merge-main-sha1-pr-3-sha-1…"
+                        );
+                    }
+                }
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @":x: The queued try build could not be started and has been removed from the queue. Use @bors try to request it again."
+                );
+                assert!(db.get_try_requests(&default_repo_name()).await?.is_empty());
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_queue_position(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(""))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 3, "@bors try"))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(3).await?,
+                    @":hourglass_flowing_sand: All try branches are busy, the try build has been queued at position 2. It will start once a try branch becomes free."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_queue_by_priority(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(r#"try_queue_order = "priority""#))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_BRANCH_NAME).expect_suites(1);
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 3, "@bors p=5"))
+                    .await?;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 3, "@bors try"))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(3).await?,
+                    @":hourglass_flowing_sand: All try branches are busy, the try build has been queued at position 1. It will start once a try branch becomes free."
+                );
+
+                tester.workflow_success(tester.try_branch()).await?;
+                tester.expect_comments(1).await;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(3).await?,
+                    @":hourglass: Trying commit pr-3-sha with merge merge-main-sha1-pr-3-sha-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_cancel_queued_build(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(state_with_more_prs(""))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try"))
+                    .await?;
+                tester.get_comment_on_pr(2).await?;
+                tester
+                    .post_comment(Comment::new(default_repo_name(), 2, "@bors try cancel"))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment_on_pr(2).await?,
+                    @"Queued try build cancelled."
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
     #[sqlx::test]
     async fn try_success(pool: sqlx::PgPool) {
         run_test(pool.clone(), |mut tester| async {
@@ -371,6 +1244,64 @@ mod tests {
         .await;
     }
 
+    #[sqlx::test]
+    async fn try_comment_templates(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[templates]
+try_build_started = ":rocket: Testing #{pr_number} at {merge_sha}"
+try_build_succeeded = "Done in {duration}:\n{workflow_urls}"
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_BRANCH_NAME).expect_suites(1);
+                tester.post_comment("@bors try").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":rocket: Testing #1 at merge-main-sha1-pr-1-sha-0"
+                );
+                tester.workflow_success(tester.try_branch()).await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with("Done in "));
+                assert!(comment.contains("\nhttps://github.com/workflows/Workflow1/1\n"));
+                // The metadata of the comment is kept
+                assert!(comment.ends_with(
+                    r#"<!-- homu: {"type":"TryBuildCompleted","merge_sha":"merge-main-sha1-pr-1-sha-0"} -->"#
+                ));
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_merge_commit_template(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(
+                r#"
+[templates]
+merge_commit = "Merge #{pr_number} ({pr_title}) by {pr_author}, r={approver}"
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try jobs=Foo").await?;
+                tester.expect_comments(1).await;
+                Ok(tester)
+            })
+            .await;
+        insta::assert_snapshot!(
+            gh.default_repo()
+                .lock()
+                .get_branch_by_name(TRY_MERGE_BRANCH_NAME)
+                .unwrap()
+                .get_commit_message(),
+            @r"
+        Merge #1 (PR #1) by default-user, r=<try>
+        try-job: Foo
+        "
+        );
+    }
+
     #[sqlx::test]
     async fn try_failure(pool: sqlx::PgPool) {
         run_test(pool.clone(), |mut tester| async {
@@ -453,6 +1384,143 @@ mod tests {
         );
     }
 
+    #[sqlx::test]
+    async fn try_merge_squash(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"merge_strategy = "squash""#))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try jobs=Foo").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Trying commit pr-1-sha with merge commit-1…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            TRY_MERGE_BRANCH_NAME,
+            &["main-sha1", "merge-main-sha1-pr-1-sha-0", "commit-1"],
+        );
+        gh.check_sha_history(default_repo_name(), TRY_BRANCH_NAME, &["commit-1"]);
+
+        let commits = gh.default_repo().lock().created_commits.clone();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].tree, "merge-main-sha1-pr-1-sha-0-tree");
+        assert_eq!(commits[0].parents, vec!["main-sha1"]);
+        assert_eq!(commits[0].author, None);
+        insta::assert_snapshot!(commits[0].message, @r"
+        PR #1 (#1)
+
+        Description of PR #1
+
+        Approved-by: <try>
+        try-job: Foo
+        ");
+    }
+
+    #[sqlx::test]
+    async fn try_merge_rebase(pool: sqlx::PgPool) {
+        let mut repo = Repo::default();
+        repo.get_pr_mut(default_pr_number()).commits =
+            vec!["pr-1-commit-1".to_string(), "pr-1-sha".to_string()];
+        let gh = BorsBuilder::new(pool)
+            .github(
+                GitHubState::default()
+                    .with_repo(repo)
+                    .with_default_config(r#"merge_strategy = "rebase""#),
+            )
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try jobs=Foo").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Trying commit pr-1-sha with merge commit-2…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            TRY_MERGE_BRANCH_NAME,
+            &[
+                "main-sha1",
+                "merge-main-sha1-pr-1-commit-1-0",
+                "commit-1",
+                "merge-commit-1-pr-1-sha-1",
+                "commit-2",
+            ],
+        );
+        gh.check_sha_history(default_repo_name(), TRY_BRANCH_NAME, &["commit-2"]);
+
+        let commits = gh.default_repo().lock().created_commits.clone();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].tree, "merge-main-sha1-pr-1-commit-1-0-tree");
+        assert_eq!(commits[0].parents, vec!["main-sha1"]);
+        assert_eq!(commits[0].message, "Commit pr-1-commit-1");
+        assert_eq!(commits[0].author.as_deref(), Some("default-user"));
+        assert_eq!(commits[1].tree, "merge-commit-1-pr-1-sha-1-tree");
+        assert_eq!(commits[1].parents, vec!["commit-1"]);
+        assert_eq!(commits[1].message, "Commit pr-1-sha\ntry-job: Foo");
+    }
+
+    #[sqlx::test]
+    async fn try_merge_rebase_keeps_jobs_of_skipped_commits(pool: sqlx::PgPool) {
+        let mut repo = Repo::default();
+        let pr = repo.get_pr_mut(default_pr_number());
+        pr.commits = vec!["pr-1-commit-1".to_string(), "pr-1-sha".to_string()];
+        pr.commit_messages.insert(
+            "pr-1-sha".to_string(),
+            "Commit pr-1-sha\ntry-job: Bar".to_string(),
+        );
+        // The last commit of the PR is already contained in the base branch
+        repo.merged_shas.insert("pr-1-sha".to_string());
+        let gh = BorsBuilder::new(pool)
+            .github(
+                GitHubState::default()
+                    .with_repo(repo)
+                    .with_default_config(r#"merge_strategy = "rebase""#),
+            )
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try jobs=Foo").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Trying commit pr-1-sha with merge commit-2…"
+                );
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(default_repo_name(), TRY_BRANCH_NAME, &["commit-2"]);
+
+        let commits = gh.default_repo().lock().created_commits.clone();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].message, "Commit pr-1-commit-1");
+        assert_eq!(commits[1].tree, commits[0].tree);
+        assert_eq!(commits[1].parents, vec!["main-sha1"]);
+        assert_eq!(
+            commits[1].message,
+            "Commit pr-1-commit-1\ntry-job: Bar\ntry-job: Foo"
+        );
+    }
+
+    #[sqlx::test]
+    async fn try_merge_rebase_conflict(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(GitHubState::default().with_default_config(r#"merge_strategy = "rebase""#))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_MERGE_BRANCH_NAME).merge_conflict = true;
+                tester.post_comment("@bors try").await?;
+                assert!(
+                    tester
+                        .get_comment()
+                        .await?
+                        .starts_with(":lock: Merge conflict")
+                );
+                Ok(tester)
+            })
+            .await;
+        assert!(gh.default_repo().lock().created_commits.is_empty());
+    }
+
     #[sqlx::test]
     async fn try_merge_explicit_parent(pool: sqlx::PgPool) {
         let gh = run_test(pool, |mut tester| async {
@@ -813,4 +1881,78 @@ try_failed = ["+foo", "+bar", "-baz"]
             })
             .await;
     }
+
+    #[sqlx::test]
+    async fn retry_no_failed_build(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+            tester.post_comment("@bors retry").await?;
+            insta::assert_snapshot!(tester.get_comment().await?, @":exclamation: There is no failed build that could be retried.");
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn retry_no_permissions(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester
+                .post_comment(Comment::from("@bors retry").with_author(User::unprivileged()))
+                .await?;
+            insta::assert_snapshot!(
+                tester.get_comment().await?,
+                @"@unprivileged-user: :key: Insufficient privileges: not in try users"
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn retry_failed_workflows(pool: sqlx::PgPool) {
+        let gh = run_test(pool, |mut tester| async {
+            tester.create_branch(TRY_BRANCH_NAME).expect_suites(2);
+            tester.post_comment("@bors try").await?;
+            tester.expect_comments(1).await;
+
+            let branch = tester.try_branch();
+            tester
+                .workflow_success(Workflow::from(branch.clone()).with_run_id(1))
+                .await?;
+            tester
+                .workflow_failure(Workflow::from(branch.clone()).with_run_id(2))
+                .await?;
+            tester.expect_comments(1).await;
+
+            tester.post_comment("@bors retry").await?;
+            insta::assert_snapshot!(tester.get_comment().await?, @r"
+            :repeat: Retrying try build merge-main-sha1-pr-1-sha-0.
+            Re-running failed workflows:
+            - https://github.com/rust-lang/borstest/actions/runs/2
+            ");
+
+            // Only the suite of the failed workflow is executed again
+            tester.get_branch_mut(TRY_BRANCH_NAME).expect_suites(1);
+            tester
+                .workflow_success(Workflow::from(branch.clone()).with_run_id(2))
+                .await?;
+            let comment = tester.get_comment().await?;
+            assert!(comment.starts_with(":sunny: Try build successful"));
+
+            let build = tester
+                .db()
+                .find_build(
+                    &default_repo_name(),
+                    TRY_BRANCH_NAME.to_string(),
+                    CommitSha(branch.get_sha().to_string()),
+                )
+                .await?
+                .unwrap();
+            assert_eq!(build.retry_count, 1);
+            Ok(tester)
+        })
+        .await;
+        gh.check_rerun_workflows(default_repo_name(), &[2]);
+    }
 }
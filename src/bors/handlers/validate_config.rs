@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use crate::bors::comment::{config_invalid_comment, config_removed_comment, config_valid_comment};
+use crate::bors::{Comment, RepositoryState};
+use crate::config::{CONFIG_FILE_PATH, RepositoryConfig};
+use crate::github::{CommitSha, PullRequest, PullRequestFile, PullRequestNumber};
+
+/// Validates the configuration file at the head of the PR and posts the result.
+pub(super) async fn command_validate_config(
+    repo: Arc<RepositoryState>,
+    pr: &PullRequest,
+) -> anyhow::Result<()> {
+    let files = repo.client.get_pull_request_files(pr.number).await?;
+    let comment = validate_config(&repo, &files, &pr.head.sha)
+        .await?
+        .unwrap_or_else(config_valid_comment);
+    repo.post_comment(pr.number, comment).await?;
+    Ok(())
+}
+
+/// If the PR modifies the configuration file, checks that bors will still be able to load it
+/// after the PR is merged. A comment is posted only if there is a problem with the file.
+pub(super) async fn check_config_change(
+    repo: &RepositoryState,
+    pr: PullRequestNumber,
+    head_sha: &CommitSha,
+) -> anyhow::Result<()> {
+    let files = repo.client.get_pull_request_files(pr).await?;
+    if !files.iter().any(|file| file.filename == CONFIG_FILE_PATH) {
+        return Ok(());
+    }
+    if let Some(comment) = validate_config(repo, &files, head_sha).await? {
+        repo.post_comment(pr, comment).await?;
+    }
+    Ok(())
+}
+
+/// Parses the configuration file in the given commit with the same deserializer that is used
+/// when the configuration of the repository is loaded.
+/// Returns a comment describing the problem if the file is invalid or removed by the PR.
+async fn validate_config(
+    repo: &RepositoryState,
+    files: &[PullRequestFile],
+    head_sha: &CommitSha,
+) -> anyhow::Result<Option<Comment>> {
+    if files
+        .iter()
+        .any(|file| file.filename == CONFIG_FILE_PATH && file.status == "removed")
+    {
+        return Ok(Some(config_removed_comment()));
+    }
+
+    let file = repo.client.get_config_file(Some(head_sha)).await?;
+    Ok(toml::from_str::<RepositoryConfig>(&file.content)
+        .err()
+        .map(|error| config_invalid_comment(&error.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::mocks::{ConfigChange, default_pr_number, default_repo_name, run_test};
+
+    #[sqlx::test]
+    async fn validate_config_valid(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors validate-config").await?;
+            insta::assert_snapshot!(
+                tester.get_comment().await?,
+                @":white_check_mark: `rust-bors.toml` is valid."
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn validate_config_invalid(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester
+                .default_repo()
+                .lock()
+                .get_pr_mut(default_pr_number())
+                .config_change = Some(ConfigChange::Modified("timeout = \"foo\"".to_string()));
+            tester.post_comment("@bors validate-config").await?;
+            let comment = tester.get_comment().await?;
+            assert!(comment.starts_with(":x: `rust-bors.toml` is invalid"));
+            assert!(comment.contains("timeout = \"foo\""));
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn check_invalid_config_on_push(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester
+                .default_repo()
+                .lock()
+                .get_pr_mut(default_pr_number())
+                .config_change = Some(ConfigChange::Modified("try_branches = 0".to_string()));
+            tester
+                .push_to_pr(default_repo_name(), default_pr_number())
+                .await?;
+            let comment = tester.get_comment().await?;
+            assert!(comment.starts_with(":x: `rust-bors.toml` is invalid"));
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn check_invalid_config_on_push_after_unapproval(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+            tester
+                .default_repo()
+                .lock()
+                .get_pr_mut(default_pr_number())
+                .config_change = Some(ConfigChange::Modified("try_branches = 0".to_string()));
+            tester
+                .push_to_pr(default_repo_name(), default_pr_number())
+                .await?;
+            let comment = tester.get_comment().await?;
+            assert!(comment.starts_with(":warning: A new commit"));
+            let comment = tester.get_comment().await?;
+            assert!(comment.starts_with(":x: `rust-bors.toml` is invalid"));
+            tester.default_pr().await.expect_unapproved();
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn check_removed_config_on_push(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester
+                .default_repo()
+                .lock()
+                .get_pr_mut(default_pr_number())
+                .config_change = Some(ConfigChange::Removed);
+            tester
+                .push_to_pr(default_repo_name(), default_pr_number())
+                .await?;
+            insta::assert_snapshot!(
+                tester.get_comment().await?,
+                @":warning: This PR removes `rust-bors.toml`, bors will stop working on this repository after it is merged."
+            );
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn ignore_valid_config_on_push(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester
+                .default_repo()
+                .lock()
+                .get_pr_mut(default_pr_number())
+                .config_change = Some(ConfigChange::Modified("timeout = 10".to_string()));
+            tester
+                .push_to_pr(default_repo_name(), default_pr_number())
+                .await?;
+            tester.post_comment("@bors ping").await?;
+            assert_eq!(tester.get_comment().await?, "Pong 🏓!");
+            Ok(tester)
+        })
+        .await;
+    }
+}
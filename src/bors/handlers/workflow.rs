@@ -6,10 +6,17 @@ use crate::bors::CheckSuiteStatus;
 use crate::bors::RepositoryState;
 use crate::bors::comment::{try_build_succeeded_comment, workflow_failed_comment};
 use crate::bors::event::{CheckSuiteCompleted, WorkflowCompleted, WorkflowStarted};
+use crate::bors::handlers::approval_status::APPROVAL_STATUS_CONTEXT;
+use crate::bors::handlers::check_run::{BORS_CHECK_RUN_NAME, update_build_check_run};
+use crate::bors::handlers::flaky::load_flaky_annotations;
 use crate::bors::handlers::is_bors_observed_branch;
 use crate::bors::handlers::labels::handle_label_trigger;
+use crate::bors::handlers::merge_queue::{AUTO_BRANCH_NAME, complete_auto_build};
+use crate::bors::handlers::refresh::elapsed_time;
+use crate::bors::handlers::trybuild::process_try_queue;
 use crate::database::{BuildStatus, WorkflowStatus};
-use crate::github::LabelTrigger;
+use crate::github::{CheckRunState, CommitSha, LabelTrigger};
+use crate::utils::metrics::TRY_BUILDS;
 
 pub(super) async fn handle_workflow_started(
     db: Arc<PgDbClient>,
@@ -184,33 +191,148 @@ async fn try_complete_build(
         return Ok(());
     }
 
-    let (status, trigger) = if has_failure {
-        (BuildStatus::Failure, LabelTrigger::TryBuildFailed)
+    let required_checks =
+        evaluate_required_checks(repo, &pr.base_branch, &payload.commit_sha).await?;
+    // A required check might be reported by a CI system that has not started yet.
+    // If it does not appear at all, the build will fail once it times out.
+    // There is no need to wait for it if the build has already failed.
+    if !has_failure && !required_checks.missing.is_empty() {
+        tracing::info!(
+            "All workflows are finished, but required checks are missing: {}",
+            required_checks.missing.join(", ")
+        );
+        return Ok(());
+    }
+    let has_failure = has_failure || !required_checks.failed.is_empty();
+
+    if build.branch == AUTO_BRANCH_NAME {
+        return complete_auto_build(
+            repo,
+            db,
+            build,
+            pr,
+            &workflows,
+            has_failure,
+            &required_checks.failed,
+        )
+        .await;
+    }
+
+    let (status, trigger, outcome) = if has_failure {
+        (BuildStatus::Failure, LabelTrigger::TryBuildFailed, "failed")
     } else {
-        (BuildStatus::Success, LabelTrigger::TryBuildSucceeded)
+        (
+            BuildStatus::Success,
+            LabelTrigger::TryBuildSucceeded,
+            "succeeded",
+        )
     };
     db.update_build_status(&build, status).await?;
+    TRY_BUILDS.with_label_values(&[outcome]).inc();
+    let check_run_state = if has_failure {
+        CheckRunState::Failure
+    } else {
+        CheckRunState::Success
+    };
+    update_build_check_run(repo, db, &build, check_run_state).await;
 
     handle_label_trigger(repo, pr.number, trigger).await?;
 
+    let duration = elapsed_time(build.started_at());
     let message = if !has_failure {
         tracing::info!("Workflow succeeded");
-        try_build_succeeded_comment(&workflows, payload.commit_sha)
+        try_build_succeeded_comment(&workflows, payload.commit_sha, duration)
     } else {
         tracing::info!("Workflow failed");
-        workflow_failed_comment(&workflows)
+        let stats = load_flaky_annotations(repo, db).await?;
+        workflow_failed_comment(&workflows, &required_checks.failed, &stats, duration)
     };
-    repo.client.post_comment(pr.number, message).await?;
+    repo.post_comment(pr.number, message).await?;
 
-    Ok(())
+    process_try_queue(repo, db).await
+}
+
+/// State of the required checks of a build.
+#[derive(Default)]
+pub(super) struct RequiredChecks {
+    /// Required checks that have not finished on the build commit.
+    pub(super) missing: Vec<String>,
+    /// Required checks that have finished unsuccessfully.
+    pub(super) failed: Vec<String>,
+}
+
+/// Compares the checks required for the given base branch, either by the repository config or by
+/// branch protection, with check runs and commit statuses attached to the build commit.
+pub(super) async fn evaluate_required_checks(
+    repo: &RepositoryState,
+    base_branch: &str,
+    commit_sha: &CommitSha,
+) -> anyhow::Result<RequiredChecks> {
+    let (mut required, from_branch_protection) = {
+        let config = repo.config.load();
+        (
+            config.required_checks.clone(),
+            config.required_checks_from_branch_protection,
+        )
+    };
+    if from_branch_protection {
+        for check in repo.client.get_required_checks(base_branch).await? {
+            // The check run and the approval status of bors are reported on the PR head, they
+            // can never be attached to the build commit.
+            if check == BORS_CHECK_RUN_NAME || check == APPROVAL_STATUS_CONTEXT {
+                continue;
+            }
+            if !required.contains(&check) {
+                required.push(check);
+            }
+        }
+    }
+    if required.is_empty() {
+        return Ok(RequiredChecks::default());
+    }
+
+    // A required check can be reported either as a check run or as a commit status
+    let check_runs = repo.client.get_check_runs_for_commit(commit_sha).await?;
+    let statuses = repo.client.get_commit_statuses(commit_sha).await?;
+    let mut result = RequiredChecks::default();
+    for check in required {
+        let reported = check_runs
+            .iter()
+            .chain(&statuses)
+            .filter(|run| run.name == check)
+            .map(|run| &run.status)
+            .collect::<Vec<_>>();
+        if reported
+            .iter()
+            .any(|status| matches!(status, CheckSuiteStatus::Success))
+        {
+            continue;
+        }
+        if reported
+            .iter()
+            .any(|status| matches!(status, CheckSuiteStatus::Failure))
+        {
+            result.failed.push(check);
+        } else {
+            result.missing.push(check);
+        }
+    }
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::bors::CheckSuiteStatus;
+    use crate::bors::handlers::approval_status::APPROVAL_STATUS_CONTEXT;
+    use crate::bors::handlers::check_run::BORS_CHECK_RUN_NAME;
+    use crate::bors::handlers::merge_queue::AUTO_BRANCH_NAME;
     use crate::bors::handlers::trybuild::TRY_BRANCH_NAME;
     use crate::database::WorkflowStatus;
     use crate::database::operations::get_all_workflows;
-    use crate::tests::mocks::{Branch, CheckSuite, Workflow, WorkflowEvent, run_test};
+    use crate::tests::mocks::{
+        BorsBuilder, Branch, CheckSuite, GitHubState, Workflow, WorkflowEvent, default_repo_name,
+        run_test,
+    };
 
     #[sqlx::test]
     async fn workflow_started_unknown_build(pool: sqlx::PgPool) {
@@ -390,4 +512,139 @@ mod tests {
         })
         .await;
     }
+
+    fn required_checks_state(config: &str) -> GitHubState {
+        GitHubState::default().with_default_config(config)
+    }
+
+    #[sqlx::test]
+    async fn try_build_waits_for_required_check(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(required_checks_state(r#"required_checks = ["build"]"#))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_success(tester.try_branch()).await?;
+                assert_eq!(
+                    tester
+                        .db()
+                        .get_running_builds(&default_repo_name())
+                        .await?
+                        .len(),
+                    1
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_with_passed_required_check(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(required_checks_state(r#"required_checks = ["build"]"#))
+            .run_test(|mut tester| async {
+                tester.create_branch(TRY_BRANCH_NAME).check_runs =
+                    vec![("build".to_string(), CheckSuiteStatus::Success)];
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_success(tester.try_branch()).await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":sunny: Try build successful"));
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_with_passed_required_status(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(required_checks_state(
+                "required_checks_from_branch_protection = true",
+            ))
+            .run_test(|mut tester| async {
+                tester.create_branch("main").required_checks = vec!["ci/external".to_string()];
+                tester.create_branch(TRY_BRANCH_NAME).statuses =
+                    vec![("ci/external".to_string(), CheckSuiteStatus::Success)];
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_success(tester.try_branch()).await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":sunny: Try build successful"));
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn try_build_fails_on_failed_branch_protection_check(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(required_checks_state(
+                "required_checks_from_branch_protection = true",
+            ))
+            .run_test(|mut tester| async {
+                tester.create_branch("main").required_checks = vec!["build".to_string()];
+                tester.create_branch(TRY_BRANCH_NAME).check_runs =
+                    vec![("build".to_string(), CheckSuiteStatus::Failure)];
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_success(tester.try_branch()).await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @r"
+                :broken_heart: Test failed
+                - [Workflow1](https://github.com/workflows/Workflow1/1) :white_check_mark:
+                Failed required checks: `build`
+                "
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn auto_build_ignores_bors_checks_from_branch_protection(pool: sqlx::PgPool) {
+        let gh = BorsBuilder::new(pool)
+            .github(required_checks_state(
+                r#"
+merge_queue_enabled = true
+required_checks_from_branch_protection = true
+"#,
+            ))
+            .run_test(|mut tester| async {
+                tester.create_branch("main").required_checks = vec![
+                    "build".to_string(),
+                    BORS_CHECK_RUN_NAME.to_string(),
+                    APPROVAL_STATUS_CONTEXT.to_string(),
+                ];
+                tester.create_branch(AUTO_BRANCH_NAME).check_runs =
+                    vec![("build".to_string(), CheckSuiteStatus::Success)];
+                tester.post_comment("@bors r+").await?;
+                tester.expect_comments(2).await;
+                tester.workflow_success(tester.auto_branch()).await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":sunny: Test successful"));
+                Ok(tester)
+            })
+            .await;
+        gh.check_sha_history(
+            default_repo_name(),
+            "main",
+            &["main-sha1", "merge-main-sha1-pr-1-sha-0"],
+        );
+    }
+
+    #[sqlx::test]
+    async fn try_build_failure_does_not_wait_for_required_check(pool: sqlx::PgPool) {
+        BorsBuilder::new(pool)
+            .github(required_checks_state(r#"required_checks = ["build"]"#))
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors try").await?;
+                tester.expect_comments(1).await;
+                tester.workflow_failure(tester.try_branch()).await?;
+                let comment = tester.get_comment().await?;
+                assert!(comment.starts_with(":broken_heart: Test failed"));
+                Ok(tester)
+            })
+            .await;
+    }
 }
@@ -5,16 +5,18 @@ use arc_swap::ArcSwap;
 
 pub use command::CommandParser;
 pub use command::RollupMode;
+pub use command::parse_bare_command;
 pub use comment::Comment;
 pub use context::BorsContext;
 #[cfg(test)]
 pub use handlers::WAIT_FOR_REFRESH;
-pub use handlers::{handle_bors_global_event, handle_bors_repository_event};
+pub use handlers::{handle_bors_global_event, handle_bors_repository_event, sort_merge_queue};
+pub(crate) use handlers::{report_config_error, report_config_valid};
 use serde::Serialize;
 
 use crate::config::RepositoryConfig;
-use crate::github::GithubRepoName;
 use crate::github::api::client::GithubRepositoryClient;
+use crate::github::{GithubRepoName, PullRequestNumber};
 use crate::permissions::UserPermissions;
 
 mod command;
@@ -37,6 +39,15 @@ pub struct CheckSuite {
     pub(crate) status: CheckSuiteStatus,
 }
 
+/// A GitHub check run, e.g. a single job of a GitHub actions workflow.
+/// Required status checks of branch protection refer to check runs by their name, or to
+/// commit statuses by their context.
+#[derive(Clone, Debug)]
+pub struct CheckRun {
+    pub(crate) name: String,
+    pub(crate) status: CheckSuiteStatus,
+}
+
 /// An access point to a single repository.
 /// Can be used to query permissions for the repository, and also to perform various
 /// actions using the stored client.
@@ -50,6 +61,17 @@ impl RepositoryState {
     pub fn repository(&self) -> &GithubRepoName {
         self.client.repository()
     }
+
+    /// Posts a comment to the given PR, using the comment templates of the repository
+    /// configuration.
+    pub async fn post_comment(
+        &self,
+        pr: PullRequestNumber,
+        comment: Comment,
+    ) -> anyhow::Result<()> {
+        let comment = comment.apply_templates(&self.config.load().templates, pr);
+        self.client.post_comment(pr, comment).await
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::num::NonZeroU32;
 use std::time::Duration;
 
+use octocrab::models::UserId;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer};
 
+use crate::bors::parse_bare_command;
 use crate::github::{LabelModification, LabelTrigger};
+use crate::permissions::PermissionSource;
+use crate::utils::template::Template;
 
 pub const CONFIG_FILE_PATH: &str = "rust-bors.toml";
 
@@ -21,12 +26,287 @@ pub struct RepositoryConfig {
     pub labels: HashMap<LabelTrigger, Vec<LabelModification>>,
     #[serde(default, deserialize_with = "deserialize_duration_from_secs_opt")]
     pub min_ci_time: Option<Duration>,
+    /// If enabled, approved PRs are merged automatically after CI passes on the auto branch.
+    #[serde(default)]
+    pub merge_queue_enabled: bool,
+    /// Names of checks that have to pass for a build to be considered successful.
+    #[serde(default)]
+    pub required_checks: Vec<String>,
+    /// If enabled, required status checks from the branch protection of the base branch
+    /// also have to pass for a build to be considered successful.
+    #[serde(default)]
+    pub required_checks_from_branch_protection: bool,
+    /// Source from which permissions of users are loaded.
+    #[serde(default)]
+    pub permissions: PermissionSource,
+    /// If enabled, build failure comments mention how often the failed workflows have
+    /// failed recently.
+    #[serde(default)]
+    pub flaky_annotations: bool,
+    /// Number of try branches, i.e. how many try builds can run at the same time.
+    #[serde(default = "default_try_branches")]
+    pub try_branches: NonZeroU32,
+    /// Order in which queued try builds are started.
+    #[serde(default)]
+    pub try_queue_order: TryQueueOrder,
+    /// How the commits of a PR are combined with its base branch in try and auto builds.
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
+    /// Templates that override the built-in commit messages and comments of the bot.
+    #[serde(default, deserialize_with = "deserialize_templates")]
+    pub templates: HashMap<TemplateKind, Template>,
+    /// Number of an issue where changes of the configuration are logged when it is reloaded.
+    #[serde(default)]
+    pub config_log_issue: Option<u64>,
+    /// Commands that are executed when a label is added to or removed from a PR.
+    #[serde(default)]
+    pub label_commands: HashMap<String, LabelCommands>,
+    /// What happens when a PR is merged or a base branch is pushed to outside of bors.
+    #[serde(default)]
+    pub manual_merge_policy: ManualMergePolicy,
+}
+
+/// Commands executed when a label is added to or removed from a PR, written without the bot
+/// prefix, e.g. `r+` or `rollup=always`.
+#[derive(serde::Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LabelCommands {
+    #[serde(default, deserialize_with = "deserialize_label_command")]
+    pub added: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_label_command")]
+    pub removed: Option<String>,
+}
+
+impl RepositoryConfig {
+    /// Returns the effective value of each setting, including the default ones, in a
+    /// deterministic textual form that can be compared between configurations.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let RepositoryConfig {
+            timeout,
+            labels,
+            min_ci_time,
+            merge_queue_enabled,
+            required_checks,
+            required_checks_from_branch_protection,
+            permissions,
+            flaky_annotations,
+            try_branches,
+            try_queue_order,
+            merge_strategy,
+            templates,
+            config_log_issue,
+            label_commands,
+            manual_merge_policy,
+        } = self;
+        let templates = templates
+            .iter()
+            .map(|(kind, template)| (format!("{kind:?}"), template.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        vec![
+            ("timeout", format!("{}s", timeout.as_secs())),
+            (
+                "labels",
+                format!("{:?}", labels.iter().collect::<BTreeMap<_, _>>()),
+            ),
+            (
+                "min_ci_time",
+                min_ci_time.map_or("none".to_string(), |time| format!("{}s", time.as_secs())),
+            ),
+            ("merge_queue_enabled", merge_queue_enabled.to_string()),
+            ("required_checks", format!("{required_checks:?}")),
+            (
+                "required_checks_from_branch_protection",
+                required_checks_from_branch_protection.to_string(),
+            ),
+            ("permissions", format_permission_source(permissions)),
+            ("flaky_annotations", flaky_annotations.to_string()),
+            ("try_branches", try_branches.to_string()),
+            ("try_queue_order", format!("{try_queue_order:?}")),
+            ("merge_strategy", format!("{merge_strategy:?}")),
+            ("templates", format!("{templates:?}")),
+            (
+                "config_log_issue",
+                config_log_issue.map_or("none".to_string(), |issue| format!("#{issue}")),
+            ),
+            (
+                "label_commands",
+                format!("{:?}", label_commands.iter().collect::<BTreeMap<_, _>>()),
+            ),
+            ("manual_merge_policy", format!("{manual_merge_policy:?}")),
+        ]
+    }
+}
+
+fn format_permission_source(source: &PermissionSource) -> String {
+    match source {
+        PermissionSource::Static { review, try_users } => {
+            let sorted = |users: &HashSet<UserId>| {
+                let mut users = users.iter().map(|user| user.0).collect::<Vec<_>>();
+                users.sort();
+                users
+            };
+            format!(
+                "Static {{ review: {:?}, try: {:?} }}",
+                sorted(review),
+                sorted(try_users)
+            )
+        }
+        source => format!("{source:?}"),
+    }
+}
+
+/// Variables available in templates of commit messages.
+const COMMIT_VARIABLES: &[&str] = &[
+    "pr_number",
+    "pr_title",
+    "pr_body",
+    "pr_author",
+    "pr_label",
+    "approver",
+    "repo",
+];
+
+/// Commit message or comment of the bot that can be overridden by a template
+/// in the `[templates]` section of the configuration.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    MergeCommit,
+    SquashCommit,
+    TryBuildStarted,
+    TryBuildSucceeded,
+    TryBuildInProgress,
+    TryBuildQueued,
+    TryBuildRetried,
+    TryBuildCancelled,
+    UncleanTryBuildCancelled,
+    QueuedTryBuildCancelled,
+    QueuedTryBuildFailed,
+    NoTryBuildInProgress,
+    NoFailedBuild,
+    NoFailedWorkflows,
+    CantFindLastParent,
+    BuildFailed,
+    BuildTimedOut,
+    MergeConflict,
+    AutoBuildStarted,
+    AutoBuildRetried,
+    AutoBuildRequeued,
+    AutoBuildSucceeded,
+    AutoBuildBaseMoved,
+    AutoBuildPushFailed,
+    RollupCreated,
+    RollupInProgress,
+    RollupMerged,
+    RollupFailed,
+    NoRollupCandidates,
+}
+
+impl TemplateKind {
+    /// Variables that can be referenced by templates of this kind.
+    pub fn variables(&self) -> &'static [&'static str] {
+        match self {
+            TemplateKind::MergeCommit | TemplateKind::SquashCommit => COMMIT_VARIABLES,
+            TemplateKind::TryBuildStarted | TemplateKind::AutoBuildStarted => {
+                &["pr_number", "head_sha", "merge_sha"]
+            }
+            TemplateKind::TryBuildSucceeded => &[
+                "pr_number",
+                "merge_sha",
+                "workflows",
+                "workflow_urls",
+                "duration",
+            ],
+            TemplateKind::TryBuildQueued => &["pr_number", "position"],
+            TemplateKind::TryBuildRetried | TemplateKind::AutoBuildRetried => {
+                &["pr_number", "merge_sha", "workflow_urls"]
+            }
+            TemplateKind::TryBuildCancelled => &["pr_number", "workflow_urls"],
+            TemplateKind::BuildFailed => &[
+                "pr_number",
+                "workflows",
+                "workflow_urls",
+                "failed_checks",
+                "flaky_warnings",
+                "duration",
+            ],
+            TemplateKind::BuildTimedOut => &["pr_number", "missing_checks", "duration"],
+            TemplateKind::MergeConflict => &["pr_number", "branch"],
+            TemplateKind::AutoBuildSucceeded => &[
+                "pr_number",
+                "merge_sha",
+                "approver",
+                "base_ref",
+                "workflows",
+                "workflow_urls",
+                "duration",
+            ],
+            TemplateKind::AutoBuildBaseMoved | TemplateKind::AutoBuildPushFailed => {
+                &["pr_number", "base_ref"]
+            }
+            TemplateKind::RollupCreated => &["pr_number", "rollup", "included_prs", "failed_prs"],
+            TemplateKind::RollupInProgress
+            | TemplateKind::RollupMerged
+            | TemplateKind::RollupFailed => &["pr_number", "rollup"],
+            TemplateKind::TryBuildInProgress
+            | TemplateKind::UncleanTryBuildCancelled
+            | TemplateKind::QueuedTryBuildCancelled
+            | TemplateKind::QueuedTryBuildFailed
+            | TemplateKind::NoTryBuildInProgress
+            | TemplateKind::NoFailedBuild
+            | TemplateKind::NoFailedWorkflows
+            | TemplateKind::CantFindLastParent
+            | TemplateKind::AutoBuildRequeued
+            | TemplateKind::NoRollupCandidates => &["pr_number"],
+        }
+    }
+}
+
+/// Describes how the commits of a PR are combined with its base branch.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Creates a merge commit with the base branch and the PR as its parents.
+    #[default]
+    Merge,
+    /// Creates a single commit with the changes of the PR on top of the base branch.
+    Squash,
+    /// Recreates each commit of the PR on top of the base branch.
+    Rebase,
+}
+
+/// Order in which queued try builds are started once a try branch becomes free.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TryQueueOrder {
+    /// Try builds are started in the order in which they were requested.
+    #[default]
+    Fifo,
+    /// Try builds of PRs with a higher priority are started first.
+    Priority,
+}
+
+/// Reaction of the bot to merges and pushes that bypass the merge queue.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualMergePolicy {
+    /// Manual merges and direct pushes are ignored.
+    #[default]
+    Ignore,
+    /// Manually merged PRs receive a warning comment and the bypass is recorded in the audit log.
+    Warn,
+    /// Like `Warn`, and an issue is also opened when a base branch is pushed to directly.
+    Issue,
 }
 
 fn default_timeout() -> Duration {
     Duration::from_secs(3600)
 }
 
+fn default_try_branches() -> NonZeroU32 {
+    NonZeroU32::MIN
+}
+
 fn deserialize_duration_from_secs_opt<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
 where
     D: Deserializer<'de>,
@@ -58,6 +338,18 @@ where
         Try,
         TrySucceed,
         TryFailed,
+        AutoBuildFailed,
+        Delegate,
+        Undelegate,
+        Priority,
+        Rollup,
+        TreeClosed,
+        TreeOpen,
+        MergeConflict,
+        MergeConflictResolved,
+        PushWhileApproved,
+        BuildCancelled,
+        BuildTimedOut,
     }
 
     impl From<Trigger> for LabelTrigger {
@@ -68,6 +360,18 @@ where
                 Trigger::Try => LabelTrigger::TryBuildStarted,
                 Trigger::TrySucceed => LabelTrigger::TryBuildSucceeded,
                 Trigger::TryFailed => LabelTrigger::TryBuildFailed,
+                Trigger::AutoBuildFailed => LabelTrigger::AutoBuildFailed,
+                Trigger::Delegate => LabelTrigger::Delegated,
+                Trigger::Undelegate => LabelTrigger::Undelegated,
+                Trigger::Priority => LabelTrigger::PriorityChanged,
+                Trigger::Rollup => LabelTrigger::RollupModeChanged,
+                Trigger::TreeClosed => LabelTrigger::TreeClosed,
+                Trigger::TreeOpen => LabelTrigger::TreeOpened,
+                Trigger::MergeConflict => LabelTrigger::MergeConflict,
+                Trigger::MergeConflictResolved => LabelTrigger::MergeConflictResolved,
+                Trigger::PushWhileApproved => LabelTrigger::PushedWhileApproved,
+                Trigger::BuildCancelled => LabelTrigger::BuildCancelled,
+                Trigger::BuildTimedOut => LabelTrigger::BuildTimedOut,
             }
         }
     }
@@ -133,11 +437,48 @@ where
     Ok(triggers)
 }
 
+fn deserialize_label_command<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let command = String::deserialize(deserializer)?;
+    if let Err(error) = parse_bare_command(&command) {
+        return Err(Error::custom(format!(
+            "Invalid label command `{command}`: {error:?}"
+        )));
+    }
+    Ok(Some(command))
+}
+
+fn deserialize_templates<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<TemplateKind, Template>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    HashMap::<TemplateKind, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(kind, text)| {
+            Template::parse(&text, kind.variables())
+                .map(|template| (kind, template))
+                .map_err(|error| Error::custom(format!("Invalid template `{kind:?}`: {error}")))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{collections::BTreeMap, time::Duration};
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::time::Duration;
 
-    use crate::config::{RepositoryConfig, default_timeout};
+    use octocrab::models::UserId;
+
+    use crate::config::{
+        LabelCommands, ManualMergePolicy, MergeStrategy, RepositoryConfig, TemplateKind,
+        TryQueueOrder, default_timeout,
+    };
+    use crate::github::{LabelModification, LabelTrigger};
+    use crate::permissions::{CollaboratorPermission, PermissionSource};
 
     #[test]
     fn deserialize_empty() {
@@ -167,6 +508,135 @@ mod tests {
         assert_eq!(config.min_ci_time, Some(Duration::from_secs(3600)));
     }
 
+    #[test]
+    fn deserialize_merge_queue_enabled_default() {
+        let content = "";
+        let config = load_config(content);
+        assert!(!config.merge_queue_enabled);
+    }
+
+    #[test]
+    fn deserialize_merge_queue_enabled() {
+        let content = "merge_queue_enabled = true";
+        let config = load_config(content);
+        assert!(config.merge_queue_enabled);
+    }
+
+    #[test]
+    fn deserialize_try_branches_default() {
+        let content = "";
+        let config = load_config(content);
+        assert_eq!(config.try_branches.get(), 1);
+    }
+
+    #[test]
+    fn deserialize_try_branches() {
+        let content = "try_branches = 3";
+        let config = load_config(content);
+        assert_eq!(config.try_branches.get(), 3);
+    }
+
+    #[test]
+    fn deserialize_merge_strategy_default() {
+        let config = load_config("");
+        assert_eq!(config.merge_strategy, MergeStrategy::Merge);
+    }
+
+    #[test]
+    fn deserialize_merge_strategy() {
+        let config = load_config(r#"merge_strategy = "rebase""#);
+        assert_eq!(config.merge_strategy, MergeStrategy::Rebase);
+    }
+
+    #[test]
+    fn settings_include_defaults() {
+        let config = load_config("timeout = 10\nconfig_log_issue = 5");
+        let settings = config.settings().into_iter().collect::<HashMap<_, _>>();
+        assert_eq!(settings["timeout"], "10s");
+        assert_eq!(settings["merge_strategy"], "Merge");
+        assert_eq!(settings["config_log_issue"], "#5");
+    }
+
+    #[test]
+    fn deserialize_try_queue_order_default() {
+        let config = load_config("");
+        assert_eq!(config.try_queue_order, TryQueueOrder::Fifo);
+    }
+
+    #[test]
+    fn deserialize_try_queue_order() {
+        let config = load_config(r#"try_queue_order = "priority""#);
+        assert_eq!(config.try_queue_order, TryQueueOrder::Priority);
+    }
+
+    #[test]
+    fn deserialize_manual_merge_policy_default() {
+        let config = load_config("");
+        assert_eq!(config.manual_merge_policy, ManualMergePolicy::Ignore);
+    }
+
+    #[test]
+    fn deserialize_manual_merge_policy() {
+        let config = load_config(r#"manual_merge_policy = "issue""#);
+        assert_eq!(config.manual_merge_policy, ManualMergePolicy::Issue);
+    }
+
+    #[test]
+    #[should_panic]
+    fn deserialize_zero_try_branches() {
+        load_config("try_branches = 0");
+    }
+
+    #[test]
+    fn deserialize_required_checks() {
+        let content = r#"required_checks = ["build", "test"]
+required_checks_from_branch_protection = true
+"#;
+        let config = load_config(content);
+        assert_eq!(config.required_checks, vec!["build", "test"]);
+        assert!(config.required_checks_from_branch_protection);
+    }
+
+    #[test]
+    fn deserialize_permissions_default() {
+        let content = "";
+        let config = load_config(content);
+        assert_eq!(config.permissions, PermissionSource::TeamApi);
+    }
+
+    #[test]
+    fn deserialize_permissions_static() {
+        let content = r#"[permissions]
+source = "static"
+review = [1, 2]
+try = [3]
+"#;
+        let config = load_config(content);
+        assert_eq!(
+            config.permissions,
+            PermissionSource::Static {
+                review: HashSet::from([UserId(1), UserId(2)]),
+                try_users: HashSet::from([UserId(3)]),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_permissions_collaborators() {
+        let content = r#"[permissions]
+source = "collaborators"
+try = "triage"
+"#;
+        let config = load_config(content);
+        assert_eq!(
+            config.permissions,
+            PermissionSource::Collaborators {
+                review: CollaboratorPermission::Maintain,
+                try_level: CollaboratorPermission::Triage,
+            }
+        );
+    }
+
     #[test]
     fn deserialize_labels() {
         let content = r#"[labels]
@@ -221,6 +691,107 @@ try = ["foo"]
         load_config(content);
     }
 
+    #[test]
+    fn deserialize_lifecycle_labels() {
+        let content = r#"[labels]
+delegate = ["+delegated"]
+undelegate = ["-delegated"]
+priority = ["+p-changed"]
+rollup = ["+rollup-changed"]
+tree_closed = ["+tree-closed"]
+tree_open = ["-tree-closed"]
+merge_conflict = ["+conflict"]
+merge_conflict_resolved = ["-conflict"]
+push_while_approved = ["+pushed"]
+build_cancelled = ["+cancelled"]
+build_timed_out = ["+timed-out"]
+"#;
+        let config = load_config(content);
+        let labels = config.labels;
+        assert_eq!(labels.len(), 11);
+        assert_eq!(
+            labels[&LabelTrigger::Delegated],
+            vec![LabelModification::Add("delegated".to_string())]
+        );
+        assert_eq!(
+            labels[&LabelTrigger::TreeOpened],
+            vec![LabelModification::Remove("tree-closed".to_string())]
+        );
+        assert_eq!(
+            labels[&LabelTrigger::MergeConflictResolved],
+            vec![LabelModification::Remove("conflict".to_string())]
+        );
+        assert_eq!(
+            labels[&LabelTrigger::BuildTimedOut],
+            vec![LabelModification::Add("timed-out".to_string())]
+        );
+    }
+
+    #[test]
+    fn deserialize_label_commands() {
+        let content = r#"[label_commands]
+S-waiting-on-bors = { added = "r+", removed = "r-" }
+bors-try = { added = "try" }
+"#;
+        let config = load_config(content);
+        assert_eq!(
+            config.label_commands["S-waiting-on-bors"],
+            LabelCommands {
+                added: Some("r+".to_string()),
+                removed: Some("r-".to_string()),
+            }
+        );
+        assert_eq!(
+            config.label_commands["bors-try"],
+            LabelCommands {
+                added: Some("try".to_string()),
+                removed: None,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid label command `foo`")]
+    fn deserialize_label_commands_unknown_command() {
+        let content = r#"[label_commands]
+bors-try = { added = "foo" }
+"#;
+        load_config(content);
+    }
+
+    #[test]
+    fn deserialize_templates() {
+        let content = r#"[templates]
+try_build_started = ":rocket: Testing {merge_sha} for #{pr_number}"
+"#;
+        let config = load_config(content);
+        let template = &config.templates[&TemplateKind::TryBuildStarted];
+        let values = HashMap::from([
+            ("pr_number", "1".to_string()),
+            ("merge_sha", "abc".to_string()),
+        ]);
+        assert_eq!(template.render(&values), ":rocket: Testing abc for #1");
+        assert!(!config.templates.contains_key(&TemplateKind::MergeCommit));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid template `MergeCommit`: Unknown variable `merge_sha`")]
+    fn deserialize_templates_unknown_variable() {
+        let content = r#"[templates]
+merge_commit = "Merge {merge_sha}"
+"#;
+        load_config(content);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown variant `foo`")]
+    fn deserialize_templates_unknown_kind() {
+        let content = r#"[templates]
+foo = "bar"
+"#;
+        load_config(content);
+    }
+
     fn load_config(config: &str) -> RepositoryConfig {
         toml::from_str(config).unwrap()
     }
@@ -1,22 +1,34 @@
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
 use crate::bors::{PullRequestStatus, RollupMode};
 use crate::database::{
-    BuildModel, BuildStatus, PullRequestModel, RepoModel, TreeState, WorkflowModel, WorkflowStatus,
+    AuditEntryKind, AuditEntryModel, BuildModel, BuildStatus, ConfigErrorModel, PullRequestFilter,
+    PullRequestModel, RepoModel, TreeState, WorkflowModel, WorkflowStats, WorkflowStatus,
     WorkflowType,
 };
 use crate::github::PullRequestNumber;
 use crate::github::{CommitSha, GithubRepoName};
 
 use super::operations::{
-    approve_pull_request, create_build, create_pull_request, create_workflow,
-    delegate_pull_request, find_build, find_pr_by_build, get_pull_request, get_repository,
-    get_running_builds, get_workflow_urls_for_build, get_workflows_for_build, set_pr_priority,
-    set_pr_rollup, set_pr_status, unapprove_pull_request, undelegate_pull_request,
-    update_build_status, update_mergeable_states_by_base_branch, update_pr_build_id,
+    approve_pull_request, clear_config_error, clear_rollup_pr, create_audit_entry, create_build,
+    create_pull_request, create_workflow, delegate_pull_request, delete_finished_events,
+    delete_try_request, delete_try_request_for_pr, enqueue_event, enqueue_try_request, find_build,
+    find_pr_by_build, get_audit_entries, get_build, get_config_error, get_config_errors,
+    get_due_events, get_event_by_delivery_id, get_open_pull_requests, get_pull_request,
+    get_pull_request_by_id, get_pull_requests, get_repositories, get_repository,
+    get_running_builds, get_try_requests, get_workflow_stats, get_workflow_urls_for_build,
+    get_workflows_for_build, mark_event_done, mark_event_failed, record_try_request_failure,
+    restart_workflow, retry_build, set_build_check_run_id, set_config_error,
+    set_pr_mergeable_state, set_pr_priority, set_pr_rollup, set_pr_status, set_rollup_pr,
+    unapprove_pull_request, undelegate_pull_request, update_build_status,
+    update_mergeable_states_by_base_branch, update_pr_auto_build_id, update_pr_build_id,
     update_workflow_status, upsert_pull_request, upsert_repository,
 };
-use super::{ApprovalInfo, DelegatedPermission, MergeableState, RunId};
+use super::{
+    ApprovalInfo, DelegatedPermission, MergeableState, QueuedEventModel, QueuedEventStatus, RunId,
+    TryRequestModel,
+};
 
 /// Provides access to a database using sqlx operations.
 #[derive(Clone)]
@@ -68,6 +80,14 @@ impl PgDbClient {
         update_mergeable_states_by_base_branch(&self.pool, repo, base_branch, mergeable_state).await
     }
 
+    pub async fn set_mergeable_state(
+        &self,
+        pr: &PullRequestModel,
+        mergeable_state: MergeableState,
+    ) -> anyhow::Result<()> {
+        set_pr_mergeable_state(&self.pool, pr.id, mergeable_state).await
+    }
+
     pub async fn set_rollup(
         &self,
         pr: &PullRequestModel,
@@ -76,6 +96,21 @@ impl PgDbClient {
         set_pr_rollup(&self.pool, pr.id, rollup).await
     }
 
+    /// Marks the given PRs as being included in the rollup PR `rollup`.
+    pub async fn set_rollup_pr(
+        &self,
+        prs: &[PullRequestModel],
+        rollup: &PullRequestModel,
+    ) -> anyhow::Result<()> {
+        let pr_ids: Vec<i32> = prs.iter().map(|pr| pr.id).collect();
+        set_rollup_pr(&self.pool, &pr_ids, rollup.id).await
+    }
+
+    /// Removes all PRs from the rollup PR `rollup`.
+    pub async fn clear_rollup_pr(&self, rollup: &PullRequestModel) -> anyhow::Result<()> {
+        clear_rollup_pr(&self.pool, rollup.id).await
+    }
+
     pub async fn get_pull_request(
         &self,
         repo: &GithubRepoName,
@@ -84,6 +119,30 @@ impl PgDbClient {
         get_pull_request(&self.pool, repo, pr_number).await
     }
 
+    pub async fn get_pull_request_by_id(
+        &self,
+        pr_id: i32,
+    ) -> anyhow::Result<Option<PullRequestModel>> {
+        get_pull_request_by_id(&self.pool, pr_id).await
+    }
+
+    pub async fn get_open_pull_requests(
+        &self,
+        repo: &GithubRepoName,
+    ) -> anyhow::Result<Vec<PullRequestModel>> {
+        get_open_pull_requests(&self.pool, repo).await
+    }
+
+    pub async fn get_pull_requests(
+        &self,
+        repo: &GithubRepoName,
+        filter: &PullRequestFilter,
+        limit: i64,
+        offset: i64,
+    ) -> anyhow::Result<Vec<PullRequestModel>> {
+        get_pull_requests(&self.pool, repo, filter, limit, offset).await
+    }
+
     pub async fn get_or_create_pull_request(
         &self,
         repo: &GithubRepoName,
@@ -130,19 +189,36 @@ impl PgDbClient {
         find_pr_by_build(&self.pool, build.id).await
     }
 
+    /// Creates a try build of the PR and returns its ID.
     pub async fn attach_try_build(
         &self,
-        pr: PullRequestModel,
+        pr: &PullRequestModel,
         branch: String,
         commit_sha: CommitSha,
         parent: CommitSha,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<i32> {
         let mut tx = self.pool.begin().await?;
         let build_id =
             create_build(&mut *tx, &pr.repository, &branch, &commit_sha, &parent).await?;
         update_pr_build_id(&mut *tx, pr.id, build_id).await?;
         tx.commit().await?;
-        Ok(())
+        Ok(build_id)
+    }
+
+    /// Creates an auto build of the PR and returns its ID.
+    pub async fn attach_auto_build(
+        &self,
+        pr: PullRequestModel,
+        branch: String,
+        commit_sha: CommitSha,
+        parent: CommitSha,
+    ) -> anyhow::Result<i32> {
+        let mut tx = self.pool.begin().await?;
+        let build_id =
+            create_build(&mut *tx, &pr.repository, &branch, &commit_sha, &parent).await?;
+        update_pr_auto_build_id(&mut *tx, pr.id, build_id).await?;
+        tx.commit().await?;
+        Ok(build_id)
     }
 
     pub async fn find_build(
@@ -154,6 +230,14 @@ impl PgDbClient {
         find_build(&self.pool, repo, &branch, &commit_sha).await
     }
 
+    pub async fn get_build(
+        &self,
+        repo: &GithubRepoName,
+        build_id: i32,
+    ) -> anyhow::Result<Option<BuildModel>> {
+        get_build(&self.pool, repo, build_id).await
+    }
+
     pub async fn get_running_builds(
         &self,
         repo: &GithubRepoName,
@@ -169,6 +253,26 @@ impl PgDbClient {
         update_build_status(&self.pool, build.id, status).await
     }
 
+    pub async fn set_build_check_run_id(
+        &self,
+        build_id: i32,
+        check_run_id: u64,
+    ) -> anyhow::Result<()> {
+        set_build_check_run_id(&self.pool, build_id, check_run_id as i64).await
+    }
+
+    /// Marks a failed build as pending again and resets the status of the given workflows,
+    /// which are being re-run.
+    pub async fn retry_build(&self, build: &BuildModel, run_ids: &[RunId]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        retry_build(&mut *tx, build.id).await?;
+        for run_id in run_ids {
+            restart_workflow(&mut *tx, run_id.0).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
     pub async fn create_workflow(
         &self,
         build: &BuildModel,
@@ -205,6 +309,14 @@ impl PgDbClient {
         get_workflows_for_build(&self.pool, build.id).await
     }
 
+    pub async fn get_workflow_stats(
+        &self,
+        repo: &GithubRepoName,
+        days: u32,
+    ) -> anyhow::Result<Vec<WorkflowStats>> {
+        get_workflow_stats(&self.pool, repo, days).await
+    }
+
     pub async fn get_workflow_urls_for_build(
         &self,
         build: &BuildModel,
@@ -232,6 +344,67 @@ impl PgDbClient {
         get_repository(&self.pool, repo).await
     }
 
+    pub async fn get_repositories(&self) -> anyhow::Result<Vec<(GithubRepoName, TreeState)>> {
+        get_repositories(&self.pool).await
+    }
+
+    /// Stores a received webhook in the event queue and returns its ID.
+    /// Returns `None` if the delivery has already been stored before.
+    pub async fn enqueue_event(
+        &self,
+        delivery_id: Option<&str>,
+        event_type: &str,
+        repository: Option<&str>,
+        payload: &str,
+        status: QueuedEventStatus,
+    ) -> anyhow::Result<Option<i32>> {
+        enqueue_event(
+            &self.pool,
+            delivery_id,
+            event_type,
+            repository,
+            payload,
+            status,
+        )
+        .await
+    }
+
+    pub async fn get_event_by_delivery_id(
+        &self,
+        delivery_id: &str,
+    ) -> anyhow::Result<Option<QueuedEventModel>> {
+        get_event_by_delivery_id(&self.pool, delivery_id).await
+    }
+
+    pub async fn get_due_events(
+        &self,
+        limit: i64,
+        exclude: &[i32],
+    ) -> anyhow::Result<Vec<QueuedEventModel>> {
+        get_due_events(&self.pool, limit, exclude).await
+    }
+
+    pub async fn mark_event_done(&self, event: &QueuedEventModel) -> anyhow::Result<()> {
+        mark_event_done(&self.pool, event.id).await
+    }
+
+    pub async fn mark_event_failed(
+        &self,
+        event: &QueuedEventModel,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> anyhow::Result<QueuedEventStatus> {
+        mark_event_failed(&self.pool, event.id, error, next_attempt_at, max_attempts).await
+    }
+
+    pub async fn delete_finished_events(
+        &self,
+        received_before: DateTime<Utc>,
+    ) -> anyhow::Result<u64> {
+        delete_finished_events(&self.pool, received_before).await
+    }
+
     pub async fn upsert_repository(
         &self,
         repo: &GithubRepoName,
@@ -239,4 +412,83 @@ impl PgDbClient {
     ) -> anyhow::Result<()> {
         upsert_repository(&self.pool, repo, tree_state).await
     }
+
+    pub async fn get_config_error(
+        &self,
+        repo: &GithubRepoName,
+    ) -> anyhow::Result<Option<ConfigErrorModel>> {
+        get_config_error(&self.pool, repo).await
+    }
+
+    pub async fn get_config_errors(&self) -> anyhow::Result<Vec<ConfigErrorModel>> {
+        get_config_errors(&self.pool).await
+    }
+
+    /// Records that the given `revision` of the configuration of a repository could not be
+    /// loaded.
+    pub async fn set_config_error(
+        &self,
+        repo: &GithubRepoName,
+        revision: &str,
+        error: &str,
+    ) -> anyhow::Result<()> {
+        set_config_error(&self.pool, repo, revision, error).await
+    }
+
+    pub async fn clear_config_error(&self, repo: &GithubRepoName) -> anyhow::Result<()> {
+        clear_config_error(&self.pool, repo).await
+    }
+
+    pub async fn enqueue_try_request(
+        &self,
+        pr: &PullRequestModel,
+        requester: &str,
+        parent: Option<&str>,
+        jobs: &[String],
+    ) -> anyhow::Result<()> {
+        enqueue_try_request(&self.pool, pr.id, requester, parent, jobs).await
+    }
+
+    pub async fn get_try_requests(
+        &self,
+        repo: &GithubRepoName,
+    ) -> anyhow::Result<Vec<TryRequestModel>> {
+        get_try_requests(&self.pool, repo).await
+    }
+
+    pub async fn delete_try_request(&self, request: &TryRequestModel) -> anyhow::Result<()> {
+        delete_try_request(&self.pool, request.id).await
+    }
+
+    /// Records a failed attempt to start the given try request and returns the number of
+    /// failed attempts.
+    pub async fn record_try_request_failure(
+        &self,
+        request: &TryRequestModel,
+    ) -> anyhow::Result<i32> {
+        record_try_request_failure(&self.pool, request.id).await
+    }
+
+    pub async fn delete_try_request_for_pr(&self, pr: &PullRequestModel) -> anyhow::Result<bool> {
+        delete_try_request_for_pr(&self.pool, pr.id).await
+    }
+
+    /// Records an event that bypassed bors in the audit log of the repository.
+    pub async fn create_audit_entry(
+        &self,
+        repo: &GithubRepoName,
+        kind: AuditEntryKind,
+        branch: &str,
+        sha: &CommitSha,
+        pr_number: Option<PullRequestNumber>,
+    ) -> anyhow::Result<()> {
+        create_audit_entry(&self.pool, repo, kind, branch, sha, pr_number).await
+    }
+
+    pub async fn get_audit_entries(
+        &self,
+        repo: &GithubRepoName,
+    ) -> anyhow::Result<Vec<AuditEntryModel>> {
+        get_audit_entries(&self.pool, repo).await
+    }
 }
@@ -186,6 +186,17 @@ pub enum MergeableState {
     Unknown,
 }
 
+impl fmt::Display for MergeableState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            MergeableState::Mergeable => "mergeable",
+            MergeableState::HasConflicts => "has_conflicts",
+            MergeableState::Unknown => "unknown",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl From<OctocrabMergeableState> for MergeableState {
     fn from(state: OctocrabMergeableState) -> Self {
         match state {
@@ -251,6 +262,19 @@ pub enum BuildStatus {
     Timeouted,
 }
 
+impl fmt::Display for BuildStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            BuildStatus::Pending => "pending",
+            BuildStatus::Success => "success",
+            BuildStatus::Failure => "failure",
+            BuildStatus::Cancelled => "cancelled",
+            BuildStatus::Timeouted => "timeouted",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// Represents a single (merged) commit.
 #[derive(Debug, sqlx::Type)]
 #[sqlx(type_name = "build")]
@@ -262,6 +286,19 @@ pub struct BuildModel {
     pub status: BuildStatus,
     pub parent: String,
     pub created_at: DateTime<Utc>,
+    /// How many times were the failed workflows of this build re-run.
+    pub retry_count: i32,
+    /// When were the workflows of this build re-run for the last time.
+    pub retried_at: Option<DateTime<Utc>>,
+    /// ID of the `bors` check run that reports the state of this build on the PR head commit.
+    pub check_run_id: Option<i64>,
+}
+
+impl BuildModel {
+    /// Time when the currently running attempt of the build has started.
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.retried_at.unwrap_or(self.created_at)
+    }
 }
 
 /// Represents a pull request.
@@ -277,7 +314,10 @@ pub struct PullRequestModel {
     pub delegated_permission: Option<DelegatedPermission>,
     pub priority: Option<i32>,
     pub rollup: Option<RollupMode>,
+    /// The rollup PR that includes this PR, if any.
+    pub rollup_pr_id: Option<PrimaryKey>,
     pub try_build: Option<BuildModel>,
+    pub auto_build: Option<BuildModel>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -301,6 +341,15 @@ impl PullRequestModel {
     }
 }
 
+/// Criteria for selecting pull requests of a repository.
+/// Criteria that are `None` match every pull request.
+#[derive(Debug, Default)]
+pub struct PullRequestFilter {
+    pub status: Option<PullRequestStatus>,
+    pub approved: Option<bool>,
+    pub rollup: Option<RollupMode>,
+}
+
 /// Describes whether a workflow is a Github Actions workflow or if it's a job from some external
 /// CI.
 #[derive(Debug, PartialEq, sqlx::Type)]
@@ -311,6 +360,16 @@ pub enum WorkflowType {
     External,
 }
 
+impl fmt::Display for WorkflowType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WorkflowType::Github => "github",
+            WorkflowType::External => "external",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// Status of a workflow.
 #[derive(Debug, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "TEXT")]
@@ -324,6 +383,17 @@ pub enum WorkflowStatus {
     Failure,
 }
 
+impl fmt::Display for WorkflowStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WorkflowStatus::Pending => "pending",
+            WorkflowStatus::Success => "success",
+            WorkflowStatus::Failure => "failure",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// Represents a workflow run, coming either from Github Actions or from some external CI.
 pub struct WorkflowModel {
     pub id: PrimaryKey,
@@ -336,6 +406,32 @@ pub struct WorkflowModel {
     pub created_at: DateTime<Utc>,
 }
 
+/// Number of days of workflow history that is used to compute workflow statistics by default.
+pub const WORKFLOW_STATS_DAYS: u32 = 30;
+
+/// Aggregated results of the recent runs of a single workflow, used to find flaky workflows.
+#[derive(Debug)]
+pub struct WorkflowStats {
+    pub name: String,
+    /// Number of finished attempts, including re-runs of failed workflows.
+    pub attempts: i64,
+    /// Number of attempts that have failed.
+    pub failed_attempts: i64,
+    /// Number of workflows that have failed, but then passed when re-run on the same commit.
+    pub passed_on_retry: i64,
+}
+
+impl WorkflowStats {
+    /// Ratio of failed attempts, between 0 and 1.
+    pub fn failure_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.failed_attempts as f64 / self.attempts as f64
+        }
+    }
+}
+
 /// Represents the state of a repository's tree.
 #[derive(Debug, PartialEq, Clone)]
 pub enum TreeState {
@@ -381,3 +477,102 @@ pub struct RepoModel {
     pub tree_state: TreeState,
     pub created_at: DateTime<Utc>,
 }
+
+/// Error that prevented the configuration file of a repository from being loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigErrorModel {
+    pub repository: GithubRepoName,
+    /// SHA of the revision of the configuration file that could not be loaded.
+    pub revision: String,
+    pub error: String,
+}
+
+/// Kind of an event that bypassed bors and was recorded in the audit log.
+#[derive(Debug, PartialEq, Clone, Copy, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AuditEntryKind {
+    /// A PR was merged without a successful bors build.
+    ManualMerge,
+    /// A base branch was moved to a commit that was not produced by a bors build.
+    DirectPush,
+}
+
+/// An entry of the audit log of a repository.
+#[derive(Clone, Debug)]
+pub struct AuditEntryModel {
+    pub id: PrimaryKey,
+    pub repository: GithubRepoName,
+    pub kind: AuditEntryKind,
+    pub branch: String,
+    pub sha: String,
+    /// The PR that was merged, if the entry is related to a PR.
+    pub pr_number: Option<PullRequestNumber>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Processing state of an event stored in the event queue.
+#[derive(Debug, PartialEq, Clone, Copy, sqlx::Type)]
+#[sqlx(type_name = "TEXT")]
+#[sqlx(rename_all = "lowercase")]
+pub enum QueuedEventStatus {
+    /// The event is waiting to be handled, possibly after a failed attempt.
+    Pending,
+    /// The event has been handled successfully.
+    Done,
+    /// Handling of the event has failed too many times, it will not be retried anymore.
+    Dead,
+    /// Bors is not interested in the event, it was only recorded.
+    Ignored,
+}
+
+impl fmt::Display for QueuedEventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            QueuedEventStatus::Pending => "pending",
+            QueuedEventStatus::Done => "done",
+            QueuedEventStatus::Dead => "dead",
+            QueuedEventStatus::Ignored => "ignored",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A request for a try build that waits until a try branch becomes free.
+#[derive(Debug)]
+pub struct TryRequestModel {
+    pub id: PrimaryKey,
+    pub repository: GithubRepoName,
+    pub pr_number: PullRequestNumber,
+    /// GitHub login of the user who has requested the try build.
+    pub requester: String,
+    /// Parent of the try build, either a commit SHA or `last`.
+    pub parent: Option<String>,
+    pub jobs: Vec<String>,
+    /// How many times starting the try build has failed.
+    pub failed_attempts: i32,
+    /// Priority of the PR at the time when the queue was loaded.
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A webhook event that was received by bors and stored until it is handled.
+#[derive(Debug)]
+pub struct QueuedEventModel {
+    pub id: PrimaryKey,
+    /// Value of the `X-GitHub-Delivery` header of the webhook.
+    /// Replayed events do not have a delivery ID.
+    pub delivery_id: Option<String>,
+    /// Value of the `X-GitHub-Event` header of the webhook.
+    pub event_type: String,
+    /// Raw JSON payload of the webhook.
+    pub payload: String,
+    /// Full name of the repository that the event belongs to, if any.
+    pub repository: Option<String>,
+    pub status: QueuedEventStatus,
+    /// How many times bors has tried to handle the event.
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
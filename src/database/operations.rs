@@ -14,12 +14,20 @@ use crate::utils::timing::measure_db_query;
 
 use super::ApprovalInfo;
 use super::ApprovalStatus;
+use super::AuditEntryKind;
+use super::AuditEntryModel;
 use super::BuildModel;
+use super::ConfigErrorModel;
 use super::DelegatedPermission;
 use super::MergeableState;
+use super::PullRequestFilter;
 use super::PullRequestModel;
+use super::QueuedEventModel;
+use super::QueuedEventStatus;
 use super::RunId;
 use super::TreeState;
+use super::TryRequestModel;
+use super::WorkflowStats;
 use super::WorkflowStatus;
 use super::WorkflowType;
 
@@ -43,13 +51,16 @@ pub(crate) async fn get_pull_request(
         pr.status as "pr_status: PullRequestStatus",
         pr.priority,
         pr.rollup as "rollup: RollupMode",
+        pr.rollup_pr_id,
         pr.delegated_permission as "delegated_permission: DelegatedPermission",
         pr.base_branch,
         pr.mergeable_state as "mergeable_state: MergeableState",
         pr.created_at as "created_at: DateTime<Utc>",
-        build AS "try_build: BuildModel"
+        build AS "try_build: BuildModel",
+        auto_build AS "auto_build: BuildModel"
     FROM pull_request as pr
     LEFT JOIN build ON pr.build_id = build.id
+    LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
     WHERE pr.repository = $1 AND
           pr.number = $2
     "#,
@@ -64,6 +75,47 @@ pub(crate) async fn get_pull_request(
     .await
 }
 
+pub(crate) async fn get_pull_request_by_id(
+    executor: impl PgExecutor<'_>,
+    pr_id: i32,
+) -> anyhow::Result<Option<PullRequestModel>> {
+    measure_db_query("get_pull_request_by_id", || async {
+        let record = sqlx::query_as!(
+            PullRequestModel,
+            r#"
+    SELECT
+        pr.id,
+        pr.repository as "repository: GithubRepoName",
+        pr.number as "number!: i64",
+        (
+            pr.approved_by,
+            pr.approved_sha
+        ) AS "approval_status!: ApprovalStatus",
+        pr.status as "pr_status: PullRequestStatus",
+        pr.priority,
+        pr.rollup as "rollup: RollupMode",
+        pr.rollup_pr_id,
+        pr.delegated_permission as "delegated_permission: DelegatedPermission",
+        pr.base_branch,
+        pr.mergeable_state as "mergeable_state: MergeableState",
+        pr.created_at as "created_at: DateTime<Utc>",
+        build AS "try_build: BuildModel",
+        auto_build AS "auto_build: BuildModel"
+    FROM pull_request as pr
+    LEFT JOIN build ON pr.build_id = build.id
+    LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
+    WHERE pr.id = $1
+    "#,
+            pr_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(record)
+    })
+    .await
+}
+
 pub(crate) async fn create_pull_request(
     executor: impl PgExecutor<'_>,
     repo: &GithubRepoName,
@@ -142,13 +194,16 @@ pub(crate) async fn upsert_pull_request(
                 pr.status as "pr_status: PullRequestStatus",
                 pr.priority,
                 pr.rollup as "rollup: RollupMode",
+                pr.rollup_pr_id,
                 pr.delegated_permission as "delegated_permission: DelegatedPermission",
                 pr.base_branch,
                 pr.mergeable_state as "mergeable_state: MergeableState",
                 pr.created_at as "created_at: DateTime<Utc>",
-                build AS "try_build: BuildModel"
+                build AS "try_build: BuildModel",
+                auto_build AS "auto_build: BuildModel"
             FROM upserted_pr as pr
             LEFT JOIN build ON pr.build_id = build.id
+            LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
             "#,
             repo as &GithubRepoName,
             pr_number.0 as i32,
@@ -163,6 +218,122 @@ pub(crate) async fn upsert_pull_request(
     .await
 }
 
+/// Returns all open (non-draft) pull requests of the given repository.
+pub(crate) async fn get_open_pull_requests(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+) -> anyhow::Result<Vec<PullRequestModel>> {
+    measure_db_query("get_open_pull_requests", || async {
+        let records = sqlx::query_as!(
+            PullRequestModel,
+            r#"
+    SELECT
+        pr.id,
+        pr.repository as "repository: GithubRepoName",
+        pr.number as "number!: i64",
+        (
+            pr.approved_by,
+            pr.approved_sha
+        ) AS "approval_status!: ApprovalStatus",
+        pr.status as "pr_status: PullRequestStatus",
+        pr.priority,
+        pr.rollup as "rollup: RollupMode",
+        pr.rollup_pr_id,
+        pr.delegated_permission as "delegated_permission: DelegatedPermission",
+        pr.base_branch,
+        pr.mergeable_state as "mergeable_state: MergeableState",
+        pr.created_at as "created_at: DateTime<Utc>",
+        build AS "try_build: BuildModel",
+        auto_build AS "auto_build: BuildModel"
+    FROM pull_request as pr
+    LEFT JOIN build ON pr.build_id = build.id
+    LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
+    WHERE pr.repository = $1 AND
+          pr.status = 'open'
+    "#,
+            repo as &GithubRepoName
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(records)
+    })
+    .await
+}
+
+pub(crate) async fn get_pull_requests(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+    filter: &PullRequestFilter,
+    limit: i64,
+    offset: i64,
+) -> anyhow::Result<Vec<PullRequestModel>> {
+    measure_db_query("get_pull_requests", || async {
+        let records = sqlx::query_as!(
+            PullRequestModel,
+            r#"
+    SELECT
+        pr.id,
+        pr.repository as "repository: GithubRepoName",
+        pr.number as "number!: i64",
+        (
+            pr.approved_by,
+            pr.approved_sha
+        ) AS "approval_status!: ApprovalStatus",
+        pr.status as "pr_status: PullRequestStatus",
+        pr.priority,
+        pr.rollup as "rollup: RollupMode",
+        pr.rollup_pr_id,
+        pr.delegated_permission as "delegated_permission: DelegatedPermission",
+        pr.base_branch,
+        pr.mergeable_state as "mergeable_state: MergeableState",
+        pr.created_at as "created_at: DateTime<Utc>",
+        build AS "try_build: BuildModel",
+        auto_build AS "auto_build: BuildModel"
+    FROM pull_request as pr
+    LEFT JOIN build ON pr.build_id = build.id
+    LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
+    WHERE pr.repository = $1
+      AND ($2::TEXT IS NULL OR pr.status = $2)
+      AND ($3::BOOLEAN IS NULL OR (pr.approved_by IS NOT NULL) = $3)
+      AND ($4::TEXT IS NULL OR pr.rollup = $4)
+    ORDER BY pr.number
+    LIMIT $5
+    OFFSET $6
+    "#,
+            repo as &GithubRepoName,
+            filter.status.as_ref().map(|status| status.to_string()),
+            filter.approved,
+            filter.rollup.map(|rollup| rollup.to_string()),
+            limit,
+            offset
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(records)
+    })
+    .await
+}
+
+pub(crate) async fn set_pr_mergeable_state(
+    executor: impl PgExecutor<'_>,
+    pr_id: i32,
+    mergeable_state: MergeableState,
+) -> anyhow::Result<()> {
+    measure_db_query("set_pr_mergeable_state", || async {
+        sqlx::query!(
+            "UPDATE pull_request SET mergeable_state = $1 WHERE id = $2",
+            mergeable_state as _,
+            pr_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
 pub(crate) async fn update_mergeable_states_by_base_branch(
     executor: impl PgExecutor<'_>,
     repo: &GithubRepoName,
@@ -206,7 +377,8 @@ UPDATE pull_request
 SET approved_by = $1,
     approved_sha = $2,
     priority = COALESCE($3, priority),
-    rollup = COALESCE($4, rollup)
+    rollup = COALESCE($4, rollup),
+    auto_build_id = NULL
 WHERE id = $5
 "#,
             approval_info.approver,
@@ -294,11 +466,14 @@ SELECT
     pr.base_branch,
     pr.mergeable_state as "mergeable_state: MergeableState",
     pr.rollup as "rollup: RollupMode",
+    pr.rollup_pr_id,
     pr.created_at as "created_at: DateTime<Utc>",
-    build AS "try_build: BuildModel"
+    build AS "try_build: BuildModel",
+    auto_build AS "auto_build: BuildModel"
 FROM pull_request as pr
 LEFT JOIN build ON pr.build_id = build.id
-WHERE build.id = $1
+LEFT JOIN build AS auto_build ON pr.auto_build_id = auto_build.id
+WHERE build.id = $1 OR auto_build.id = $1
 "#,
             build_id
         )
@@ -328,6 +503,58 @@ pub(crate) async fn update_pr_build_id(
     .await
 }
 
+pub(crate) async fn set_rollup_pr(
+    executor: impl PgExecutor<'_>,
+    pr_ids: &[i32],
+    rollup_pr_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("set_rollup_pr", || async {
+        sqlx::query!(
+            "UPDATE pull_request SET rollup_pr_id = $1 WHERE id = ANY($2)",
+            rollup_pr_id,
+            pr_ids
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+pub(crate) async fn clear_rollup_pr(
+    executor: impl PgExecutor<'_>,
+    rollup_pr_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("clear_rollup_pr", || async {
+        sqlx::query!(
+            "UPDATE pull_request SET rollup_pr_id = NULL WHERE rollup_pr_id = $1",
+            rollup_pr_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+pub(crate) async fn update_pr_auto_build_id(
+    executor: impl PgExecutor<'_>,
+    pr_id: i32,
+    build_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("update_pr_auto_build_id", || async {
+        sqlx::query!(
+            "UPDATE pull_request SET auto_build_id = $1 WHERE id = $2",
+            build_id,
+            pr_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
 pub(crate) async fn create_build(
     executor: impl PgExecutor<'_>,
     repo: &GithubRepoName,
@@ -372,7 +599,10 @@ SELECT
     commit_sha,
     parent,
     status as "status: BuildStatus",
-    created_at as "created_at: DateTime<Utc>"
+    created_at as "created_at: DateTime<Utc>",
+    retry_count,
+    retried_at as "retried_at: DateTime<Utc>",
+    check_run_id
 FROM build
 WHERE repository = $1
     AND branch = $2
@@ -389,6 +619,40 @@ WHERE repository = $1
     .await
 }
 
+pub(crate) async fn get_build(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+    build_id: i32,
+) -> anyhow::Result<Option<BuildModel>> {
+    measure_db_query("get_build", || async {
+        let build = sqlx::query_as!(
+            BuildModel,
+            r#"
+SELECT
+    id,
+    repository as "repository: GithubRepoName",
+    branch,
+    commit_sha,
+    parent,
+    status as "status: BuildStatus",
+    created_at as "created_at: DateTime<Utc>",
+    retry_count,
+    retried_at as "retried_at: DateTime<Utc>",
+    check_run_id
+FROM build
+WHERE repository = $1
+    AND id = $2
+"#,
+            repo as &GithubRepoName,
+            build_id
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(build)
+    })
+    .await
+}
+
 pub(crate) async fn get_running_builds(
     executor: impl PgExecutor<'_>,
     repo: &GithubRepoName,
@@ -404,7 +668,10 @@ SELECT
     commit_sha,
     parent,
     status as "status: BuildStatus",
-    created_at as "created_at: DateTime<Utc>"
+    created_at as "created_at: DateTime<Utc>",
+    retry_count,
+    retried_at as "retried_at: DateTime<Utc>",
+    check_run_id
 FROM build
 WHERE repository = $1
     AND status = $2
@@ -437,6 +704,51 @@ pub(crate) async fn update_build_status(
     .await
 }
 
+/// Stores the ID of the check run that reports the state of the build on GitHub.
+pub(crate) async fn set_build_check_run_id(
+    executor: impl PgExecutor<'_>,
+    build_id: i32,
+    check_run_id: i64,
+) -> anyhow::Result<()> {
+    measure_db_query("set_build_check_run_id", || async {
+        sqlx::query!(
+            "UPDATE build SET check_run_id = $1 WHERE id = $2",
+            check_run_id,
+            build_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Marks a failed build as pending again, so that its failed workflows can be re-run.
+pub(crate) async fn retry_build(
+    executor: impl PgExecutor<'_>,
+    build_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("retry_build", || async {
+        sqlx::query!(
+            r#"
+UPDATE build
+SET status = $1,
+    retry_count = retry_count + 1,
+    retried_at = NOW()
+WHERE id = $2
+"#,
+            BuildStatus::Pending as BuildStatus,
+            build_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Stores a workflow of a build.
+/// If the workflow was already stored (e.g. because it was re-run), its status is updated instead.
 pub(crate) async fn create_workflow(
     executor: impl PgExecutor<'_>,
     build_id: i32,
@@ -451,6 +763,7 @@ pub(crate) async fn create_workflow(
             r#"
 INSERT INTO workflow (build_id, name, url, run_id, type, status)
 VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (build_id, url) DO UPDATE SET status = EXCLUDED.status
 "#,
             build_id,
             name,
@@ -484,6 +797,65 @@ pub(crate) async fn update_workflow_status(
     .await
 }
 
+/// Marks a failed workflow as pending again, because it is being re-run.
+pub(crate) async fn restart_workflow(
+    executor: impl PgExecutor<'_>,
+    run_id: u64,
+) -> anyhow::Result<()> {
+    measure_db_query("restart_workflow", || async {
+        sqlx::query!(
+            "UPDATE workflow SET status = $1, attempts = attempts + 1 WHERE run_id = $2",
+            WorkflowStatus::Pending as WorkflowStatus,
+            run_id as i64
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Computes statistics of finished workflows of the given repository from the last `days` days.
+pub(crate) async fn get_workflow_stats(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+    days: u32,
+) -> anyhow::Result<Vec<WorkflowStats>> {
+    let days = i32::try_from(days)?;
+    measure_db_query("get_workflow_stats", || async {
+        let stats = sqlx::query_as!(
+            WorkflowStats,
+            r#"
+SELECT
+    workflow.name,
+    SUM(workflow.attempts)::BIGINT AS "attempts!",
+    (
+        SUM(workflow.attempts - 1) + COUNT(*) FILTER (WHERE workflow.status = $3)
+    )::BIGINT AS "failed_attempts!",
+    COUNT(*) FILTER (
+        WHERE workflow.status = $4 AND workflow.attempts > 1
+    ) AS "passed_on_retry!"
+FROM workflow
+    JOIN build ON workflow.build_id = build.id
+WHERE build.repository = $1
+    AND workflow.created_at >= NOW() - make_interval(days => $2)
+    AND workflow.status != $5
+GROUP BY workflow.name
+ORDER BY workflow.name
+"#,
+            repo as &GithubRepoName,
+            days,
+            WorkflowStatus::Failure as WorkflowStatus,
+            WorkflowStatus::Success as WorkflowStatus,
+            WorkflowStatus::Pending as WorkflowStatus
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(stats)
+    })
+    .await
+}
+
 pub(crate) async fn set_pr_priority(
     executor: impl PgExecutor<'_>,
     pr_id: i32,
@@ -543,7 +915,10 @@ SELECT
         build.commit_sha,
         build.status,
         build.parent,
-        build.created_at
+        build.created_at,
+        build.retry_count,
+        build.retried_at,
+        build.check_run_id
     ) AS "build!: BuildModel"
 FROM workflow
     LEFT JOIN build ON workflow.build_id = build.id
@@ -602,7 +977,10 @@ SELECT
         build.commit_sha,
         build.status,
         build.parent,
-        build.created_at
+        build.created_at,
+        build.retry_count,
+        build.retried_at,
+        build.check_run_id
     ) AS "build!: BuildModel"
 FROM workflow
     LEFT JOIN build ON workflow.build_id = build.id
@@ -645,6 +1023,39 @@ pub(crate) async fn get_repository(
     .await
 }
 
+/// Returns the names and tree states of all repositories that are known to the database.
+pub(crate) async fn get_repositories(
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<Vec<(GithubRepoName, TreeState)>> {
+    measure_db_query("get_repositories", || async {
+        let records = sqlx::query!(
+            r#"
+        SELECT
+            names.name as "name!: GithubRepoName",
+            (
+                repository.tree_state,
+                repository.treeclosed_src
+            ) AS "tree_state!: TreeState"
+        FROM (
+            SELECT name FROM repository
+            UNION
+            SELECT repository AS name FROM pull_request
+        ) AS names
+        LEFT JOIN repository ON repository.name = names.name
+        ORDER BY names.name
+        "#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| (record.name, record.tree_state))
+            .collect())
+    })
+    .await
+}
+
 /// Updates the tree state of a repository.
 pub(crate) async fn upsert_repository(
     executor: impl PgExecutor<'_>,
@@ -674,3 +1085,462 @@ pub(crate) async fn upsert_repository(
     })
     .await
 }
+
+/// Returns the configuration error of a repository, if its configuration could not be loaded.
+pub(crate) async fn get_config_error(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+) -> anyhow::Result<Option<ConfigErrorModel>> {
+    measure_db_query("get_config_error", || async {
+        let error = sqlx::query_as!(
+            ConfigErrorModel,
+            r#"
+        SELECT
+            name as "repository: GithubRepoName",
+            config_error_revision as "revision!",
+            config_error as "error!"
+        FROM repository
+        WHERE name = $1 AND config_error IS NOT NULL
+        "#,
+            repo as &GithubRepoName
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(error)
+    })
+    .await
+}
+
+/// Returns the configuration errors of all repositories whose configuration could not be loaded.
+pub(crate) async fn get_config_errors(
+    executor: impl PgExecutor<'_>,
+) -> anyhow::Result<Vec<ConfigErrorModel>> {
+    measure_db_query("get_config_errors", || async {
+        let errors = sqlx::query_as!(
+            ConfigErrorModel,
+            r#"
+        SELECT
+            name as "repository: GithubRepoName",
+            config_error_revision as "revision!",
+            config_error as "error!"
+        FROM repository
+        WHERE config_error IS NOT NULL
+        ORDER BY name
+        "#
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(errors)
+    })
+    .await
+}
+
+pub(crate) async fn set_config_error(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+    revision: &str,
+    error: &str,
+) -> anyhow::Result<()> {
+    measure_db_query("set_config_error", || async {
+        sqlx::query!(
+            r#"
+        INSERT INTO repository (name, config_error, config_error_revision)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (name)
+        DO UPDATE SET config_error = EXCLUDED.config_error, config_error_revision = EXCLUDED.config_error_revision
+        "#,
+            repo as &GithubRepoName,
+            error,
+            revision
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+pub(crate) async fn clear_config_error(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+) -> anyhow::Result<()> {
+    measure_db_query("clear_config_error", || async {
+        sqlx::query!(
+            r#"
+        UPDATE repository
+        SET config_error = NULL, config_error_revision = NULL
+        WHERE name = $1
+        "#,
+            repo as &GithubRepoName
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Stores a webhook event in the event queue and returns its ID.
+/// Returns `None` if an event with the same delivery ID has already been stored.
+pub(crate) async fn enqueue_event(
+    executor: impl PgExecutor<'_>,
+    delivery_id: Option<&str>,
+    event_type: &str,
+    repository: Option<&str>,
+    payload: &str,
+    status: QueuedEventStatus,
+) -> anyhow::Result<Option<i32>> {
+    measure_db_query("enqueue_event", || async {
+        let record = sqlx::query!(
+            r#"
+        INSERT INTO event_queue (delivery_id, event_type, repository, payload, status)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (delivery_id) DO NOTHING
+        RETURNING id
+        "#,
+            delivery_id,
+            event_type,
+            repository,
+            payload,
+            status as _
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(record.map(|record| record.id))
+    })
+    .await
+}
+
+pub(crate) async fn get_event_by_delivery_id(
+    executor: impl PgExecutor<'_>,
+    delivery_id: &str,
+) -> anyhow::Result<Option<QueuedEventModel>> {
+    measure_db_query("get_event_by_delivery_id", || async {
+        let event = sqlx::query_as!(
+            QueuedEventModel,
+            r#"
+        SELECT
+            id,
+            delivery_id,
+            event_type,
+            payload,
+            repository,
+            status as "status: QueuedEventStatus",
+            attempts,
+            last_error,
+            next_attempt_at as "next_attempt_at: DateTime<Utc>",
+            created_at as "created_at: DateTime<Utc>"
+        FROM event_queue
+        WHERE delivery_id = $1
+        "#,
+            delivery_id
+        )
+        .fetch_optional(executor)
+        .await?;
+        Ok(event)
+    })
+    .await
+}
+
+/// Returns pending events whose next attempt is due, in the order in which they were received.
+/// Events with IDs contained in `exclude` are skipped, as well as events of a repository that has
+/// an earlier event waiting for a retry, so that events of a repository are handled in order.
+pub(crate) async fn get_due_events(
+    executor: impl PgExecutor<'_>,
+    limit: i64,
+    exclude: &[i32],
+) -> anyhow::Result<Vec<QueuedEventModel>> {
+    measure_db_query("get_due_events", || async {
+        let events = sqlx::query_as!(
+            QueuedEventModel,
+            r#"
+        SELECT
+            id,
+            delivery_id,
+            event_type,
+            payload,
+            repository,
+            status as "status: QueuedEventStatus",
+            attempts,
+            last_error,
+            next_attempt_at as "next_attempt_at: DateTime<Utc>",
+            created_at as "created_at: DateTime<Utc>"
+        FROM event_queue
+        WHERE status = 'pending'
+            AND next_attempt_at <= NOW()
+            AND NOT (id = ANY($2))
+            AND NOT EXISTS (
+                SELECT 1 FROM event_queue AS earlier
+                WHERE earlier.repository = event_queue.repository
+                    AND earlier.status = 'pending'
+                    AND earlier.next_attempt_at > NOW()
+                    AND earlier.id < event_queue.id
+            )
+        ORDER BY id
+        LIMIT $1
+        "#,
+            limit,
+            exclude
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(events)
+    })
+    .await
+}
+
+pub(crate) async fn mark_event_done(
+    executor: impl PgExecutor<'_>,
+    event_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("mark_event_done", || async {
+        sqlx::query!(
+            "UPDATE event_queue SET status = $1, attempts = attempts + 1 WHERE id = $2",
+            QueuedEventStatus::Done as _,
+            event_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Records a failed attempt to handle an event.
+/// The event is dead-lettered once it has been attempted `max_attempts` times, otherwise it
+/// will be retried at `next_attempt_at`.
+pub(crate) async fn mark_event_failed(
+    executor: impl PgExecutor<'_>,
+    event_id: i32,
+    error: &str,
+    next_attempt_at: DateTime<Utc>,
+    max_attempts: i32,
+) -> anyhow::Result<QueuedEventStatus> {
+    measure_db_query("mark_event_failed", || async {
+        let record = sqlx::query!(
+            r#"
+        UPDATE event_queue
+        SET attempts = attempts + 1,
+            last_error = $1,
+            next_attempt_at = $2,
+            status = CASE WHEN attempts + 1 >= $3 THEN 'dead' ELSE 'pending' END
+        WHERE id = $4
+        RETURNING status as "status: QueuedEventStatus"
+        "#,
+            error,
+            next_attempt_at,
+            max_attempts,
+            event_id
+        )
+        .fetch_one(executor)
+        .await?;
+        Ok(record.status)
+    })
+    .await
+}
+
+/// Deletes events that were received before `received_before` and that will not be handled
+/// anymore, i.e. events that are done, ignored or dead. Returns the number of deleted events.
+pub(crate) async fn delete_finished_events(
+    executor: impl PgExecutor<'_>,
+    received_before: DateTime<Utc>,
+) -> anyhow::Result<u64> {
+    measure_db_query("delete_finished_events", || async {
+        let result = sqlx::query!(
+            r#"
+        DELETE FROM event_queue
+        WHERE status IN ('done', 'ignored', 'dead')
+            AND created_at < $1
+        "#,
+            received_before
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    })
+    .await
+}
+
+/// Stores a try build request of a PR.
+/// If the PR already has a queued request, its parameters are replaced, but it keeps its place
+/// in the queue.
+pub(crate) async fn enqueue_try_request(
+    executor: impl PgExecutor<'_>,
+    pr_id: i32,
+    requester: &str,
+    parent: Option<&str>,
+    jobs: &[String],
+) -> anyhow::Result<()> {
+    measure_db_query("enqueue_try_request", || async {
+        sqlx::query!(
+            r#"
+INSERT INTO try_request (pull_request_id, requester, parent, jobs)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (pull_request_id) DO UPDATE
+SET requester = EXCLUDED.requester,
+    parent = EXCLUDED.parent,
+    jobs = EXCLUDED.jobs,
+    failed_attempts = 0
+"#,
+            pr_id,
+            requester,
+            parent,
+            jobs
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the queued try build requests of the given repository, oldest first.
+pub(crate) async fn get_try_requests(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+) -> anyhow::Result<Vec<TryRequestModel>> {
+    measure_db_query("get_try_requests", || async {
+        let requests = sqlx::query_as!(
+            TryRequestModel,
+            r#"
+SELECT
+    request.id,
+    pr.repository as "repository: GithubRepoName",
+    pr.number as "pr_number!: i64",
+    request.requester,
+    request.parent,
+    request.jobs,
+    request.failed_attempts,
+    COALESCE(pr.priority, 0) as "priority!: i32",
+    request.created_at as "created_at: DateTime<Utc>"
+FROM try_request AS request
+    JOIN pull_request AS pr ON request.pull_request_id = pr.id
+WHERE pr.repository = $1
+ORDER BY request.created_at, request.id
+"#,
+            repo as &GithubRepoName
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(requests)
+    })
+    .await
+}
+
+pub(crate) async fn delete_try_request(
+    executor: impl PgExecutor<'_>,
+    request_id: i32,
+) -> anyhow::Result<()> {
+    measure_db_query("delete_try_request", || async {
+        sqlx::query!("DELETE FROM try_request WHERE id = $1", request_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Records that starting the build of a queued try request has failed.
+/// Returns the number of failed attempts of the request.
+pub(crate) async fn record_try_request_failure(
+    executor: impl PgExecutor<'_>,
+    request_id: i32,
+) -> anyhow::Result<i32> {
+    measure_db_query("record_try_request_failure", || async {
+        let record = sqlx::query!(
+            r#"
+UPDATE try_request
+SET failed_attempts = failed_attempts + 1
+WHERE id = $1
+RETURNING failed_attempts
+"#,
+            request_id
+        )
+        .fetch_one(executor)
+        .await?;
+        Ok(record.failed_attempts)
+    })
+    .await
+}
+
+/// Removes the queued try build request of a PR.
+/// Returns `true` if there was a request to remove.
+pub(crate) async fn delete_try_request_for_pr(
+    executor: impl PgExecutor<'_>,
+    pr_id: i32,
+) -> anyhow::Result<bool> {
+    measure_db_query("delete_try_request_for_pr", || async {
+        let result = sqlx::query!("DELETE FROM try_request WHERE pull_request_id = $1", pr_id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    })
+    .await
+}
+
+pub(crate) async fn create_audit_entry(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+    kind: AuditEntryKind,
+    branch: &str,
+    sha: &CommitSha,
+    pr_number: Option<PullRequestNumber>,
+) -> anyhow::Result<()> {
+    measure_db_query("create_audit_entry", || async {
+        sqlx::query!(
+            r#"
+INSERT INTO audit_entry (repository, kind, branch, sha, pr_number)
+VALUES ($1, $2, $3, $4, $5)
+"#,
+            repo as &GithubRepoName,
+            kind as _,
+            branch,
+            sha.0,
+            pr_number.map(|pr| pr.0 as i64)
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the audit log of the given repository, oldest entries first.
+pub(crate) async fn get_audit_entries(
+    executor: impl PgExecutor<'_>,
+    repo: &GithubRepoName,
+) -> anyhow::Result<Vec<AuditEntryModel>> {
+    measure_db_query("get_audit_entries", || async {
+        let records = sqlx::query!(
+            r#"
+SELECT
+    id,
+    repository as "repository: GithubRepoName",
+    kind as "kind: AuditEntryKind",
+    branch,
+    sha,
+    pr_number,
+    created_at as "created_at: DateTime<Utc>"
+FROM audit_entry
+WHERE repository = $1
+ORDER BY id
+"#,
+            repo as &GithubRepoName
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(records
+            .into_iter()
+            .map(|record| AuditEntryModel {
+                id: record.id,
+                repository: record.repository,
+                kind: record.kind,
+                branch: record.branch,
+                sha: record.sha,
+                pr_number: record.pr_number.map(PullRequestNumber::from),
+                created_at: record.created_at,
+            })
+            .collect())
+    })
+    .await
+}
@@ -1,15 +1,22 @@
 use anyhow::Context;
-use octocrab::models::{App, Repository};
+use octocrab::models::{App, Repository, UserId};
 use octocrab::{Error, Octocrab};
+use serde::de::DeserializeOwned;
 use tracing::log;
 
 use crate::bors::event::PullRequestComment;
-use crate::bors::{CheckSuite, CheckSuiteStatus, Comment};
-use crate::config::{CONFIG_FILE_PATH, RepositoryConfig};
+use crate::bors::{CheckRun, CheckSuite, CheckSuiteStatus, Comment};
+use crate::config::CONFIG_FILE_PATH;
 use crate::database::RunId;
 use crate::github::api::base_github_html_url;
-use crate::github::api::operations::{MergeError, merge_branches, set_branch_to_commit};
-use crate::github::{CommitSha, GithubRepoName, PullRequest, PullRequestNumber};
+use crate::github::api::operations::{
+    BranchUpdateError, MergeError, fast_forward_branch, merge_branches, set_branch_to_commit,
+};
+use crate::github::{
+    CheckRunState, CommitAuthor, CommitSha, CommitStatusState, ConfigFile, GithubRepoName,
+    GithubUser, PullRequest, PullRequestCommit, PullRequestFile, PullRequestNumber,
+};
+use crate::permissions::{CollaboratorPermission, GithubTeam};
 use crate::utils::timing::measure_network_request;
 
 /// Provides access to a single app installation (repository) using the GitHub API.
@@ -47,43 +54,53 @@ impl GithubRepositoryClient {
         &self.repo_name
     }
 
+    /// Name of the default branch of the repository.
+    pub fn default_branch(&self) -> &str {
+        self.repository.default_branch.as_deref().unwrap_or("main")
+    }
+
     /// Was the comment created by the bot?
     pub async fn is_comment_internal(&self, comment: &PullRequestComment) -> anyhow::Result<bool> {
-        Ok(comment.author.html_url == self.app.html_url)
+        Ok(self.is_user_internal(&comment.author))
     }
 
-    /// Loads repository configuration from a file located at `[CONFIG_FILE_PATH]` in the main
-    /// branch.
-    pub async fn load_config(&self) -> anyhow::Result<RepositoryConfig> {
-        measure_network_request("load_config", || async {
-            let mut response = self
-                .client
-                .repos(&self.repo_name.owner, &self.repo_name.name)
-                .get_content()
-                .path(CONFIG_FILE_PATH)
-                .send()
-                .await
-                .map_err(|error| {
-                    anyhow::anyhow!(
-                        "Could not fetch {CONFIG_FILE_PATH} from {}: {error:?}",
-                        self.repo_name
-                    )
-                })?;
+    /// Is the user the bot itself?
+    pub fn is_user_internal(&self, user: &GithubUser) -> bool {
+        user.html_url == self.app.html_url
+    }
 
-            response
-                .take_items()
-                .into_iter()
-                .next()
-                .and_then(|content| content.decoded_content())
-                .ok_or_else(|| anyhow::anyhow!("Configuration file not found"))
-                .and_then(|content| {
-                    let config: RepositoryConfig = toml::from_str(&content).map_err(|error| {
-                        anyhow::anyhow!("Could not deserialize repository config: {error:?}")
-                    })?;
-                    Ok(config)
-                })
-        })
-        .await
+    /// Returns the configuration file located at `[CONFIG_FILE_PATH]` in the given commit,
+    /// or in the main branch if no commit is given.
+    pub async fn get_config_file(&self, sha: Option<&CommitSha>) -> anyhow::Result<ConfigFile> {
+        measure_network_request("get_config_file", || self.fetch_config_file(sha)).await
+    }
+
+    async fn fetch_config_file(&self, sha: Option<&CommitSha>) -> anyhow::Result<ConfigFile> {
+        let repos = self
+            .client
+            .repos(&self.repo_name.owner, &self.repo_name.name);
+        let mut request = repos.get_content().path(CONFIG_FILE_PATH);
+        if let Some(sha) = sha {
+            request = request.r#ref(sha.as_ref());
+        }
+        let mut response = request.send().await.map_err(|error| {
+            anyhow::anyhow!(
+                "Could not fetch {CONFIG_FILE_PATH} from {}: {error:?}",
+                self.repo_name
+            )
+        })?;
+
+        response
+            .take_items()
+            .into_iter()
+            .next()
+            .and_then(|content| {
+                let sha = content.sha.clone();
+                content
+                    .decoded_content()
+                    .map(|content| ConfigFile { content, sha })
+            })
+            .ok_or_else(|| anyhow::anyhow!("Configuration file not found"))
     }
 
     /// Return the current SHA of the given branch.
@@ -119,6 +136,160 @@ impl GithubRepositoryClient {
         .await
     }
 
+    /// Open a new pull request that merges `head` into `base`.
+    pub async fn create_pull_request(
+        &self,
+        title: &str,
+        head: &str,
+        base: &str,
+        body: &str,
+    ) -> anyhow::Result<PullRequest> {
+        measure_network_request("create_pull_request", || async {
+            let pr = self
+                .client
+                .pulls(self.repository().owner(), self.repository().name())
+                .create(title, head, base)
+                .body(body)
+                .send()
+                .await
+                .map_err(|error| {
+                    anyhow::anyhow!(
+                        "Could not create PR from {head} to {base} in {}: {error:?}",
+                        self.repository()
+                    )
+                })?;
+            Ok(pr.into())
+        })
+        .await
+    }
+
+    /// Opens an issue in the repository and returns its number.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/issues/issues?apiVersion=2022-11-28#create-an-issue
+    pub async fn create_issue(&self, title: &str, body: &str) -> anyhow::Result<u64> {
+        measure_network_request("create_issue", || async {
+            #[derive(serde::Serialize)]
+            struct CreateIssueRequest<'a> {
+                title: &'a str,
+                body: &'a str,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CreateIssueResponse {
+                number: u64,
+            }
+
+            let response: CreateIssueResponse = self
+                .client
+                .post(
+                    format!("/repos/{}/issues", self.repo_name),
+                    Some(&CreateIssueRequest { title, body }),
+                )
+                .await
+                .with_context(|| format!("Cannot create issue in {}", self.repo_name))?;
+            Ok(response.number)
+        })
+        .await
+    }
+
+    /// Creates a check run with the given name on the given commit and returns its ID.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/checks/runs?apiVersion=2022-11-28#create-a-check-run
+    pub async fn create_check_run(
+        &self,
+        name: &str,
+        head_sha: &CommitSha,
+        state: CheckRunState,
+        title: &str,
+        summary: &str,
+    ) -> anyhow::Result<u64> {
+        measure_network_request("create_check_run", || async {
+            #[derive(serde::Deserialize)]
+            struct CreateCheckRunResponse {
+                id: u64,
+            }
+
+            let request = CheckRunRequest {
+                name: Some(name),
+                head_sha: Some(head_sha.as_ref()),
+                ..CheckRunRequest::new(state, title, summary)
+            };
+            let response: CreateCheckRunResponse = self
+                .client
+                .post(
+                    format!("/repos/{}/check-runs", self.repo_name),
+                    Some(&request),
+                )
+                .await
+                .with_context(|| format!("Cannot create check run for {head_sha}"))?;
+            Ok(response.id)
+        })
+        .await
+    }
+
+    /// Updates the state and the output of a check run.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/checks/runs?apiVersion=2022-11-28#update-a-check-run
+    pub async fn update_check_run(
+        &self,
+        check_run_id: u64,
+        state: CheckRunState,
+        title: &str,
+        summary: &str,
+    ) -> anyhow::Result<()> {
+        measure_network_request("update_check_run", || async {
+            let _: serde_json::Value = self
+                .client
+                .patch(
+                    format!("/repos/{}/check-runs/{check_run_id}", self.repo_name),
+                    Some(&CheckRunRequest::new(state, title, summary)),
+                )
+                .await
+                .with_context(|| format!("Cannot update check run {check_run_id}"))?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Publishes a commit status with the given context on the given commit.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/commits/statuses?apiVersion=2022-11-28#create-a-commit-status
+    pub async fn create_commit_status(
+        &self,
+        sha: &CommitSha,
+        context: &str,
+        state: CommitStatusState,
+        description: &str,
+    ) -> anyhow::Result<()> {
+        measure_network_request("create_commit_status", || async {
+            #[derive(serde::Serialize)]
+            struct CreateCommitStatusRequest<'a> {
+                state: &'static str,
+                context: &'a str,
+                description: &'a str,
+            }
+
+            let state = match state {
+                CommitStatusState::Pending => "pending",
+                CommitStatusState::Success => "success",
+            };
+            let _: serde_json::Value = self
+                .client
+                .post(
+                    format!("/repos/{}/statuses/{sha}", self.repo_name),
+                    Some(&CreateCommitStatusRequest {
+                        state,
+                        context,
+                        description,
+                    }),
+                )
+                .await
+                .with_context(|| format!("Cannot create commit status {context} for {sha}"))?;
+            Ok(())
+        })
+        .await
+    }
+
     /// Post a comment to the pull request with the given number.
     /// The comment will be posted as the Github App user of the bot.
     pub async fn post_comment(
@@ -145,6 +316,18 @@ impl GithubRepositoryClient {
         .await
     }
 
+    /// Fast-forward the given branch to a commit with the given `sha`.
+    pub async fn fast_forward_branch(
+        &self,
+        branch: &str,
+        sha: &CommitSha,
+    ) -> Result<(), BranchUpdateError> {
+        measure_network_request("fast_forward_branch", || async {
+            fast_forward_branch(self, branch.to_string(), sha).await
+        })
+        .await
+    }
+
     /// Merge `head` into `base`. Returns the SHA of the merge commit.
     pub async fn merge_branches(
         &self,
@@ -158,6 +341,130 @@ impl GithubRepositoryClient {
         .await
     }
 
+    /// Returns the commits of a pull request, from the oldest to the newest one.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-commits-on-a-pull-request
+    pub async fn get_pull_request_commits(
+        &self,
+        pr: PullRequestNumber,
+    ) -> anyhow::Result<Vec<PullRequestCommit>> {
+        measure_network_request("get_pull_request_commits", || async {
+            #[derive(serde::Deserialize)]
+            struct CommitDetail {
+                message: String,
+                author: Option<CommitAuthor>,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CommitResponse {
+                sha: String,
+                commit: CommitDetail,
+            }
+
+            let commits: Vec<CommitResponse> = self
+                .get_all_pages(&format!("/repos/{}/pulls/{pr}/commits", self.repo_name))
+                .await
+                .with_context(|| format!("Cannot load commits of PR {}", self.format_pr(pr)))?;
+            Ok(commits
+                .into_iter()
+                .map(|commit| PullRequestCommit {
+                    sha: commit.sha.into(),
+                    message: commit.commit.message,
+                    author: commit.commit.author,
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the files changed by a pull request.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/pulls/pulls?apiVersion=2022-11-28#list-pull-requests-files
+    pub async fn get_pull_request_files(
+        &self,
+        pr: PullRequestNumber,
+    ) -> anyhow::Result<Vec<PullRequestFile>> {
+        measure_network_request("get_pull_request_files", || async {
+            self.get_all_pages(&format!("/repos/{}/pulls/{pr}/files", self.repo_name))
+                .await
+                .with_context(|| format!("Cannot load files of PR {}", self.format_pr(pr)))
+        })
+        .await
+    }
+
+    /// Returns the SHA of the tree of the given commit.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#get-a-commit-object
+    pub async fn get_commit_tree(&self, sha: &CommitSha) -> anyhow::Result<String> {
+        measure_network_request("get_commit_tree", || async {
+            #[derive(serde::Deserialize)]
+            struct Tree {
+                sha: String,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CommitResponse {
+                tree: Tree,
+            }
+
+            let commit: CommitResponse = self
+                .client
+                .get(
+                    format!("/repos/{}/git/commits/{sha}", self.repo_name).as_str(),
+                    None::<&()>,
+                )
+                .await
+                .with_context(|| format!("Cannot load commit {sha}"))?;
+            Ok(commit.tree.sha)
+        })
+        .await
+    }
+
+    /// Creates a new commit with the given tree and parents, without updating any branch.
+    /// If `author` is not set, the bot will be the author of the commit.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/git/commits?apiVersion=2022-11-28#create-a-commit
+    pub async fn create_commit(
+        &self,
+        tree: &str,
+        parents: &[CommitSha],
+        message: &str,
+        author: Option<&CommitAuthor>,
+    ) -> anyhow::Result<CommitSha> {
+        measure_network_request("create_commit", || async {
+            #[derive(serde::Serialize)]
+            struct CreateCommitRequest<'a> {
+                message: &'a str,
+                tree: &'a str,
+                parents: Vec<&'a str>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                author: Option<&'a CommitAuthor>,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CreateCommitResponse {
+                sha: String,
+            }
+
+            let request = CreateCommitRequest {
+                message,
+                tree,
+                parents: parents.iter().map(|parent| parent.as_ref()).collect(),
+                author,
+            };
+            let response: CreateCommitResponse = self
+                .client
+                .post(
+                    format!("/repos/{}/git/commits", self.repo_name),
+                    Some(&request),
+                )
+                .await
+                .context("Cannot create commit")?;
+            Ok(response.sha.into())
+        })
+        .await
+    }
+
     /// Find all check suites attached to the given commit and branch.
     pub async fn get_check_suites_for_commit(
         &self,
@@ -221,6 +528,253 @@ impl GithubRepositoryClient {
         .await
     }
 
+    /// Returns all collaborators of the repository, along with their permission level.
+    pub async fn get_collaborators(&self) -> anyhow::Result<Vec<(UserId, CollaboratorPermission)>> {
+        measure_network_request("get_collaborators", || async {
+            #[derive(serde::Deserialize)]
+            struct CollaboratorPermissions {
+                admin: bool,
+                #[serde(default)]
+                maintain: bool,
+                push: bool,
+                #[serde(default)]
+                triage: bool,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct Collaborator {
+                id: UserId,
+                permissions: CollaboratorPermissions,
+            }
+
+            // https://docs.github.com/en/rest/collaborators/collaborators?apiVersion=2022-11-28#list-repository-collaborators
+            let collaborators: Vec<Collaborator> = self
+                .get_all_pages(&format!("/repos/{}/collaborators", self.repository()))
+                .await
+                .context("Cannot fetch collaborators")?;
+            let collaborators = collaborators
+                .into_iter()
+                .map(|collaborator| {
+                    let permissions = collaborator.permissions;
+                    let level = if permissions.admin {
+                        CollaboratorPermission::Admin
+                    } else if permissions.maintain {
+                        CollaboratorPermission::Maintain
+                    } else if permissions.push {
+                        CollaboratorPermission::Write
+                    } else if permissions.triage {
+                        CollaboratorPermission::Triage
+                    } else {
+                        CollaboratorPermission::Read
+                    };
+                    (collaborator.id, level)
+                })
+                .collect();
+            Ok(collaborators)
+        })
+        .await
+    }
+
+    /// Returns IDs of members of the given organization team.
+    pub async fn get_team_members(&self, team: &GithubTeam) -> anyhow::Result<Vec<UserId>> {
+        measure_network_request("get_team_members", || async {
+            #[derive(serde::Deserialize)]
+            struct Member {
+                id: UserId,
+            }
+
+            // https://docs.github.com/en/rest/teams/members?apiVersion=2022-11-28#list-team-members
+            let members: Vec<Member> = self
+                .get_all_pages(&format!("/orgs/{}/teams/{}/members", team.org, team.slug))
+                .await
+                .with_context(|| format!("Cannot fetch members of team {team}"))?;
+            Ok(members.into_iter().map(|member| member.id).collect())
+        })
+        .await
+    }
+
+    /// Loads all items of a paginated GitHub API list endpoint.
+    async fn get_all_pages<T: DeserializeOwned>(&self, url: &str) -> anyhow::Result<Vec<T>> {
+        const PAGE_SIZE: usize = 100;
+
+        let mut items = vec![];
+        let mut page = 1;
+        loop {
+            let response: Vec<T> = self
+                .client
+                .get(
+                    format!("{url}?per_page={PAGE_SIZE}&page={page}").as_str(),
+                    None::<&()>,
+                )
+                .await?;
+            let count = response.len();
+            items.extend(response);
+            if count < PAGE_SIZE {
+                return Ok(items);
+            }
+            page += 1;
+        }
+    }
+
+    /// Returns names of the status checks that are required by the branch protection of the
+    /// given branch.
+    pub async fn get_required_checks(&self, branch: &str) -> anyhow::Result<Vec<String>> {
+        measure_network_request("get_required_checks", || async {
+            #[derive(serde::Deserialize)]
+            struct RequiredStatusChecks {
+                #[serde(default)]
+                contexts: Vec<String>,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct Protection {
+                required_status_checks: Option<RequiredStatusChecks>,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct BranchResponse {
+                protection: Option<Protection>,
+            }
+
+            // https://docs.github.com/en/rest/branches/branches?apiVersion=2022-11-28#get-a-branch
+            let branch: BranchResponse = self
+                .client
+                .get(
+                    format!("/repos/{}/branches/{branch}", self.repository()).as_str(),
+                    None::<&()>,
+                )
+                .await
+                .with_context(|| format!("Cannot fetch branch {branch}"))?;
+            Ok(branch
+                .protection
+                .and_then(|protection| protection.required_status_checks)
+                .map(|checks| checks.contexts)
+                .unwrap_or_default())
+        })
+        .await
+    }
+
+    /// Find the latest check runs attached to the given commit.
+    pub async fn get_check_runs_for_commit(
+        &self,
+        sha: &CommitSha,
+    ) -> anyhow::Result<Vec<CheckRun>> {
+        measure_network_request("get_check_runs_for_commit", || async {
+            #[derive(serde::Deserialize)]
+            struct CheckRunPayload {
+                name: String,
+                status: String,
+                conclusion: Option<String>,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CheckRunResponse {
+                check_runs: Vec<CheckRunPayload>,
+            }
+
+            // The check runs are wrapped in an object, so `get_all_pages` cannot be used here.
+            // https://docs.github.com/en/rest/checks/runs?apiVersion=2022-11-28#list-check-runs-for-a-git-reference
+            const PAGE_SIZE: usize = 100;
+
+            let mut check_runs = vec![];
+            let mut page = 1;
+            loop {
+                let response: CheckRunResponse = self
+                    .client
+                    .get(
+                        format!(
+                            "/repos/{}/commits/{}/check-runs?per_page={PAGE_SIZE}&page={page}",
+                            self.repository(),
+                            sha.0
+                        )
+                        .as_str(),
+                        None::<&()>,
+                    )
+                    .await
+                    .context("Cannot fetch CheckRunResponse")?;
+                let count = response.check_runs.len();
+                check_runs.extend(response.check_runs);
+                if count < PAGE_SIZE {
+                    break;
+                }
+                page += 1;
+            }
+
+            Ok(check_runs
+                .into_iter()
+                .map(|run| CheckRun {
+                    status: match (run.status.as_str(), run.conclusion.as_deref()) {
+                        ("completed", Some("success" | "skipped" | "neutral")) => {
+                            CheckSuiteStatus::Success
+                        }
+                        ("completed", _) => CheckSuiteStatus::Failure,
+                        _ => CheckSuiteStatus::Pending,
+                    },
+                    name: run.name,
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Find the latest commit statuses attached to the given commit.
+    /// The context of each status is returned as the name of a [`CheckRun`].
+    pub async fn get_commit_statuses(&self, sha: &CommitSha) -> anyhow::Result<Vec<CheckRun>> {
+        measure_network_request("get_commit_statuses", || async {
+            #[derive(serde::Deserialize)]
+            struct StatusPayload {
+                context: String,
+                state: String,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CombinedStatusResponse {
+                statuses: Vec<StatusPayload>,
+            }
+
+            // The statuses are wrapped in an object, so `get_all_pages` cannot be used here.
+            // https://docs.github.com/en/rest/commits/statuses?apiVersion=2022-11-28#get-the-combined-status-for-a-specific-reference
+            const PAGE_SIZE: usize = 100;
+
+            let mut statuses = vec![];
+            let mut page = 1;
+            loop {
+                let response: CombinedStatusResponse = self
+                    .client
+                    .get(
+                        format!(
+                            "/repos/{}/commits/{}/status?per_page={PAGE_SIZE}&page={page}",
+                            self.repository(),
+                            sha.0
+                        )
+                        .as_str(),
+                        None::<&()>,
+                    )
+                    .await
+                    .context("Cannot fetch CombinedStatusResponse")?;
+                let count = response.statuses.len();
+                statuses.extend(response.statuses);
+                if count < PAGE_SIZE {
+                    break;
+                }
+                page += 1;
+            }
+
+            Ok(statuses
+                .into_iter()
+                .map(|status| CheckRun {
+                    status: match status.state.as_str() {
+                        "success" => CheckSuiteStatus::Success,
+                        "failure" | "error" => CheckSuiteStatus::Failure,
+                        _ => CheckSuiteStatus::Pending,
+                    },
+                    name: status.context,
+                })
+                .collect())
+        })
+        .await
+    }
+
     /// Cancels Github Actions workflows.
     pub async fn cancel_workflows(&self, run_ids: &[RunId]) -> anyhow::Result<()> {
         measure_network_request("cancel_workflows", || async {
@@ -243,6 +797,37 @@ impl GithubRepositoryClient {
         .await
     }
 
+    /// Re-runs the failed jobs of Github Actions workflows.
+    ///
+    /// Documentation: https://docs.github.com/en/rest/actions/workflow-runs?apiVersion=2022-11-28#re-run-failed-jobs-from-a-workflow-run
+    pub async fn rerun_failed_jobs(&self, run_ids: &[RunId]) -> anyhow::Result<()> {
+        measure_network_request("rerun_failed_jobs", || async {
+            futures::future::join_all(run_ids.iter().map(|run_id| async move {
+                let url = format!(
+                    "/repos/{}/actions/runs/{}/rerun-failed-jobs",
+                    self.repo_name, run_id
+                );
+                let response = self.client._post(url, None::<&()>).await?;
+                let status = response.status();
+                if !status.is_success() {
+                    let text = self
+                        .client
+                        .body_to_string(response)
+                        .await
+                        .unwrap_or_default();
+                    anyhow::bail!("Cannot re-run workflow {run_id}: {status} ({text})");
+                }
+                Ok(())
+            }))
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+            Ok(())
+        })
+        .await
+    }
+
     /// Add a set of labels to a PR.
     pub async fn add_labels(&self, pr: PullRequestNumber, labels: &[String]) -> anyhow::Result<()> {
         measure_network_request("add_labels", || async {
@@ -323,8 +908,51 @@ impl GithubRepositoryClient {
     }
 }
 
+/// Body of a request that creates or updates a check run.
+#[derive(serde::Serialize)]
+struct CheckRunRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_sha: Option<&'a str>,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conclusion: Option<&'static str>,
+    output: CheckRunOutput<'a>,
+}
+
+#[derive(serde::Serialize)]
+struct CheckRunOutput<'a> {
+    title: &'a str,
+    summary: &'a str,
+}
+
+impl<'a> CheckRunRequest<'a> {
+    fn new(state: CheckRunState, title: &'a str, summary: &'a str) -> Self {
+        let conclusion = match state {
+            CheckRunState::InProgress => None,
+            CheckRunState::Success => Some("success"),
+            CheckRunState::Failure => Some("failure"),
+            CheckRunState::Cancelled => Some("cancelled"),
+            CheckRunState::TimedOut => Some("timed_out"),
+        };
+        Self {
+            name: None,
+            head_sha: None,
+            status: if conclusion.is_some() {
+                "completed"
+            } else {
+                "in_progress"
+            },
+            conclusion,
+            output: CheckRunOutput { title, summary },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::PgDbClient;
     use crate::github::GithubRepoName;
     use crate::github::api::load_repositories;
     use crate::permissions::PermissionType;
@@ -333,8 +961,8 @@ mod tests {
     use crate::tests::mocks::{GitHubState, User};
     use octocrab::models::UserId;
 
-    #[tokio::test]
-    async fn load_installed_repos() {
+    #[sqlx::test]
+    async fn load_installed_repos(pool: sqlx::PgPool) {
         let mock = ExternalHttpMock::start(
             &GitHubState::new()
                 .with_repo(
@@ -354,7 +982,10 @@ mod tests {
         .await;
         let client = mock.github_client();
         let team_api_client = mock.team_api_client();
-        let mut repos = load_repositories(&client, &team_api_client).await.unwrap();
+        let db = PgDbClient::new(pool);
+        let mut repos = load_repositories(&client, &team_api_client, &db)
+            .await
+            .unwrap();
         assert_eq!(repos.len(), 2);
 
         let repo = repos
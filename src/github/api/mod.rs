@@ -9,15 +9,16 @@ use secrecy::{ExposeSecret, SecretString};
 
 use client::GithubRepositoryClient;
 
-use crate::bors::RepositoryState;
+use crate::PgDbClient;
+use crate::bors::{RepositoryState, report_config_error, report_config_valid};
 use crate::config::RepositoryConfig;
 use crate::github::GithubRepoName;
-use crate::permissions::TeamApiClient;
+use crate::permissions::{TeamApiClient, load_permissions};
 
 pub mod client;
 pub(crate) mod operations;
 
-fn base_github_html_url() -> &'static str {
+pub(crate) fn base_github_html_url() -> &'static str {
     "https://github.com"
 }
 
@@ -40,9 +41,13 @@ pub fn create_github_client(
 /// The anyhow::Result<RepositoryState> is intended, because we wanted to have
 /// a hard error when the repos fail to load when the bot starts, but only log
 /// a warning when we reload the state during the bot's execution.
+///
+/// An invalid configuration file does not fail the repository. It is reported like an invalid
+/// configuration found during a refresh and the repository uses the default configuration.
 pub async fn load_repositories(
     client: &Octocrab,
     team_api_client: &TeamApiClient,
+    db: &PgDbClient,
 ) -> anyhow::Result<HashMap<GithubRepoName, anyhow::Result<RepositoryState>>> {
     let installations = client
         .apps()
@@ -93,6 +98,7 @@ pub async fn load_repositories(
                 app.clone(),
                 installation_client.clone(),
                 team_api_client,
+                db,
                 repo.clone(),
                 name.clone(),
             )
@@ -144,6 +150,7 @@ async fn create_repo_state(
     app: App,
     repo_client: Octocrab,
     team_api_client: &TeamApiClient,
+    db: &PgDbClient,
     repo: Repository,
     name: GithubRepoName,
 ) -> anyhow::Result<RepositoryState> {
@@ -151,29 +158,40 @@ async fn create_repo_state(
 
     let client = GithubRepositoryClient::new(app, repo_client, name.clone(), repo);
 
-    let permissions = team_api_client
-        .load_permissions(&name)
+    let file = client
+        .get_config_file(None)
         .await
-        .with_context(|| format!("Could not load permissions for repository {name}"))?;
+        .with_context(|| format!("Could not load repository config for {name}"))?;
+    let (config, config_error) = match toml::from_str::<RepositoryConfig>(&file.content) {
+        Ok(config) => {
+            tracing::info!("Loaded repository config for {name}: {config:#?}");
+            (config, None)
+        }
+        Err(error) => {
+            let error = error.to_string();
+            tracing::error!(
+                "Could not load repository config for {name} (revision {}), using the default configuration: {error}",
+                file.sha
+            );
+            let config = toml::from_str("").expect("Default configuration is invalid");
+            (config, Some(error))
+        }
+    };
 
-    let config = load_config(&client).await?;
+    let permissions = load_permissions(&client, &config.permissions, team_api_client)
+        .await
+        .with_context(|| format!("Could not load permissions for repository {name}"))?;
 
-    Ok(RepositoryState {
+    let repo = RepositoryState {
         client,
         config: ArcSwap::new(Arc::new(config)),
         permissions: ArcSwap::new(Arc::new(permissions)),
-    })
-}
-
-async fn load_config(client: &GithubRepositoryClient) -> anyhow::Result<RepositoryConfig> {
-    let name = client.repository();
-    match client.load_config().await {
-        Ok(config) => {
-            tracing::info!("Loaded repository config for {name}: {config:#?}");
-            Ok(config)
+    };
+    match config_error {
+        Some(error) => {
+            report_config_error(&repo, db, &file.sha, &error, "the default configuration").await?
         }
-        Err(error) => Err(anyhow::anyhow!(
-            "Could not load repository config for {name}: {error:?}"
-        )),
+        None => report_config_valid(&name, db).await?,
     }
+    Ok(repo)
 }
@@ -6,6 +6,18 @@ pub enum LabelTrigger {
     TryBuildStarted,
     TryBuildSucceeded,
     TryBuildFailed,
+    AutoBuildFailed,
+    Delegated,
+    Undelegated,
+    PriorityChanged,
+    RollupModeChanged,
+    TreeClosed,
+    TreeOpened,
+    MergeConflict,
+    MergeConflictResolved,
+    PushedWhileApproved,
+    BuildCancelled,
+    BuildTimedOut,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -11,7 +11,7 @@ mod labels;
 pub mod server;
 mod webhook;
 
-pub use api::operations::MergeError;
+pub use api::operations::{BranchUpdateError, MergeError};
 pub use labels::{LabelModification, LabelTrigger};
 pub use webhook::WebhookSecret;
 
@@ -100,6 +100,56 @@ impl Display for CommitSha {
     }
 }
 
+/// Author of a Git commit.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+    /// Time of authorship in ISO 8601 format.
+    pub date: Option<String>,
+}
+
+/// A commit that is a part of a pull request.
+#[derive(Clone, Debug)]
+pub struct PullRequestCommit {
+    pub sha: CommitSha,
+    pub message: String,
+    pub author: Option<CommitAuthor>,
+}
+
+/// Configuration file of a repository.
+#[derive(Clone, Debug)]
+pub struct ConfigFile {
+    pub content: String,
+    /// SHA of the file blob, which identifies the revision of the file.
+    pub sha: String,
+}
+
+/// State of a check run created by the bot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckRunState {
+    InProgress,
+    Success,
+    Failure,
+    Cancelled,
+    TimedOut,
+}
+
+/// State of a commit status published by the bot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommitStatusState {
+    Pending,
+    Success,
+}
+
+/// A file changed by a pull request.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct PullRequestFile {
+    pub filename: String,
+    /// `added`, `removed`, `modified`, `renamed`, `copied`, `changed` or `unchanged`.
+    pub status: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct Branch {
     pub name: String,
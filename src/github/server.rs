@@ -1,45 +1,87 @@
+use crate::api::api_routes;
 use crate::bors::event::BorsEvent;
-use crate::bors::{BorsContext, handle_bors_global_event, handle_bors_repository_event};
-use crate::github::webhook::GitHubWebhook;
+use crate::bors::{
+    BorsContext, handle_bors_global_event, handle_bors_repository_event, sort_merge_queue,
+};
+use crate::database::TreeState;
+use crate::database::{QueuedEventModel, QueuedEventStatus};
+use crate::github::GithubRepoName;
 use crate::github::webhook::WebhookSecret;
-use crate::{BorsGlobalEvent, BorsRepositoryEvent, TeamApiClient};
+use crate::github::webhook::{GitHubWebhook, WebhookPayload};
+use crate::templates::{QueueEntry, QueueTemplate};
+use crate::utils::metrics::{REPOSITORY_QUEUE_DEPTH, WEBHOOKS_RECEIVED, encode_metrics};
+use crate::{BorsGlobalEvent, BorsRepositoryEvent, PgDbClient, TeamApiClient};
 
 use anyhow::Error;
 use axum::Router;
-use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{get, post};
+use chrono::Utc;
 use octocrab::Octocrab;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
 use tower::limit::ConcurrencyLimitLayer;
 use tracing::{Instrument, Span};
 
 /// Shared server state for all axum handlers.
 pub struct ServerState {
-    repository_event_queue: mpsc::Sender<BorsRepositoryEvent>,
-    global_event_queue: mpsc::Sender<BorsGlobalEvent>,
+    event_queue: mpsc::Sender<()>,
     webhook_secret: WebhookSecret,
+    db: Arc<PgDbClient>,
+    /// Token that authenticates requests to the admin API.
+    /// The admin API is disabled if it is not set.
+    admin_token: Option<SecretString>,
 }
 
 impl ServerState {
     pub fn new(
-        repository_event_queue: mpsc::Sender<BorsRepositoryEvent>,
-        global_event_queue: mpsc::Sender<BorsGlobalEvent>,
+        event_queue: mpsc::Sender<()>,
         webhook_secret: WebhookSecret,
+        db: Arc<PgDbClient>,
     ) -> Self {
         Self {
-            repository_event_queue,
-            global_event_queue,
+            event_queue,
             webhook_secret,
+            db,
+            admin_token: None,
         }
     }
 
+    pub fn with_admin_token(mut self, token: String) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
     pub fn get_webhook_secret(&self) -> &WebhookSecret {
         &self.webhook_secret
     }
+
+    pub fn db(&self) -> &PgDbClient {
+        &self.db
+    }
+
+    pub fn admin_token(&self) -> Option<&str> {
+        self.admin_token.as_ref().map(|token| token.expose_secret())
+    }
+
+    /// Wakes up the Bors process, so that it handles newly stored events.
+    pub fn notify_event_queue(&self) {
+        // If the channel is full, the consumer has not yet been woken up by a previous
+        // notification, and it will pick up the new event together with the previous ones.
+        if let Err(TrySendError::Closed(_)) = self.event_queue.try_send(()) {
+            tracing::error!(
+                "Event queue consumer has ended, the event will be handled after restart"
+            );
+        }
+    }
 }
 
 pub type ServerStateRef = Arc<ServerState>;
@@ -48,53 +90,191 @@ pub fn create_app(state: ServerState) -> Router {
     Router::new()
         .route("/github", post(github_webhook_handler))
         .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/queue/{owner}/{name}", get(queue_handler))
+        .nest("/api", api_routes())
         .layer(ConcurrencyLimitLayer::new(100))
         .with_state(Arc::new(state))
 }
 
-async fn health_handler() -> impl IntoResponse {
-    (StatusCode::OK, "")
+/// Reports repositories whose configuration could not be loaded, which are served with their
+/// last valid configuration.
+async fn health_handler(State(state): State<ServerStateRef>) -> Response {
+    match state.db.get_config_errors().await {
+        Ok(errors) => {
+            let report = errors
+                .into_iter()
+                .map(|error| {
+                    format!(
+                        "Invalid configuration of {} (revision {}): {}",
+                        error.repository,
+                        error.revision,
+                        error.error.trim_end()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            (StatusCode::OK, report).into_response()
+        }
+        Err(error) => {
+            tracing::error!("Cannot load configuration errors: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn metrics_handler() -> Response {
+    match encode_metrics() {
+        Ok(metrics) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+            metrics,
+        )
+            .into_response(),
+        Err(error) => {
+            tracing::error!("Cannot encode metrics: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
-/// Axum handler that receives a webhook and sends it to a webhook channel.
+/// Renders the merge queue of a repository as an HTML page.
+async fn queue_handler(
+    Path((owner, name)): Path<(String, String)>,
+    State(state): State<ServerStateRef>,
+) -> Response {
+    let repo_name = GithubRepoName::new(&owner, &name);
+    match load_queue(&state.db, repo_name).await {
+        Ok(queue) => Html(queue.render()).into_response(),
+        Err(error) => {
+            tracing::error!("Cannot load merge queue of {owner}/{name}: {error:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "Cannot load merge queue").into_response()
+        }
+    }
+}
+
+async fn load_queue(db: &PgDbClient, repo_name: GithubRepoName) -> anyhow::Result<QueueTemplate> {
+    let tree_state = db
+        .repo_db(&repo_name)
+        .await?
+        .map(|repo| repo.tree_state)
+        .unwrap_or(TreeState::Open);
+
+    let mut prs = db.get_open_pull_requests(&repo_name).await?;
+    sort_merge_queue(&mut prs);
+
+    let mut entries = Vec::with_capacity(prs.len());
+    for pr in prs {
+        let try_build_urls = match &pr.try_build {
+            Some(build) => db.get_workflow_urls_for_build(build).await?,
+            None => vec![],
+        };
+        entries.push(QueueEntry { pr, try_build_urls });
+    }
+
+    Ok(QueueTemplate {
+        repo_name,
+        tree_state,
+        entries,
+    })
+}
+
+/// Axum handler that receives a webhook, stores it in the event queue and wakes up the
+/// Bors process that handles queued events.
 pub async fn github_webhook_handler(
     State(state): State<ServerStateRef>,
-    GitHubWebhook(event): GitHubWebhook,
+    payload: WebhookPayload,
 ) -> impl IntoResponse {
-    match event {
-        BorsEvent::Global(e) => match state.global_event_queue.send(e).await {
-            Ok(_) => (StatusCode::OK, ""),
-            Err(err) => {
-                tracing::error!("Could not send webhook global event: {err:?}");
-                (StatusCode::INTERNAL_SERVER_ERROR, "")
-            }
-        },
-        BorsEvent::Repository(e) => match state.repository_event_queue.send(e).await {
-            Ok(_) => (StatusCode::OK, ""),
-            Err(err) => {
-                tracing::error!("Could not send webhook repository event: {err:?}");
-                (StatusCode::INTERNAL_SERVER_ERROR, "")
-            }
-        },
+    WEBHOOKS_RECEIVED
+        .with_label_values(&[&payload.event_type])
+        .inc();
+
+    let status = match payload.parse() {
+        Ok(Some(webhook)) => {
+            tracing::trace!("Received webhook event {webhook:?}");
+            QueuedEventStatus::Pending
+        }
+        // Events that bors is not interested in are also recorded, so that all deliveries
+        // can be inspected and replayed later.
+        Ok(None) => QueuedEventStatus::Ignored,
+        Err(error) => {
+            tracing::error!("Cannot parse webhook event: {error:?}");
+            return (StatusCode::BAD_REQUEST, "");
+        }
+    };
+
+    // The webhook is acknowledged only once it has been persisted, so that it is not lost
+    // if bors is restarted before it gets to handle it.
+    let repository = payload.repository();
+    match state
+        .db
+        .enqueue_event(
+            payload.delivery_id.as_deref(),
+            &payload.event_type,
+            repository.as_deref(),
+            &payload.body,
+            status,
+        )
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            // GitHub can deliver the same webhook more than once, it must not be handled twice.
+            tracing::info!(
+                "Ignoring duplicate delivery {}",
+                payload.delivery_id.as_deref().unwrap_or_default()
+            );
+            return (StatusCode::OK, "");
+        }
+        Err(error) => {
+            tracing::error!("Could not store webhook event: {error:?}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "");
+        }
+    }
+
+    if status == QueuedEventStatus::Pending {
+        state.notify_event_queue();
     }
+    (StatusCode::OK, "")
 }
 
+/// How many times bors tries to handle a queued event before it is dead-lettered.
+const MAX_EVENT_ATTEMPTS: i32 = 5;
+
+/// How often the event queue is checked for events whose retry is due.
+const EVENT_QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many events are loaded from the event queue at once.
+const EVENT_QUEUE_BATCH_SIZE: i64 = 100;
+
 /// Creates a future with a Bors process that continuously receives webhook events and reacts to
 /// them.
+///
+/// Webhook events are read from the persistent event queue. The returned `mpsc::Sender<()>`
+/// should be notified whenever a new event is stored in the queue.
 pub fn create_bors_process(
     ctx: BorsContext,
     gh_client: Octocrab,
     team_api: TeamApiClient,
 ) -> (
-    mpsc::Sender<BorsRepositoryEvent>,
+    mpsc::Sender<()>,
     mpsc::Sender<BorsGlobalEvent>,
     impl Future<Output = ()>,
 ) {
-    let (repository_tx, repository_rx) = mpsc::channel::<BorsRepositoryEvent>(1024);
+    let (queue_tx, queue_rx) = mpsc::channel::<()>(1);
     let (global_tx, global_rx) = mpsc::channel::<BorsGlobalEvent>(1024);
+    let (queued_global_tx, queued_global_rx) = mpsc::unbounded_channel::<GlobalWorkItem>();
+    // IDs of queued events that were dispatched to a handler, but not yet handled.
+    let in_flight: Arc<Mutex<HashSet<i32>>> = Default::default();
 
     let service = async move {
         let ctx = Arc::new(ctx);
+        let workers = RepositoryWorkers::new(in_flight.clone());
+        let global_events = GlobalEventReceivers {
+            global_rx,
+            queued_global_rx,
+            in_flight,
+        };
 
         // In tests, we shutdown these futures by dropping the channel sender,
         // In that case, we need to wait until both of these futures resolve,
@@ -103,8 +283,8 @@ pub fn create_bors_process(
         #[cfg(test)]
         {
             tokio::join!(
-                consume_repository_events(ctx.clone(), repository_rx),
-                consume_global_events(ctx.clone(), global_rx, gh_client, team_api)
+                consume_queued_events(ctx.clone(), queue_rx, queued_global_tx, workers),
+                consume_global_events(ctx.clone(), global_events, gh_client, team_api)
             );
         }
         // In real execution, the bot runs forever. If there is something that finishes
@@ -113,52 +293,299 @@ pub fn create_bors_process(
         #[cfg(not(test))]
         {
             tokio::select! {
-                _ = consume_repository_events(ctx.clone(), repository_rx) => {
-                    tracing::error!("Repository event handling process has ended");
+                _ = consume_queued_events(ctx.clone(), queue_rx, queued_global_tx, workers) => {
+                    tracing::error!("Event queue handling process has ended");
                 }
-                _ = consume_global_events(ctx.clone(), global_rx, gh_client, team_api) => {
+                _ = consume_global_events(ctx.clone(), global_events, gh_client, team_api) => {
                     tracing::error!("Global event handling process has ended");
                 }
             }
         }
     };
-    (repository_tx, global_tx, service)
+    (queue_tx, global_tx, service)
 }
 
-async fn consume_repository_events(
+/// Handles events from the persistent event queue.
+/// Events that were stored before bors was started are handled right away, new events are
+/// handled when the consumer is notified through `queue_rx`, and failed events are retried
+/// once their backoff expires.
+///
+/// Repository events are dispatched to a separate worker for each repository, so that events of
+/// different repositories are handled concurrently, while events of a single repository are
+/// handled in the order in which they were received. Global events are dispatched to the global
+/// event handler.
+async fn consume_queued_events(
     ctx: Arc<BorsContext>,
-    mut repository_rx: mpsc::Receiver<BorsRepositoryEvent>,
+    mut queue_rx: mpsc::Receiver<()>,
+    global_tx: mpsc::UnboundedSender<GlobalWorkItem>,
+    mut workers: RepositoryWorkers,
 ) {
-    while let Some(event) = repository_rx.recv().await {
-        let ctx = ctx.clone();
+    loop {
+        if let Err(error) = dispatch_due_events(&ctx, &global_tx, &mut workers).await {
+            tracing::error!("Cannot load events from the event queue: {error:?}");
+        }
 
-        let span = tracing::info_span!("RepositoryEvent");
-        tracing::debug!("Received repository event: {event:#?}");
-        if let Err(error) = handle_bors_repository_event(event, ctx)
-            .instrument(span.clone())
-            .await
-        {
+        tokio::select! {
+            notification = queue_rx.recv() => {
+                if notification.is_none() {
+                    break;
+                }
+            }
+            _ = tokio::time::sleep(EVENT_QUEUE_POLL_INTERVAL) => {}
+        }
+    }
+    workers.finish().await;
+}
+
+async fn dispatch_due_events(
+    ctx: &Arc<BorsContext>,
+    global_tx: &mpsc::UnboundedSender<GlobalWorkItem>,
+    workers: &mut RepositoryWorkers,
+) -> anyhow::Result<()> {
+    loop {
+        // Events that are being handled by a worker or by the global event handler are still
+        // pending in the database, so we have to skip them to avoid handling them twice.
+        let in_flight = workers.in_flight();
+        let events = ctx
+            .db
+            .get_due_events(EVENT_QUEUE_BATCH_SIZE, &in_flight)
+            .await?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        for event in events {
+            let payload = WebhookPayload {
+                delivery_id: event.delivery_id.clone(),
+                event_type: event.event_type.clone(),
+                body: event.payload.clone(),
+            };
+            match payload.parse() {
+                Ok(Some(GitHubWebhook(BorsEvent::Repository(repository_event)))) => {
+                    workers.dispatch(ctx, event, repository_event);
+                }
+                Ok(Some(GitHubWebhook(BorsEvent::Global(global_event)))) => {
+                    workers.in_flight.lock().unwrap().insert(event.id);
+                    if let Err(error) = global_tx.send((event, global_event)) {
+                        let (event, _) = error.0;
+                        workers.in_flight.lock().unwrap().remove(&event.id);
+                        let span = tracing::info_span!("GlobalEvent", event_id = event.id);
+                        let error = anyhow::anyhow!("Global event channel is closed");
+                        finish_queued_event(ctx, &event, Err(error), span).await?;
+                    }
+                }
+                Ok(None) => ctx.db.mark_event_done(&event).await?,
+                Err(error) => {
+                    let span = tracing::info_span!("QueuedEvent", event_id = event.id);
+                    finish_queued_event(ctx, &event, Err(error), span).await?;
+                }
+            }
+        }
+    }
+}
+
+type RepositoryWorkItem = (QueuedEventModel, BorsRepositoryEvent);
+type GlobalWorkItem = (QueuedEventModel, BorsGlobalEvent);
+
+/// Worker tasks that handle events of individual repositories.
+struct RepositoryWorkers {
+    senders: HashMap<GithubRepoName, mpsc::UnboundedSender<RepositoryWorkItem>>,
+    handles: Vec<JoinHandle<()>>,
+    /// IDs of events that were dispatched to a worker or to the global event handler,
+    /// but not yet handled.
+    in_flight: Arc<Mutex<HashSet<i32>>>,
+}
+
+impl RepositoryWorkers {
+    fn new(in_flight: Arc<Mutex<HashSet<i32>>>) -> Self {
+        Self {
+            senders: HashMap::new(),
+            handles: vec![],
+            in_flight,
+        }
+    }
+
+    fn in_flight(&self) -> Vec<i32> {
+        self.in_flight.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Sends the event to the worker of its repository, spawning the worker if needed.
+    fn dispatch(
+        &mut self,
+        ctx: &Arc<BorsContext>,
+        event: QueuedEventModel,
+        repository_event: BorsRepositoryEvent,
+    ) {
+        let repo = repository_event.repository().clone();
+        let sender = self.senders.entry(repo.clone()).or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            self.handles.push(tokio::spawn(repository_worker(
+                ctx.clone(),
+                repo.clone(),
+                rx,
+                self.in_flight.clone(),
+            )));
+            tx
+        });
+
+        self.in_flight.lock().unwrap().insert(event.id);
+        REPOSITORY_QUEUE_DEPTH
+            .with_label_values(&[&repo.to_string()])
+            .inc();
+        if sender.send((event, repository_event)).is_err() {
+            tracing::error!("Worker of repository {repo} has ended");
+        }
+    }
+
+    /// Waits until all workers handle the events that were dispatched to them.
+    async fn finish(self) {
+        drop(self.senders);
+        for handle in self.handles {
+            if let Err(error) = handle.await {
+                // Propagate panics from handlers, so that tests fail on them.
+                if error.is_panic() {
+                    std::panic::resume_unwind(error.into_panic());
+                }
+            }
+        }
+    }
+}
+
+async fn repository_worker(
+    ctx: Arc<BorsContext>,
+    repo: GithubRepoName,
+    mut rx: mpsc::UnboundedReceiver<RepositoryWorkItem>,
+    in_flight: Arc<Mutex<HashSet<i32>>>,
+) {
+    // ID of an event that has failed and waits for its retry. Later events of the repository
+    // are left in the queue until it is retried, so that they are not handled before it.
+    let mut waiting_for_retry: Option<i32> = None;
+    while let Some((event, repository_event)) = rx.recv().await {
+        if let Some(failed_id) = waiting_for_retry.filter(|failed_id| *failed_id < event.id) {
+            tracing::debug!(
+                "Postponing event {} until event {failed_id} is retried",
+                event.id
+            );
+        } else {
+            let span = tracing::info_span!(
+                "RepositoryEvent",
+                repo = repo.to_string(),
+                event_id = event.id
+            );
+            tracing::debug!("Received repository event: {repository_event:#?}");
+            let result = handle_bors_repository_event(repository_event, ctx.clone())
+                .instrument(span.clone())
+                .await;
+            waiting_for_retry = match finish_queued_event(&ctx, &event, result, span).await {
+                Ok(QueuedEventStatus::Pending) => Some(event.id),
+                Ok(_) => None,
+                Err(error) => {
+                    tracing::error!("Cannot update state of event {}: {error:?}", event.id);
+                    None
+                }
+            };
+        }
+
+        in_flight.lock().unwrap().remove(&event.id);
+        REPOSITORY_QUEUE_DEPTH
+            .with_label_values(&[&repo.to_string()])
+            .dec();
+    }
+}
+
+/// Marks the event as done if it was handled successfully, or schedules its retry otherwise.
+/// Returns the new status of the event.
+async fn finish_queued_event(
+    ctx: &BorsContext,
+    event: &QueuedEventModel,
+    result: anyhow::Result<()>,
+    span: Span,
+) -> anyhow::Result<QueuedEventStatus> {
+    match result {
+        Ok(()) => {
+            ctx.db.mark_event_done(event).await?;
+            Ok(QueuedEventStatus::Done)
+        }
+        Err(error) => {
+            let next_attempt_at = Utc::now() + retry_backoff(event.attempts);
+            let status = ctx
+                .db
+                .mark_event_failed(
+                    event,
+                    &format!("{error:?}"),
+                    next_attempt_at,
+                    MAX_EVENT_ATTEMPTS,
+                )
+                .await?;
+            if status == QueuedEventStatus::Dead {
+                tracing::error!(
+                    "Event {} has failed {MAX_EVENT_ATTEMPTS} times, giving up",
+                    event.id
+                );
+            }
             handle_root_error(span, error);
+            Ok(status)
         }
     }
 }
 
+/// Returns how long to wait before retrying an event that has already been attempted
+/// `attempts` times. The delay grows exponentially up to ten minutes.
+fn retry_backoff(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.clamp(0, 6) as u32;
+    chrono::Duration::seconds((10 * 2i64.pow(exponent)).min(600))
+}
+
+/// Sources of global events.
+struct GlobalEventReceivers {
+    /// Events produced by bors itself, e.g. periodic refreshes.
+    global_rx: mpsc::Receiver<BorsGlobalEvent>,
+    /// Events from the persistent event queue.
+    queued_global_rx: mpsc::UnboundedReceiver<GlobalWorkItem>,
+    /// IDs of queued events that were dispatched, but not yet handled.
+    in_flight: Arc<Mutex<HashSet<i32>>>,
+}
+
 async fn consume_global_events(
     ctx: Arc<BorsContext>,
-    mut global_rx: mpsc::Receiver<BorsGlobalEvent>,
+    mut receivers: GlobalEventReceivers,
     gh_client: Octocrab,
     team_api: TeamApiClient,
 ) {
-    while let Some(event) = global_rx.recv().await {
-        let ctx = ctx.clone();
+    loop {
+        let (queued_event, event) = tokio::select! {
+            Some(event) = receivers.global_rx.recv() => (None, event),
+            Some((queued_event, event)) = receivers.queued_global_rx.recv() => {
+                (Some(queued_event), event)
+            }
+            else => break,
+        };
 
-        let span = tracing::info_span!("GlobalEvent");
+        let span = tracing::info_span!(
+            "GlobalEvent",
+            event_id = queued_event.as_ref().map(|event| event.id)
+        );
         tracing::debug!("Received global event: {event:#?}");
-        if let Err(error) = handle_bors_global_event(event, ctx, &gh_client, &team_api)
+        let result = handle_bors_global_event(event, ctx.clone(), &gh_client, &team_api)
             .instrument(span.clone())
-            .await
-        {
-            handle_root_error(span, error);
+            .await;
+        match queued_event {
+            // Queued events are finished only once they were handled, so that they are retried
+            // if the handler fails
+            Some(queued_event) => {
+                if let Err(error) = finish_queued_event(&ctx, &queued_event, result, span).await {
+                    tracing::error!(
+                        "Cannot update state of event {}: {error:?}",
+                        queued_event.id
+                    );
+                }
+                receivers.in_flight.lock().unwrap().remove(&queued_event.id);
+            }
+            None => {
+                if let Err(error) = result {
+                    handle_root_error(span, error);
+                }
+            }
         }
     }
 }
@@ -176,3 +603,248 @@ fn handle_root_error(span: Span, error: Error) {
         span.log_error(error);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+
+    use crate::PgDbClient;
+    use crate::database::QueuedEventStatus;
+    use crate::github::GithubRepoName;
+    use crate::github::server::{MAX_EVENT_ATTEMPTS, retry_backoff};
+    use crate::tests::mocks::{
+        BorsBuilder, Comment, GitHubState, PullRequest, Repo, User, default_pr_number, run_test,
+    };
+
+    #[test]
+    fn retry_backoff_grows_exponentially() {
+        assert_eq!(retry_backoff(0).num_seconds(), 10);
+        assert_eq!(retry_backoff(1).num_seconds(), 20);
+        assert_eq!(retry_backoff(3).num_seconds(), 80);
+        assert_eq!(retry_backoff(10).num_seconds(), 600);
+    }
+
+    #[sqlx::test]
+    async fn failed_event_is_dead_lettered(pool: sqlx::PgPool) {
+        let db = PgDbClient::new(pool);
+        db.enqueue_event(None, "push", None, "{}", QueuedEventStatus::Pending)
+            .await
+            .unwrap();
+
+        for attempt in 1..=MAX_EVENT_ATTEMPTS {
+            let event = db.get_due_events(10, &[]).await.unwrap().remove(0);
+            assert_eq!(event.attempts, attempt - 1);
+            let status = db
+                .mark_event_failed(&event, "error", Utc::now(), MAX_EVENT_ATTEMPTS)
+                .await
+                .unwrap();
+            let expected = if attempt == MAX_EVENT_ATTEMPTS {
+                QueuedEventStatus::Dead
+            } else {
+                QueuedEventStatus::Pending
+            };
+            assert_eq!(status, expected);
+        }
+        assert!(db.get_due_events(10, &[]).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn failed_event_blocks_later_events_of_repository(pool: sqlx::PgPool) {
+        let db = PgDbClient::new(pool);
+        for repo in ["foo/a", "foo/a", "foo/b"] {
+            db.enqueue_event(None, "push", Some(repo), "{}", QueuedEventStatus::Pending)
+                .await
+                .unwrap();
+        }
+
+        let event = db.get_due_events(10, &[]).await.unwrap().remove(0);
+        db.mark_event_failed(
+            &event,
+            "error",
+            Utc::now() + retry_backoff(0),
+            MAX_EVENT_ATTEMPTS,
+        )
+        .await
+        .unwrap();
+
+        let due: Vec<_> = db
+            .get_due_events(10, &[])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.repository)
+            .collect();
+        assert_eq!(due, vec![Some("foo/b".to_string())]);
+    }
+
+    #[sqlx::test]
+    async fn finished_events_are_deleted(pool: sqlx::PgPool) {
+        let db = PgDbClient::new(pool);
+        for status in [
+            QueuedEventStatus::Ignored,
+            QueuedEventStatus::Pending,
+            QueuedEventStatus::Pending,
+        ] {
+            db.enqueue_event(None, "push", None, "{}", status)
+                .await
+                .unwrap();
+        }
+        let event = db.get_due_events(10, &[]).await.unwrap().remove(0);
+        db.mark_event_done(&event).await.unwrap();
+
+        let deleted = db
+            .delete_finished_events(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(db.get_due_events(10, &[]).await.unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn repository_events_are_ordered_and_concurrent(pool: sqlx::PgPool) {
+        let other_repo = GithubRepoName::new("foo", "other");
+        let gh = GitHubState::default().with_repo(
+            Repo {
+                name: other_repo.clone(),
+                ..Repo::default()
+            }
+            .with_pr(PullRequest::new(
+                other_repo.clone(),
+                default_pr_number(),
+                User::default_pr_author(),
+                false,
+            )),
+        );
+        // Keep the worker of the default repository busy
+        gh.default_repo().lock().comment_delay = Duration::from_millis(500);
+
+        BorsBuilder::new(pool)
+            .github(gh)
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                tester.post_comment("@bors r-").await?;
+                tester
+                    .post_comment(Comment::new(
+                        other_repo.clone(),
+                        default_pr_number(),
+                        "@bors ping",
+                    ))
+                    .await?;
+
+                // The other repository does not wait for the events of the default repository
+                assert_eq!(
+                    tester
+                        .get_comment_on_repo(other_repo.clone(), default_pr_number())
+                        .await?,
+                    "Pong 🏓!"
+                );
+                assert!(
+                    tester
+                        .default_repo()
+                        .lock()
+                        .get_pr(default_pr_number())
+                        .comment_counter
+                        < 2
+                );
+
+                assert_eq!(
+                    tester.get_comment().await?,
+                    "Commit pr-1-sha has been approved by `default-user`"
+                );
+                assert_eq!(
+                    tester.get_comment().await?,
+                    "Commit pr-1-sha has been unapproved"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn metrics_endpoint(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+
+            let metrics = tester.get_page("/metrics").await?;
+            assert!(metrics.contains(r#"bors_commands_received_total{command="ping"}"#));
+            assert!(
+                metrics.contains(r#"bors_webhooks_received_total{event_type="issue_comment"}"#)
+            );
+            assert!(metrics.contains("bors_db_query_duration_seconds_bucket"));
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn duplicate_delivery_is_ignored(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+            tester.redeliver_last_webhook().await?;
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn handled_webhook_is_marked_done(pool: sqlx::PgPool) {
+        run_test(pool.clone(), |mut tester| async {
+            tester.post_comment("@bors ping").await?;
+            tester.expect_comments(1).await;
+            Ok(tester)
+        })
+        .await;
+        let db = PgDbClient::new(pool);
+        assert!(db.get_due_events(10, &[]).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn handled_global_webhook_is_marked_done(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.change_installation_repositories().await?;
+            let db = tester.db();
+            tester
+                .wait_for(|| {
+                    let db = db.clone();
+                    async move { Ok(db.get_due_events(10, &[]).await?.is_empty()) }
+                })
+                .await?;
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn queue_page_lists_approved_pr(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors r+").await?;
+            tester.expect_comments(1).await;
+
+            let page = tester.get_page("/queue/rust-lang/borstest").await?;
+            assert!(
+                page.contains(r#"<a href="https://github.com/rust-lang/borstest/pull/1">#1</a>"#)
+            );
+            assert!(page.contains("<td>approved</td><td>default-user</td>"));
+            assert!(!page.contains("Tree is closed"));
+            Ok(tester)
+        })
+        .await;
+    }
+
+    #[sqlx::test]
+    async fn queue_page_shows_closed_tree(pool: sqlx::PgPool) {
+        run_test(pool, |mut tester| async {
+            tester.post_comment("@bors treeclosed=5").await?;
+            tester.expect_comments(1).await;
+
+            let page = tester.get_page("/queue/rust-lang/borstest").await?;
+            assert!(page.contains("Tree is closed for PRs with priority less than 5"));
+            Ok(tester)
+        })
+        .await;
+    }
+}
@@ -4,7 +4,6 @@ use std::fmt::Debug;
 use axum::RequestExt;
 use axum::body::Bytes;
 use axum::extract::FromRequest;
-use axum::http::request::Parts;
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use hmac::{Hmac, Mac};
 use octocrab::models::events::payload::{
@@ -13,15 +12,16 @@ use octocrab::models::events::payload::{
 };
 use octocrab::models::pulls::{PullRequest, Review};
 use octocrab::models::webhook_events::payload::PullRequestWebhookEventAction;
-use octocrab::models::{App, Author, CheckRun, Repository, RunId, workflows};
+use octocrab::models::{App, Author, CheckRun, Label, Repository, RunId, workflows};
 use secrecy::{ExposeSecret, SecretString};
 use sha2::Sha256;
 
 use crate::bors::event::{
-    BorsEvent, BorsGlobalEvent, BorsRepositoryEvent, CheckSuiteCompleted, PullRequestClosed,
-    PullRequestComment, PullRequestConvertedToDraft, PullRequestEdited, PullRequestMerged,
-    PullRequestOpened, PullRequestPushed, PullRequestReadyForReview, PullRequestReopened,
-    PushToBranch, WorkflowCompleted, WorkflowStarted,
+    BorsEvent, BorsGlobalEvent, BorsRepositoryEvent, CheckSuiteCompleted, LabelChange,
+    PullRequestClosed, PullRequestComment, PullRequestConvertedToDraft, PullRequestEdited,
+    PullRequestLabelChanged, PullRequestMerged, PullRequestOpened, PullRequestPushed,
+    PullRequestReadyForReview, PullRequestReopened, PushToBranch, WorkflowCompleted,
+    WorkflowStarted,
 };
 use crate::database::{WorkflowStatus, WorkflowType};
 use crate::github::server::ServerStateRef;
@@ -46,6 +46,19 @@ pub struct WebhookPushToBranchEvent {
     repository: Repository,
     #[serde(rename = "ref")]
     ref_field: String,
+    after: String,
+    #[serde(default)]
+    commits: Vec<WebhookPushCommit>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct WebhookPushCommit {
+    #[serde(default)]
+    added: Vec<String>,
+    #[serde(default)]
+    removed: Vec<String>,
+    #[serde(default)]
+    modified: Vec<String>,
 }
 
 /// This struct is used to extract the repository and user from a GitHub webhook event.
@@ -108,6 +121,9 @@ struct WebhookPullRequestEvent {
     pull_request: PullRequest,
     changes: Option<WebhookPullRequestChanges>,
     repository: Repository,
+    /// Label that was added or removed, only present for `labeled` and `unlabeled` actions.
+    label: Option<Label>,
+    sender: Author,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -120,14 +136,52 @@ struct WebhookPullRequestBaseChanges {
     sha: Option<PullRequestEventChangesFrom>,
 }
 
-/// axum extractor for GitHub webhook events.
+/// A GitHub webhook event parsed into a [`BorsEvent`].
 #[derive(Debug)]
 pub struct GitHubWebhook(pub BorsEvent);
 
+/// Raw content of a GitHub webhook whose signature has been verified.
+///
+/// The payload is kept in its raw form so that it can be stored in the event queue and parsed
+/// again once the event is handled.
+#[derive(Debug)]
+pub struct WebhookPayload {
+    /// Unique ID of the delivery, used to ignore webhooks that are delivered more than once.
+    pub delivery_id: Option<String>,
+    pub event_type: String,
+    pub body: String,
+}
+
+impl WebhookPayload {
+    /// Parses the payload into a webhook event.
+    /// Returns `None` if bors is not interested in the event.
+    pub fn parse(&self) -> anyhow::Result<Option<GitHubWebhook>> {
+        Ok(parse_webhook_event(&self.event_type, self.body.as_bytes())?.map(GitHubWebhook))
+    }
+
+    /// Returns the full name of the repository that the webhook belongs to, if it has one.
+    pub fn repository(&self) -> Option<String> {
+        #[derive(serde::Deserialize)]
+        struct Payload {
+            repository: Option<PayloadRepository>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct PayloadRepository {
+            full_name: String,
+        }
+
+        serde_json::from_str::<Payload>(&self.body)
+            .ok()?
+            .repository
+            .map(|repository| repository.full_name)
+    }
+}
+
 const REQUEST_BODY_LIMIT: usize = 10 * 1024 * 1024;
 
-/// Extracts a webhook event from a HTTP request.
-impl FromRequest<ServerStateRef> for GitHubWebhook {
+/// Extracts a webhook payload from a HTTP request.
+impl FromRequest<ServerStateRef> for WebhookPayload {
     type Rejection = StatusCode;
 
     async fn from_request(
@@ -150,46 +204,59 @@ impl FromRequest<ServerStateRef> for GitHubWebhook {
             return Err(StatusCode::BAD_REQUEST);
         }
 
-        // Parse webhook content
-        match parse_webhook_event(parts, &body) {
-            Ok(Some(event)) => {
-                tracing::trace!("Received webhook event {event:?}");
-                Ok(GitHubWebhook(event))
-            }
-            Ok(None) => Err(StatusCode::OK),
-            Err(error) => {
-                tracing::error!("Cannot parse webhook event: {error:?}");
-                Err(StatusCode::BAD_REQUEST)
-            }
-        }
+        let Some(event_type) = parts.headers.get("x-github-event") else {
+            tracing::error!("x-github-event header not found");
+            return Err(StatusCode::BAD_REQUEST);
+        };
+        let event_type = event_type.to_str().map_err(|error| {
+            tracing::error!("Invalid x-github-event header: {error:?}");
+            StatusCode::BAD_REQUEST
+        })?;
+        let delivery_id = match parts.headers.get("x-github-delivery") {
+            Some(delivery_id) => Some(
+                delivery_id
+                    .to_str()
+                    .map_err(|error| {
+                        tracing::error!("Invalid x-github-delivery header: {error:?}");
+                        StatusCode::BAD_REQUEST
+                    })?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let body = String::from_utf8(body.to_vec()).map_err(|error| {
+            tracing::error!("Webhook body is not valid UTF-8: {error:?}");
+            StatusCode::BAD_REQUEST
+        })?;
+
+        Ok(WebhookPayload {
+            delivery_id,
+            event_type: event_type.to_string(),
+            body,
+        })
     }
 }
 
-fn parse_webhook_event(request: Parts, body: &[u8]) -> anyhow::Result<Option<BorsEvent>> {
-    let Some(event_type) = request.headers.get("x-github-event") else {
-        return Err(anyhow::anyhow!("x-github-event header not found"));
-    };
-
+fn parse_webhook_event(event_type: &str, body: &[u8]) -> anyhow::Result<Option<BorsEvent>> {
     tracing::trace!(
-        "Webhook: event_type `{}`, payload\n{}",
-        event_type.to_str().unwrap_or_default(),
+        "Webhook: event_type `{event_type}`, payload\n{}",
         std::str::from_utf8(body).unwrap_or_default()
     );
 
-    match event_type.as_bytes() {
-        b"push" => parse_push_event(body),
-        b"issue_comment" => parse_issue_comment_event(body),
-        b"pull_request" => parse_pull_request_events(body),
-        b"pull_request_review" => parse_pull_request_review_events(body),
-        b"pull_request_review_comment" => parse_pull_request_review_comment_events(body),
-        b"installation_repositories" | b"installation" => Ok(Some(BorsEvent::Global(
+    match event_type {
+        "push" => parse_push_event(body),
+        "issue_comment" => parse_issue_comment_event(body),
+        "pull_request" => parse_pull_request_events(body),
+        "pull_request_review" => parse_pull_request_review_events(body),
+        "pull_request_review_comment" => parse_pull_request_review_comment_events(body),
+        "installation_repositories" | "installation" => Ok(Some(BorsEvent::Global(
             BorsGlobalEvent::InstallationsChanged,
         ))),
-        b"workflow_run" => parse_workflow_run_events(body),
-        b"check_run" => parse_check_run_events(body),
-        b"check_suite" => parse_check_suite_events(body),
+        "workflow_run" => parse_workflow_run_events(body),
+        "check_run" => parse_check_run_events(body),
+        "check_suite" => parse_check_suite_events(body),
         _ => {
-            tracing::debug!("Ignoring unknown event type {:?}", event_type.to_str());
+            tracing::debug!("Ignoring unknown event type {event_type:?}");
             Ok(None)
         }
     }
@@ -205,8 +272,27 @@ fn parse_push_event(body: &[u8]) -> anyhow::Result<Option<BorsEvent>> {
         return Ok(None);
     };
 
+    let mut changed_files = payload
+        .commits
+        .into_iter()
+        .flat_map(|commit| {
+            commit
+                .added
+                .into_iter()
+                .chain(commit.removed)
+                .chain(commit.modified)
+        })
+        .collect::<Vec<_>>();
+    changed_files.sort();
+    changed_files.dedup();
+
     Ok(Some(BorsEvent::Repository(
-        BorsRepositoryEvent::PushToBranch(PushToBranch { repository, branch }),
+        BorsRepositoryEvent::PushToBranch(PushToBranch {
+            repository,
+            branch,
+            sha: CommitSha(payload.after),
+            changed_files,
+        }),
     )))
 }
 
@@ -290,6 +376,33 @@ fn parse_pull_request_events(body: &[u8]) -> anyhow::Result<Option<BorsEvent>> {
                 pull_request: payload.pull_request.into(),
             }),
         ))),
+        PullRequestWebhookEventAction::Labeled | PullRequestWebhookEventAction::Unlabeled => {
+            let Some(label) = payload.label else {
+                return Err(anyhow::anyhow!(
+                    "Labeled pull request event should have `label` field"
+                ));
+            };
+            let change = match payload.action {
+                PullRequestWebhookEventAction::Labeled => LabelChange::Added,
+                _ => LabelChange::Removed,
+            };
+            let html_url = payload
+                .pull_request
+                .html_url
+                .as_ref()
+                .map(|url| url.to_string())
+                .unwrap_or_default();
+            Ok(Some(BorsEvent::Repository(
+                BorsRepositoryEvent::PullRequestLabelChanged(PullRequestLabelChanged {
+                    repository: repository_name,
+                    pull_request: payload.pull_request.into(),
+                    author: payload.sender.into(),
+                    label: label.name,
+                    change,
+                    html_url,
+                }),
+            )))
+        }
         _ => Ok(None),
     }
 }
@@ -487,14 +600,19 @@ fn verify_gh_signature(
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use axum::extract::FromRequest;
     use hyper::StatusCode;
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
     use tokio::sync::mpsc;
 
-    use crate::bors::event::{BorsEvent, BorsGlobalEvent};
+    use crate::PgDbClient;
+
+    use crate::bors::event::{BorsEvent, BorsGlobalEvent, BorsRepositoryEvent, LabelChange};
     use crate::github::server::{ServerState, ServerStateRef};
-    use crate::github::webhook::GitHubWebhook;
     use crate::github::webhook::WebhookSecret;
+    use crate::github::webhook::{GitHubWebhook, WebhookPayload};
     use crate::tests::io::load_test_file;
     use crate::tests::webhook::{TEST_WEBHOOK_SECRET, create_webhook_request};
 
@@ -533,6 +651,12 @@ mod tests {
                                     name: "bors-kindergarten",
                                 },
                                 branch: "main",
+                                sha: CommitSha(
+                                    "bc7370e473896a94d40a7dff71f197a3ff0208f5",
+                                ),
+                                changed_files: [
+                                    "test.txt",
+                                ],
                             },
                         ),
                     ),
@@ -976,6 +1100,24 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn pull_request_labeled() {
+        let Ok(GitHubWebhook(BorsEvent::Repository(BorsRepositoryEvent::PullRequestLabelChanged(
+            event,
+        )))) = check_webhook("webhook/pull-request-labeled.json", "pull_request").await
+        else {
+            panic!("Expected a label change event");
+        };
+        assert_eq!(event.pull_request.number.0, 3);
+        assert_eq!(event.author.username, "geetanshjuneja");
+        assert_eq!(event.label, "S-waiting-on-bors");
+        assert_eq!(event.change, LabelChange::Added);
+        assert_eq!(
+            event.html_url,
+            "https://github.com/geetanshjuneja/test-bors/pull/3"
+        );
+    }
+
     #[tokio::test]
     async fn pull_request_reopened() {
         insta::assert_debug_snapshot!(
@@ -1416,15 +1558,21 @@ mod tests {
 
     async fn check_webhook(file: &str, event: &str) -> Result<GitHubWebhook, StatusCode> {
         let body = load_test_file(file);
-        let request = create_webhook_request(event, &body);
+        let request = create_webhook_request(event, &body, "delivery-1");
 
-        let (repository_tx, _) = mpsc::channel(1024);
-        let (global_tx, _) = mpsc::channel(1024);
+        let (queue_tx, _) = mpsc::channel(1);
+        // Webhook parsing does not touch the database, so the pool never has to connect.
+        let pool = PgPoolOptions::new().connect_lazy_with(PgConnectOptions::new());
         let server_ref = ServerStateRef::new(ServerState::new(
-            repository_tx,
-            global_tx,
+            queue_tx,
             WebhookSecret::new(TEST_WEBHOOK_SECRET.to_string()),
+            Arc::new(PgDbClient::new(pool)),
         ));
-        GitHubWebhook::from_request(request, &server_ref).await
+        let payload = WebhookPayload::from_request(request, &server_ref).await?;
+        match payload.parse() {
+            Ok(Some(webhook)) => Ok(webhook),
+            Ok(None) => Err(StatusCode::OK),
+            Err(_) => Err(StatusCode::BAD_REQUEST),
+        }
     }
 }
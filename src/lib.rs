@@ -1,11 +1,13 @@
 #![allow(async_fn_in_trait)]
 
 //! This is the library of the bors bot.
+mod api;
 mod bors;
 mod config;
 mod database;
 mod github;
 mod permissions;
+mod templates;
 mod utils;
 
 pub use bors::{BorsContext, CommandParser, event::BorsGlobalEvent, event::BorsRepositoryEvent};
@@ -1,9 +1,10 @@
 use octocrab::models::UserId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 use crate::github::GithubRepoName;
+use crate::github::api::client::GithubRepositoryClient;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum PermissionType {
@@ -43,6 +44,144 @@ impl UserPermissions {
     }
 }
 
+/// Source from which the permissions of users of a repository are loaded.
+/// Configured in the `[permissions]` section of `rust-bors.toml`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum PermissionSource {
+    /// Permissions are loaded from the Rust Team API.
+    #[default]
+    TeamApi,
+    /// Permissions are listed in the configuration, as GitHub user IDs.
+    Static {
+        #[serde(default)]
+        review: HashSet<UserId>,
+        #[serde(default, rename = "try")]
+        try_users: HashSet<UserId>,
+    },
+    /// Permissions are derived from the permission levels of repository collaborators.
+    Collaborators {
+        #[serde(default = "default_review_level")]
+        review: CollaboratorPermission,
+        #[serde(default = "default_try_level", rename = "try")]
+        try_level: CollaboratorPermission,
+    },
+    /// Permissions are granted to members of GitHub organization teams.
+    Teams {
+        #[serde(default)]
+        review_teams: Vec<GithubTeam>,
+        #[serde(default)]
+        try_teams: Vec<GithubTeam>,
+    },
+}
+
+fn default_review_level() -> CollaboratorPermission {
+    CollaboratorPermission::Maintain
+}
+
+fn default_try_level() -> CollaboratorPermission {
+    CollaboratorPermission::Write
+}
+
+/// Team of a GitHub organization, written as `<org>/<team-slug>` in the configuration.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "String")]
+pub struct GithubTeam {
+    pub org: String,
+    pub slug: String,
+}
+
+impl TryFrom<String> for GithubTeam {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.split_once('/') {
+            Some((org, slug)) if !org.is_empty() && !slug.is_empty() && !slug.contains('/') => {
+                Ok(Self {
+                    org: org.to_string(),
+                    slug: slug.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid team `{value}`, expected the `<org>/<team-slug>` format"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GithubTeam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.org, self.slug)
+    }
+}
+
+/// Permission level of a collaborator of a GitHub repository, ordered from the lowest one.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CollaboratorPermission {
+    Read,
+    Triage,
+    Write,
+    Maintain,
+    Admin,
+}
+
+/// Loads permissions of users of a repository from the given source.
+pub(crate) async fn load_permissions(
+    client: &GithubRepositoryClient,
+    source: &PermissionSource,
+    team_api_client: &TeamApiClient,
+) -> anyhow::Result<UserPermissions> {
+    let repo = client.repository();
+    tracing::info!("Reloading permissions for repository {repo}");
+
+    match source {
+        PermissionSource::TeamApi => team_api_client.load_permissions(repo).await,
+        PermissionSource::Static { review, try_users } => {
+            Ok(UserPermissions::new(review.clone(), try_users.clone()))
+        }
+        PermissionSource::Collaborators { review, try_level } => {
+            let collaborators = client
+                .get_collaborators()
+                .await
+                .map_err(|error| anyhow::anyhow!("Cannot load collaborators: {error:?}"))?;
+            let users_with_level = |level: CollaboratorPermission| {
+                collaborators
+                    .iter()
+                    .filter(|(_, permission)| *permission >= level)
+                    .map(|(user_id, _)| *user_id)
+                    .collect::<HashSet<_>>()
+            };
+            Ok(UserPermissions::new(
+                users_with_level(*review),
+                users_with_level(*try_level),
+            ))
+        }
+        PermissionSource::Teams {
+            review_teams,
+            try_teams,
+        } => {
+            // Members of a team that grants both permissions are only loaded once.
+            let mut members: HashMap<&GithubTeam, Vec<UserId>> = HashMap::new();
+            for team in review_teams.iter().chain(try_teams) {
+                if !members.contains_key(team) {
+                    members.insert(team, client.get_team_members(team).await?);
+                }
+            }
+            let members_of = |teams: &[GithubTeam]| {
+                teams
+                    .iter()
+                    .flat_map(|team| members[team].iter().copied())
+                    .collect::<HashSet<_>>()
+            };
+            Ok(UserPermissions::new(
+                members_of(review_teams),
+                members_of(try_teams),
+            ))
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub(crate) struct UserPermissionsResponse {
     github_ids: HashSet<UserId>,
@@ -63,8 +202,6 @@ impl TeamApiClient {
         &self,
         repo: &GithubRepoName,
     ) -> anyhow::Result<UserPermissions> {
-        tracing::info!("Reloading permissions for repository {repo}");
-
         let review_users: HashSet<UserId> = self
             .load_users(repo.name(), PermissionType::Review)
             .await
@@ -115,3 +252,124 @@ impl Default for TeamApiClient {
         Self::new("https://team-api.infra.rust-lang.org")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::permissions::{GithubTeam, PermissionType};
+    use crate::tests::mocks::{BorsBuilder, Comment, GitHubState, Permissions, User};
+
+    #[test]
+    fn parse_team() {
+        assert_eq!(
+            GithubTeam::try_from("rust-lang/compiler".to_string()),
+            Ok(GithubTeam {
+                org: "rust-lang".to_string(),
+                slug: "compiler".to_string()
+            })
+        );
+        assert!(GithubTeam::try_from("compiler".to_string()).is_err());
+        assert!(GithubTeam::try_from("rust-lang/".to_string()).is_err());
+        assert!(GithubTeam::try_from("a/b/c".to_string()).is_err());
+    }
+
+    #[sqlx::test]
+    async fn static_permissions(pool: sqlx::PgPool) {
+        let gh = GitHubState::default().with_default_config(
+            r#"
+[permissions]
+source = "static"
+review = [105]
+try = [104, 105]
+"#,
+        );
+        BorsBuilder::new(pool)
+            .github(gh)
+            .run_test(|mut tester| async {
+                tester.post_comment("@bors r+").await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"@default-user: :key: Insufficient privileges: not in review users"
+                );
+                tester
+                    .post_comment(Comment::from("@bors r+").with_author(User::reviewer()))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"Commit pr-1-sha has been approved by `reviewer`"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn collaborator_permissions(pool: sqlx::PgPool) {
+        let gh = GitHubState::default().with_default_config(
+            r#"
+[permissions]
+source = "collaborators"
+"#,
+        );
+        gh.default_repo().lock().permissions = Permissions::empty();
+        gh.default_repo()
+            .lock()
+            .permissions
+            .users
+            .insert(User::try_user(), vec![PermissionType::Try]);
+        BorsBuilder::new(pool)
+            .github(gh)
+            .run_test(|mut tester| async {
+                tester
+                    .post_comment(Comment::from("@bors r+").with_author(User::try_user()))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"@user-with-try-privileges: :key: Insufficient privileges: not in review users"
+                );
+                tester
+                    .post_comment(Comment::from("@bors try").with_author(User::try_user()))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @":hourglass: Trying commit pr-1-sha with merge merge-main-sha1-pr-1-sha-0…"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+
+    #[sqlx::test]
+    async fn team_permissions(pool: sqlx::PgPool) {
+        let gh = GitHubState::default()
+            .with_default_config(
+                r#"
+[permissions]
+source = "teams"
+review_teams = ["rust-lang/reviewers"]
+try_teams = ["rust-lang/reviewers", "rust-lang/contributors"]
+"#,
+            )
+            .with_team("rust-lang/reviewers", vec![User::reviewer()])
+            .with_team("rust-lang/contributors", vec![User::try_user()]);
+        BorsBuilder::new(pool)
+            .github(gh)
+            .run_test(|mut tester| async {
+                tester
+                    .post_comment(Comment::from("@bors r+").with_author(User::try_user()))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"@user-with-try-privileges: :key: Insufficient privileges: not in review users"
+                );
+                tester
+                    .post_comment(Comment::from("@bors r+").with_author(User::reviewer()))
+                    .await?;
+                insta::assert_snapshot!(
+                    tester.get_comment().await?,
+                    @"Commit pr-1-sha has been approved by `reviewer`"
+                );
+                Ok(tester)
+            })
+            .await;
+    }
+}
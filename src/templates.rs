@@ -0,0 +1,180 @@
+//! HTML pages served by the web server of the bot.
+use std::fmt::Write;
+
+use crate::database::{BuildModel, BuildStatus, MergeableState, PullRequestModel, TreeState};
+use crate::github::GithubRepoName;
+use crate::github::api::base_github_html_url;
+
+/// A single row of the merge queue page.
+pub struct QueueEntry {
+    pub pr: PullRequestModel,
+    /// Links to workflows of the latest try build of the PR.
+    pub try_build_urls: Vec<String>,
+}
+
+/// Merge queue of a single repository, sorted in the order in which PRs will be merged.
+pub struct QueueTemplate {
+    pub repo_name: GithubRepoName,
+    pub tree_state: TreeState,
+    pub entries: Vec<QueueEntry>,
+}
+
+impl QueueTemplate {
+    pub fn render(&self) -> String {
+        let repo = escape_html(&self.repo_name.to_string());
+        let approved = self
+            .entries
+            .iter()
+            .filter(|entry| entry.pr.is_approved())
+            .count();
+
+        let mut html = String::new();
+        writeln!(
+            html,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Bors queue - {repo}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+tr.approved {{ background-color: #e6ffed; }}
+tr.testing {{ background-color: #fff5b1; }}
+.treeclosed {{ background-color: #ffdce0; padding: 8px; margin-bottom: 1em; }}
+</style>
+</head>
+<body>
+<h1>Bors queue - <a href="{base}/{repo}">{repo}</a></h1>"#,
+            base = base_github_html_url(),
+        )
+        .unwrap();
+
+        if let TreeState::Closed { priority, source } = &self.tree_state {
+            writeln!(
+                html,
+                r#"<div class="treeclosed">Tree is closed for PRs with priority less than {priority} (<a href="{}">source</a>).</div>"#,
+                escape_html(source)
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            html,
+            r#"<p>{} pull request(s), {approved} approved.</p>
+<table>
+<thead>
+<tr><th>#</th><th>PR</th><th>Status</th><th>Approved by</th><th>Priority</th><th>Rollup</th><th>Mergeable</th><th>Tree</th><th>Try build</th></tr>
+</thead>
+<tbody>"#,
+            self.entries.len()
+        )
+        .unwrap();
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            self.render_entry(&mut html, index + 1, entry);
+        }
+
+        html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+        html
+    }
+
+    fn render_entry(&self, html: &mut String, position: usize, entry: &QueueEntry) {
+        let pr = &entry.pr;
+        let status = pr_status(pr);
+        let tree = match &self.tree_state {
+            TreeState::Closed { priority, .. } if pr.priority.unwrap_or(0) < *priority as i32 => {
+                "blocked"
+            }
+            _ => "",
+        };
+        let try_build = match &pr.try_build {
+            Some(build) => {
+                let links = entry
+                    .try_build_urls
+                    .iter()
+                    .enumerate()
+                    .map(|(index, url)| {
+                        format!(r#" <a href="{}">[{}]</a>"#, escape_html(url), index + 1)
+                    })
+                    .collect::<String>();
+                format!("{}{links}", build_status(build))
+            }
+            None => String::new(),
+        };
+
+        writeln!(
+            html,
+            r#"<tr class="{status}"><td>{position}</td><td><a href="{base}/{repo}/pull/{number}">#{number}</a></td><td>{status}</td><td>{approver}</td><td>{priority}</td><td>{rollup}</td><td>{mergeable}</td><td>{tree}</td><td>{try_build}</td></tr>"#,
+            base = base_github_html_url(),
+            repo = escape_html(&self.repo_name.to_string()),
+            number = pr.number,
+            approver = escape_html(pr.approver().unwrap_or_default()),
+            priority = pr.priority.map(|p| p.to_string()).unwrap_or_default(),
+            rollup = pr.rollup.map(|r| r.to_string()).unwrap_or_default(),
+            mergeable = mergeable_state(&pr.mergeable_state),
+        )
+        .unwrap();
+    }
+}
+
+fn pr_status(pr: &PullRequestModel) -> &'static str {
+    let auto_build_running = pr
+        .auto_build
+        .as_ref()
+        .is_some_and(|build| build.status == BuildStatus::Pending);
+    if auto_build_running {
+        "testing"
+    } else if pr.is_approved() {
+        "approved"
+    } else {
+        "unapproved"
+    }
+}
+
+fn build_status(build: &BuildModel) -> &'static str {
+    match build.status {
+        BuildStatus::Pending => "pending",
+        BuildStatus::Success => "success",
+        BuildStatus::Failure => "failure",
+        BuildStatus::Cancelled => "cancelled",
+        BuildStatus::Timeouted => "timed out",
+    }
+}
+
+fn mergeable_state(state: &MergeableState) -> &'static str {
+    match state {
+        MergeableState::Mergeable => "yes",
+        MergeableState::HasConflicts => "no",
+        MergeableState::Unknown => "",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_html;
+
+    #[test]
+    fn escape_html_special_characters() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+}
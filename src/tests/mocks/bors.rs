@@ -24,13 +24,15 @@ use crate::tests::mocks::workflow::{
 use crate::tests::mocks::{
     Branch, ExternalHttpMock, GitHubState, Repo, User, default_pr_number, default_repo_name,
 };
-use crate::tests::webhook::{TEST_WEBHOOK_SECRET, create_webhook_request};
+use crate::tests::webhook::{TEST_ADMIN_TOKEN, TEST_WEBHOOK_SECRET, create_webhook_request};
 use crate::{
     BorsContext, BorsGlobalEvent, CommandParser, PgDbClient, ServerState, WebhookSecret,
     create_app, create_bors_process,
 };
 
-use super::pull_request::{GitHubPullRequestEventPayload, PullRequestChangeEvent};
+use super::pull_request::{
+    GitHubPullRequestEventPayload, GitHubPushEventPayload, PullRequestChangeEvent,
+};
 use super::repository::PullRequest;
 
 pub struct BorsBuilder {
@@ -97,6 +99,10 @@ pub struct BorsTester {
     db: Arc<PgDbClient>,
     // Sender for bors global events
     global_tx: Sender<BorsGlobalEvent>,
+    // Number of webhooks sent so far, used to generate unique delivery IDs
+    webhook_count: u64,
+    // Event type, body and delivery ID of the last sent webhook
+    last_webhook: Option<(String, String, String)>,
 }
 
 impl BorsTester {
@@ -104,7 +110,7 @@ impl BorsTester {
         let mock = ExternalHttpMock::start(&github).await;
         let db = Arc::new(PgDbClient::new(pool));
 
-        let loaded_repos = load_repositories(&mock.github_client(), &mock.team_api_client())
+        let loaded_repos = load_repositories(&mock.github_client(), &mock.team_api_client(), &db)
             .await
             .unwrap();
         let mut repos = HashMap::default();
@@ -115,14 +121,15 @@ impl BorsTester {
 
         let ctx = BorsContext::new(CommandParser::new("@bors".to_string()), db.clone(), repos);
 
-        let (repository_tx, global_tx, bors_process) =
+        let (queue_tx, global_tx, bors_process) =
             create_bors_process(ctx, mock.github_client(), mock.team_api_client());
 
         let state = ServerState::new(
-            repository_tx,
-            global_tx.clone(),
+            queue_tx,
             WebhookSecret::new(TEST_WEBHOOK_SECRET.to_string()),
-        );
+            db.clone(),
+        )
+        .with_admin_token(TEST_ADMIN_TOKEN.to_string());
         let app = create_app(state);
         let bors = tokio::spawn(bors_process);
         (
@@ -132,6 +139,8 @@ impl BorsTester {
                 github,
                 db,
                 global_tx,
+                webhook_count: 0,
+                last_webhook: None,
             },
             bors,
         )
@@ -236,16 +245,92 @@ impl BorsTester {
         self.get_branch("automation/bors/try")
     }
 
+    pub fn auto_branch(&self) -> Branch {
+        self.get_branch("automation/bors/auto")
+    }
+
     /// Wait until the next bot comment is received on the default repo and the default PR.
     pub async fn get_comment(&mut self) -> anyhow::Result<String> {
+        self.get_comment_on_pr(default_pr_number()).await
+    }
+
+    /// Wait until the next bot comment is received on the default repo and the given PR.
+    pub async fn get_comment_on_pr(&mut self, pr_number: u64) -> anyhow::Result<String> {
+        self.get_comment_on_repo(Repo::default().name, pr_number)
+            .await
+    }
+
+    /// Wait until the next bot comment is received on the given repo and PR.
+    pub async fn get_comment_on_repo(
+        &mut self,
+        repo: GithubRepoName,
+        pr_number: u64,
+    ) -> anyhow::Result<String> {
         Ok(self
             .http_mock
             .gh_server
-            .get_comment(Repo::default().name, default_pr_number())
+            .get_comment(repo, pr_number)
             .await?
             .content)
     }
 
+    /// Send a GET request to the web server of bors and return the body of the response.
+    pub async fn get_page(&mut self, path: &str) -> anyhow::Result<String> {
+        let request = axum::http::Request::get(path).body(axum::body::Body::empty())?;
+        self.send_request(request).await
+    }
+
+    /// Send an authenticated request to the admin API of bors and return the body of the
+    /// response.
+    pub async fn admin_request(&mut self, method: &str, path: &str) -> anyhow::Result<String> {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(path)
+            .header("Authorization", format!("Bearer {TEST_ADMIN_TOKEN}"))
+            .body(axum::body::Body::empty())?;
+        self.send_request(request).await
+    }
+
+    async fn send_request(
+        &mut self,
+        request: axum::http::Request<axum::body::Body>,
+    ) -> anyhow::Result<String> {
+        let description = format!("{} {}", request.method(), request.uri());
+        let response = self
+            .app
+            .call(request)
+            .await
+            .with_context(|| format!("Cannot send {description} request"))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Wrong status code {status} for {description}"
+            ));
+        }
+        Ok(String::from_utf8(
+            axum::body::to_bytes(response.into_body(), 10 * 1024 * 1024)
+                .await?
+                .to_vec(),
+        )?)
+    }
+
+    /// Returns the delivery ID of the last webhook sent to bors.
+    pub fn last_delivery_id(&self) -> &str {
+        &self
+            .last_webhook
+            .as_ref()
+            .expect("No webhook has been sent")
+            .2
+    }
+
+    /// Sends the last webhook again, with the same delivery ID, like GitHub does when it
+    /// redelivers a webhook.
+    pub async fn redeliver_last_webhook(&mut self) -> anyhow::Result<()> {
+        let (event, body, delivery_id) =
+            self.last_webhook.clone().expect("No webhook has been sent");
+        self.deliver_webhook(&event, body, delivery_id).await
+    }
+
     //-- Generation of GitHub events --//
     pub async fn post_comment<C: Into<Comment>>(&mut self, comment: C) -> anyhow::Result<()> {
         self.webhook_comment(comment.into()).await
@@ -406,6 +491,52 @@ impl BorsTester {
         Ok(())
     }
 
+    /// Sends the "labeled" PR webhook to bors, as if `user` has added the label to the given PR.
+    pub async fn label_pr(
+        &mut self,
+        repo_name: GithubRepoName,
+        pr_number: u64,
+        label: &str,
+        user: User,
+    ) -> anyhow::Result<()> {
+        self.send_label_webhook(repo_name, pr_number, "labeled", label, user)
+            .await
+    }
+
+    /// Sends the "unlabeled" PR webhook to bors, as if `user` has removed the label from the
+    /// given PR.
+    pub async fn unlabel_pr(
+        &mut self,
+        repo_name: GithubRepoName,
+        pr_number: u64,
+        label: &str,
+        user: User,
+    ) -> anyhow::Result<()> {
+        self.send_label_webhook(repo_name, pr_number, "unlabeled", label, user)
+            .await
+    }
+
+    async fn send_label_webhook(
+        &mut self,
+        repo_name: GithubRepoName,
+        pr_number: u64,
+        action: &str,
+        label: &str,
+        user: User,
+    ) -> anyhow::Result<()> {
+        let pr = self
+            .github
+            .get_repo(&repo_name)
+            .lock()
+            .get_pr(pr_number)
+            .clone();
+        self.send_webhook(
+            "pull_request",
+            GitHubPullRequestEventPayload::new(pr, action, None).with_label(label, user),
+        )
+        .await
+    }
+
     pub async fn ready_for_review(
         &mut self,
         repo_name: GithubRepoName,
@@ -505,6 +636,27 @@ impl BorsTester {
         .await
     }
 
+    /// Sends a webhook notifying bors that the repositories of its installation have changed.
+    pub async fn change_installation_repositories(&mut self) -> anyhow::Result<()> {
+        self.send_webhook("installation_repositories", serde_json::json!({}))
+            .await
+    }
+
+    /// Sends a push webhook for a branch of the default repository, whose pushed commits
+    /// modified the given files.
+    pub async fn push_to_branch(
+        &mut self,
+        branch: &str,
+        modified_files: &[&str],
+    ) -> anyhow::Result<()> {
+        let sha = self.get_branch(branch).get_sha().to_string();
+        self.send_webhook(
+            "push",
+            GitHubPushEventPayload::new(branch, &sha, modified_files),
+        )
+        .await
+    }
+
     //-- Test assertions --//
     /// Expect that `count` comments will be received, without checking their contents.
     pub async fn expect_comments(&mut self, count: u64) {
@@ -592,7 +744,19 @@ impl BorsTester {
 
     async fn send_webhook<S: Serialize>(&mut self, event: &str, content: S) -> anyhow::Result<()> {
         let serialized = serde_json::to_string(&content)?;
-        let webhook = create_webhook_request(event, &serialized);
+        self.webhook_count += 1;
+        let delivery_id = format!("delivery-{}", self.webhook_count);
+        self.deliver_webhook(event, serialized, delivery_id).await
+    }
+
+    async fn deliver_webhook(
+        &mut self,
+        event: &str,
+        body: String,
+        delivery_id: String,
+    ) -> anyhow::Result<()> {
+        let webhook = create_webhook_request(event, &body, &delivery_id);
+        self.last_webhook = Some((event.to_string(), body, delivery_id));
         let response = self
             .app
             .call(webhook)
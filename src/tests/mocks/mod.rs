@@ -13,11 +13,15 @@ use crate::tests::mocks::github::GitHubMockServer;
 use crate::tests::mocks::permissions::TeamApiMockServer;
 
 pub use bors::BorsBuilder;
+pub use bors::BorsTester;
 pub use bors::run_test;
 pub use comment::Comment;
 pub use permissions::Permissions;
 pub use pull_request::default_pr_number;
 pub use repository::Branch;
+pub use repository::CommitStatus;
+pub use repository::ConfigChange;
+pub use repository::PullRequest;
 pub use repository::Repo;
 pub use repository::default_branch_name;
 pub use repository::default_repo_name;
@@ -40,12 +44,15 @@ mod workflow;
 /// Represents the state of GitHub.
 pub struct GitHubState {
     repos: HashMap<GithubRepoName, Arc<Mutex<Repo>>>,
+    /// Members of organization teams, keyed by `<org>/<team-slug>`.
+    teams: HashMap<String, Vec<User>>,
 }
 
 impl GitHubState {
     pub fn new() -> Self {
         Self {
             repos: Default::default(),
+            teams: Default::default(),
         }
     }
 
@@ -80,6 +87,11 @@ impl GitHubState {
         self
     }
 
+    pub fn with_team(mut self, team: &str, members: Vec<User>) -> Self {
+        self.teams.insert(team.to_string(), members);
+        self
+    }
+
     pub fn check_sha_history(&self, repo: GithubRepoName, branch: &str, expected_shas: &[&str]) {
         let actual_shas = self
             .get_repo(&repo)
@@ -97,6 +109,13 @@ impl GitHubState {
             expected_run_ids
         );
     }
+
+    pub fn check_rerun_workflows(&self, repo: GithubRepoName, expected_run_ids: &[u64]) {
+        assert_eq!(
+            &self.get_repo(&repo).lock().rerun_workflows,
+            expected_run_ids
+        );
+    }
 }
 
 impl Default for GitHubState {
@@ -104,6 +123,7 @@ impl Default for GitHubState {
         let repo = Repo::default();
         Self {
             repos: HashMap::from([(repo.name.clone(), Arc::new(Mutex::new(repo)))]),
+            teams: Default::default(),
         }
     }
 }
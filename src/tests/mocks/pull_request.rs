@@ -5,7 +5,7 @@ use super::{
     repository::GitHubRepository,
     user::GitHubUser,
 };
-use crate::tests::mocks::repository::PullRequest;
+use crate::tests::mocks::repository::{ConfigChange, PullRequest};
 use crate::{bors::PullRequestStatus, github::GithubRepoName};
 use chrono::{DateTime, Utc};
 use octocrab::models::LabelId;
@@ -48,9 +48,109 @@ pub async fn mock_pull_requests(
             .mount(mock_server)
             .await;
 
+        let repo_clone = repo.clone();
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/repos/{repo_name}/pulls/{pr_number}/commits"
+            )))
+            .respond_with(move |_: &Request| {
+                let repo = repo_clone.lock();
+                let pr = repo.get_pr(pr_number);
+                let commits = if pr.commits.is_empty() {
+                    vec![pr.head_sha.clone()]
+                } else {
+                    pr.commits.clone()
+                };
+                let commits = commits
+                    .into_iter()
+                    .map(|sha| GitHubPullRequestCommit {
+                        commit: GitHubCommitDetail {
+                            message: pr
+                                .commit_messages
+                                .get(&sha)
+                                .cloned()
+                                .unwrap_or_else(|| format!("Commit {sha}")),
+                            author: GitHubCommitAuthor {
+                                name: pr.author.name.clone(),
+                                email: format!("{}@example.com", pr.author.name),
+                                date: "2025-01-01T00:00:00Z".to_string(),
+                            },
+                        },
+                        sha,
+                    })
+                    .collect::<Vec<_>>();
+                ResponseTemplate::new(200).set_body_json(commits)
+            })
+            .mount(mock_server)
+            .await;
+
         mock_pr_comments(repo.clone(), pr_number, comments_tx.clone(), mock_server).await;
         mock_pr_labels(repo.clone(), repo_name.clone(), pr_number, mock_server).await;
     }
+
+    mock_pull_request_files(repo.clone(), mock_server).await;
+    mock_create_pull_request(repo, mock_server).await;
+}
+
+async fn mock_pull_request_files(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+    dynamic_mock_req(
+        move |_req: &Request, [pr_number]: [&str; 1]| {
+            let pr_number: u64 = pr_number.parse().unwrap();
+            let repo = repo.lock();
+            let Some(pr) = repo.pull_requests.get(&pr_number) else {
+                return ResponseTemplate::new(404);
+            };
+            let mut files = vec![GitHubPullRequestFile {
+                filename: "src/lib.rs".to_string(),
+                status: "modified".to_string(),
+            }];
+            if let Some(change) = &pr.config_change {
+                files.push(GitHubPullRequestFile {
+                    filename: "rust-bors.toml".to_string(),
+                    status: match change {
+                        ConfigChange::Modified(_) => "modified",
+                        ConfigChange::Removed => "removed",
+                    }
+                    .to_string(),
+                });
+            }
+            ResponseTemplate::new(200).set_body_json(files)
+        },
+        "GET",
+        format!("^/repos/{repo_name}/pulls/([0-9]+)/files$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
+async fn mock_create_pull_request(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+    Mock::given(method("POST"))
+        .and(path(format!("/repos/{repo_name}/pulls")))
+        .respond_with(move |req: &Request| {
+            #[derive(Deserialize)]
+            struct CreatePullRequestPayload {
+                head: String,
+                base: String,
+            }
+
+            let payload: CreatePullRequestPayload = req.body_json().unwrap();
+            let mut repo = repo.lock();
+            let number = repo.pull_requests.keys().max().copied().unwrap_or(0) + 1;
+            let mut pr = PullRequest::new(repo.name.clone(), number, User::bors_bot(), false);
+            if let Some(head) = repo.get_branch_by_name(&payload.head) {
+                pr.head_sha = head.get_sha().to_string();
+            }
+            let Some(base) = repo.get_branch_by_name(&payload.base) else {
+                return ResponseTemplate::new(422);
+            };
+            pr.base_branch = base.clone();
+            repo.pull_requests.insert(number, pr.clone());
+            ResponseTemplate::new(201).set_body_json(GitHubPullRequest::from(pr))
+        })
+        .mount(mock_server)
+        .await;
 }
 
 async fn mock_pr_comments(
@@ -83,7 +183,9 @@ async fn mock_pr_comments(
             // from within an async task, but it is not async, so we also cannot use
             // `tx.send()`.
             comments_tx.try_send(comment.clone()).unwrap();
-            ResponseTemplate::new(201).set_body_json(GitHubComment::from(comment))
+            ResponseTemplate::new(201)
+                .set_body_json(GitHubComment::from(comment))
+                .set_delay(repo.comment_delay)
         })
         .mount(mock_server)
         .await;
@@ -229,6 +331,9 @@ pub(super) struct GitHubPullRequestEventPayload {
     pull_request: GitHubPullRequest,
     changes: Option<GitHubPullRequestChanges>,
     repository: GitHubRepository,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<GitHubLabel>,
+    sender: GitHubUser,
 }
 
 impl GitHubPullRequestEventPayload {
@@ -238,13 +343,32 @@ impl GitHubPullRequestEventPayload {
         changes: Option<PullRequestChangeEvent>,
     ) -> Self {
         let repository = pull_request.repo.clone();
+        let sender = pull_request.author.clone().into();
         GitHubPullRequestEventPayload {
             action: action.to_string(),
             pull_request: pull_request.into(),
             changes: changes.map(Into::into),
             repository: repository.into(),
+            label: None,
+            sender,
         }
     }
+
+    /// Sets the label of a `labeled` or `unlabeled` event and the user who has changed it.
+    pub fn with_label(mut self, label: &str, sender: User) -> Self {
+        self.label = Some(GitHubLabel {
+            id: 1.into(),
+            node_id: "".to_string(),
+            url: format!("https://github.com/labels/{label}")
+                .parse()
+                .unwrap(),
+            name: label.to_string(),
+            color: "blue".to_string(),
+            default: false,
+        });
+        self.sender = sender.into();
+        self
+    }
 }
 
 #[derive(Serialize)]
@@ -288,14 +412,53 @@ pub struct GitHubPushEventPayload {
     pub repository: GitHubRepository,
     #[serde(rename = "ref")]
     pub ref_field: String,
+    pub after: String,
+    pub commits: Vec<GitHubPushCommit>,
+}
+
+#[derive(Serialize)]
+pub struct GitHubPushCommit {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
 }
 
 impl GitHubPushEventPayload {
-    #[allow(unused)]
-    pub fn new(branch_name: &str) -> Self {
+    pub fn new(branch_name: &str, sha: &str, modified_files: &[&str]) -> Self {
         GitHubPushEventPayload {
             repository: default_repo_name().into(),
             ref_field: format!("refs/heads/{branch_name}"),
+            after: sha.to_string(),
+            commits: vec![GitHubPushCommit {
+                added: vec![],
+                removed: vec![],
+                modified: modified_files.iter().map(|file| file.to_string()).collect(),
+            }],
         }
     }
 }
+
+#[derive(Serialize)]
+struct GitHubPullRequestFile {
+    filename: String,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct GitHubPullRequestCommit {
+    sha: String,
+    commit: GitHubCommitDetail,
+}
+
+#[derive(Serialize)]
+struct GitHubCommitDetail {
+    message: String,
+    author: GitHubCommitAuthor,
+}
+
+#[derive(Serialize)]
+struct GitHubCommitAuthor {
+    name: String,
+    email: String,
+    date: String,
+}
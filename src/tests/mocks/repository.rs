@@ -1,5 +1,10 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
-use std::{collections::HashMap, time::SystemTime};
+use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
 
 use crate::bors::{CheckSuiteStatus, PullRequestStatus};
 use base64::Engine;
@@ -38,6 +43,20 @@ pub struct PullRequest {
     pub mergeable_state: MergeableState,
     pub status: PullRequestStatus,
     pub merged_at: Option<DateTime<Utc>>,
+    /// SHAs of the commits of the PR. If empty, the PR only contains its head commit.
+    pub commits: Vec<String>,
+    /// Messages of the PR commits by their SHA. Other commits have the message `Commit <sha>`.
+    pub commit_messages: HashMap<String, String>,
+    /// Change of `rust-bors.toml` made by the PR.
+    pub config_change: Option<ConfigChange>,
+}
+
+/// Describes how a pull request changes the configuration file of the repository.
+#[derive(Clone)]
+pub enum ConfigChange {
+    /// The file has the given content at the head of the PR.
+    Modified(String),
+    Removed,
 }
 
 impl PullRequest {
@@ -58,6 +77,9 @@ impl PullRequest {
                 PullRequestStatus::Open
             },
             merged_at: None,
+            commits: vec![],
+            commit_messages: Default::default(),
+            config_change: None,
         }
     }
 }
@@ -112,10 +134,27 @@ pub struct Repo {
     pub branches: Vec<Branch>,
     pub cancelled_workflows: Vec<u64>,
     pub workflow_cancel_error: bool,
+    pub rerun_workflows: Vec<u64>,
+    /// Commits created through the Git Data API.
+    pub created_commits: Vec<GitCommit>,
+    /// Parents of the commits created by merges and through the Git Data API.
+    pub commit_parents: HashMap<String, Vec<String>>,
+    /// SHAs of commits that do not exist, branches cannot be set to them.
+    pub unknown_shas: HashSet<String>,
+    /// SHAs of commits that are already contained in every branch, merging them does nothing.
+    pub merged_shas: HashSet<String>,
+    pub created_issues: Vec<Issue>,
+    /// Check runs created by the bot, indexed by their ID minus one.
+    pub created_check_runs: Vec<CreatedCheckRun>,
+    /// Commit statuses published by the bot, in the order in which they were published.
+    pub commit_statuses: Vec<CommitStatus>,
     pub pull_requests: HashMap<u64, PullRequest>,
     // Cause pull request fetch to fail.
     pub pull_request_error: bool,
     pub pr_push_counter: u64,
+    /// Delay of the responses to comments posted by the bot, which keeps the handler of the
+    /// event that posts the comment busy.
+    pub comment_delay: Duration,
 }
 
 impl Repo {
@@ -128,8 +167,17 @@ impl Repo {
             branches: vec![Branch::default()],
             cancelled_workflows: vec![],
             workflow_cancel_error: false,
+            rerun_workflows: vec![],
+            created_commits: vec![],
+            commit_parents: Default::default(),
+            unknown_shas: Default::default(),
+            merged_shas: Default::default(),
+            created_issues: vec![],
+            created_check_runs: vec![],
+            commit_statuses: vec![],
             pull_request_error: false,
             pr_push_counter: 0,
+            comment_delay: Duration::ZERO,
         }
     }
 
@@ -160,6 +208,16 @@ impl Repo {
         self.branches.iter_mut().find(|b| b.sha == sha)
     }
 
+    /// Returns true if `sha` is `ancestor` or if it descends from it.
+    pub fn is_ancestor(&self, ancestor: &str, sha: &str) -> bool {
+        sha == ancestor
+            || self.commit_parents.get(sha).is_some_and(|parents| {
+                parents
+                    .iter()
+                    .any(|parent| self.is_ancestor(ancestor, parent))
+            })
+    }
+
     pub fn add_cancelled_workflow(&mut self, run_id: u64) {
         self.cancelled_workflows.push(run_id);
     }
@@ -170,6 +228,44 @@ impl Repo {
     }
 }
 
+/// A commit created through the Git Data API.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitCommit {
+    pub sha: String,
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub message: String,
+    pub author: Option<String>,
+}
+
+/// An issue opened in the repository.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+}
+
+/// A check run created by the bot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreatedCheckRun {
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    pub conclusion: Option<String>,
+    pub title: String,
+    pub summary: String,
+}
+
+/// A commit status published by the bot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitStatus {
+    pub sha: String,
+    pub context: String,
+    pub state: String,
+    pub description: String,
+}
+
 /// Represents the default repository for tests.
 /// It uses a basic configuration that might be also encountered on a real repository.
 ///
@@ -215,6 +311,12 @@ pub struct Branch {
     suite_statuses: Vec<CheckSuiteStatus>,
     merge_counter: u64,
     pub merge_conflict: bool,
+    /// Status checks required by the branch protection of this branch.
+    pub required_checks: Vec<String>,
+    /// Check runs reported for the current commit of this branch.
+    pub check_runs: Vec<(String, CheckSuiteStatus)>,
+    /// Commit statuses reported for the current commit of this branch, by their context.
+    pub statuses: Vec<(String, CheckSuiteStatus)>,
 }
 
 impl Branch {
@@ -227,6 +329,9 @@ impl Branch {
             suite_statuses: vec![CheckSuiteStatus::Pending],
             merge_counter: 0,
             merge_conflict: false,
+            required_checks: vec![],
+            check_runs: vec![],
+            statuses: vec![],
         }
     }
 
@@ -236,6 +341,9 @@ impl Branch {
     pub fn get_sha(&self) -> &str {
         &self.sha
     }
+    pub fn get_commit_message(&self) -> &str {
+        &self.commit_message
+    }
     pub fn get_suites(&self) -> &[CheckSuiteStatus] {
         &self.suite_statuses
     }
@@ -326,15 +434,150 @@ pub async fn mock_repo(
     mock_pull_requests(repo.clone(), comments_tx, mock_server).await;
     mock_branches(repo.clone(), mock_server).await;
     mock_cancel_workflow(repo.clone(), mock_server).await;
+    mock_rerun_workflow(repo.clone(), mock_server).await;
+    mock_collaborators(repo.clone(), mock_server).await;
+    mock_create_issue(repo.clone(), mock_server).await;
+    mock_create_check_runs(repo.clone(), mock_server).await;
+    mock_create_commit_status(repo.clone(), mock_server).await;
     mock_config(repo, mock_server).await;
 }
 
+#[derive(serde::Deserialize)]
+struct CheckRunRequest {
+    name: Option<String>,
+    head_sha: Option<String>,
+    status: String,
+    conclusion: Option<String>,
+    output: CheckRunOutput,
+}
+
+#[derive(serde::Deserialize)]
+struct CheckRunOutput {
+    title: String,
+    summary: String,
+}
+
+#[derive(Serialize)]
+struct CheckRunIdResponse {
+    id: u64,
+}
+
+async fn mock_create_check_runs(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+    let repo2 = repo.clone();
+    Mock::given(method("POST"))
+        .and(path(format!("/repos/{repo_name}/check-runs")))
+        .respond_with(move |req: &Request| {
+            let payload: CheckRunRequest = req.body_json().unwrap();
+            let mut repo = repo.lock();
+            repo.created_check_runs.push(CreatedCheckRun {
+                name: payload.name.unwrap(),
+                head_sha: payload.head_sha.unwrap(),
+                status: payload.status,
+                conclusion: payload.conclusion,
+                title: payload.output.title,
+                summary: payload.output.summary,
+            });
+            let id = repo.created_check_runs.len() as u64;
+            ResponseTemplate::new(201).set_body_json(CheckRunIdResponse { id })
+        })
+        .mount(mock_server)
+        .await;
+
+    dynamic_mock_req(
+        move |req: &Request, [id]: [&str; 1]| {
+            let id: u64 = id.parse().unwrap();
+            let payload: CheckRunRequest = req.body_json().unwrap();
+            let mut repo = repo2.lock();
+            let Some(check_run) = repo.created_check_runs.get_mut(id as usize - 1) else {
+                return ResponseTemplate::new(404);
+            };
+            check_run.status = payload.status;
+            check_run.conclusion = payload.conclusion;
+            check_run.title = payload.output.title;
+            check_run.summary = payload.output.summary;
+            ResponseTemplate::new(200).set_body_json(CheckRunIdResponse { id })
+        },
+        "PATCH",
+        format!("^/repos/{repo_name}/check-runs/([0-9]+)$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
+async fn mock_create_commit_status(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    #[derive(serde::Deserialize)]
+    struct CommitStatusPayload {
+        state: String,
+        context: String,
+        description: String,
+    }
+
+    let repo_name = repo.lock().name.clone();
+    dynamic_mock_req(
+        move |req: &Request, [sha]: [&str; 1]| {
+            let payload: CommitStatusPayload = req.body_json().unwrap();
+            repo.lock().commit_statuses.push(CommitStatus {
+                sha: sha.to_string(),
+                context: payload.context,
+                state: payload.state,
+                description: payload.description,
+            });
+            ResponseTemplate::new(201).set_body_json(serde_json::json!({}))
+        },
+        "POST",
+        format!("^/repos/{repo_name}/statuses/(.+)$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
+async fn mock_create_issue(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+    Mock::given(method("POST"))
+        .and(path(format!("/repos/{repo_name}/issues")))
+        .respond_with(move |req: &Request| {
+            #[derive(serde::Deserialize)]
+            struct CreateIssuePayload {
+                title: String,
+                body: String,
+            }
+
+            #[derive(Serialize)]
+            struct CreateIssueResponse {
+                number: u64,
+            }
+
+            let payload: CreateIssuePayload = req.body_json().unwrap();
+            let mut repo = repo.lock();
+            let number = repo
+                .pull_requests
+                .keys()
+                .copied()
+                .chain(repo.created_issues.iter().map(|issue| issue.number))
+                .max()
+                .unwrap_or(0)
+                + 1;
+            repo.created_issues.push(Issue {
+                number,
+                title: payload.title,
+                body: payload.body,
+            });
+            ResponseTemplate::new(201).set_body_json(CreateIssueResponse { number })
+        })
+        .mount(mock_server)
+        .await;
+}
+
 async fn mock_branches(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
     mock_get_branch(repo.clone(), mock_server).await;
     mock_create_branch(repo.clone(), mock_server).await;
     mock_update_branch(repo.clone(), mock_server).await;
     mock_merge_branch(repo.clone(), mock_server).await;
-    mock_check_suites(repo, mock_server).await;
+    mock_git_commits(repo.clone(), mock_server).await;
+    mock_check_suites(repo.clone(), mock_server).await;
+    mock_check_runs(repo.clone(), mock_server).await;
+    mock_commit_statuses(repo, mock_server).await;
 }
 
 async fn mock_cancel_workflow(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
@@ -357,6 +600,21 @@ async fn mock_cancel_workflow(repo: Arc<Mutex<Repo>>, mock_server: &MockServer)
     .await;
 }
 
+async fn mock_rerun_workflow(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+    dynamic_mock_req(
+        move |_req: &Request, [run_id]: [&str; 1]| {
+            let run_id: u64 = run_id.parse().unwrap();
+            repo.lock().rerun_workflows.push(run_id);
+            ResponseTemplate::new(201)
+        },
+        "POST",
+        format!("^/repos/{repo_name}/actions/runs/(.*)/rerun-failed-jobs$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
 async fn mock_get_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
     let repo_name = repo.lock().name.clone();
     dynamic_mock_req(
@@ -373,7 +631,12 @@ async fn mock_get_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
                         .parse()
                         .unwrap(),
                 },
-                protected: false,
+                protected: !branch.required_checks.is_empty(),
+                protection: (!branch.required_checks.is_empty()).then(|| GitHubBranchProtection {
+                    required_status_checks: GitHubRequiredStatusChecks {
+                        contexts: branch.required_checks.clone(),
+                    },
+                }),
             };
             ResponseTemplate::new(200).set_body_json(branch)
         },
@@ -441,11 +704,23 @@ async fn mock_update_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
             #[derive(serde::Deserialize)]
             struct SetRefRequest {
                 sha: String,
+                force: bool,
             }
 
             let data: SetRefRequest = req.body_json().unwrap();
 
             let sha = data.sha;
+            if repo.unknown_shas.contains(&sha) {
+                return ResponseTemplate::new(422);
+            }
+            let current_sha = repo
+                .get_branch_by_name(branch_name)
+                .map(|branch| branch.sha.clone());
+            let is_fast_forward =
+                current_sha.is_none_or(|current_sha| repo.is_ancestor(&current_sha, &sha));
+            if !data.force && !is_fast_forward {
+                return ResponseTemplate::new(422);
+            }
             match repo.get_branch_by_name(branch_name) {
                 Some(branch) => {
                     // Update branch
@@ -490,6 +765,10 @@ async fn mock_merge_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
                     branch.sha.clone()
                 }
             };
+            if repo.merged_shas.contains(&head_sha) {
+                // Nothing to merge
+                return ResponseTemplate::new(204);
+            }
             let Some(base_branch) = repo.get_branch_by_name(&data.base) else {
                 return ResponseTemplate::new(404);
             };
@@ -503,8 +782,11 @@ async fn mock_merge_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
                 base_branch.sha, base_branch.merge_counter
             );
             base_branch.merge_counter += 1;
+            let base_sha = base_branch.sha.clone();
             base_branch.set_to_sha(&merge_sha);
             base_branch.commit_message = data.commit_message;
+            repo.commit_parents
+                .insert(merge_sha.clone(), vec![base_sha, head_sha]);
 
             #[derive(serde::Serialize)]
             struct MergeResponse {
@@ -517,6 +799,81 @@ async fn mock_merge_branch(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
         .await;
 }
 
+async fn mock_git_commits(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    let repo_name = repo.lock().name.clone();
+
+    let repo_clone = repo.clone();
+    dynamic_mock_req(
+        move |_req: &Request, [sha]: [&str; 1]| {
+            let repo = repo_clone.lock();
+            // Commits created by bors keep their tree, other commits get a fake one
+            let tree = repo
+                .created_commits
+                .iter()
+                .find(|commit| commit.sha == sha)
+                .map(|commit| commit.tree.clone())
+                .unwrap_or_else(|| format!("{sha}-tree"));
+            ResponseTemplate::new(200).set_body_json(GitHubGitCommit {
+                sha: sha.to_string(),
+                tree: GitHubGitTree { sha: tree },
+            })
+        },
+        "GET",
+        format!("^/repos/{repo_name}/git/commits/(.*)$"),
+    )
+    .mount(mock_server)
+    .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("/repos/{repo_name}/git/commits")))
+        .respond_with(move |request: &Request| {
+            let mut repo = repo.lock();
+
+            #[derive(serde::Deserialize)]
+            struct Author {
+                name: String,
+            }
+
+            #[derive(serde::Deserialize)]
+            struct CreateCommitRequest {
+                message: String,
+                tree: String,
+                parents: Vec<String>,
+                author: Option<Author>,
+            }
+
+            let data: CreateCommitRequest = request.body_json().unwrap();
+            let sha = format!("commit-{}", repo.created_commits.len() + 1);
+            repo.commit_parents
+                .insert(sha.clone(), data.parents.clone());
+            repo.created_commits.push(GitCommit {
+                sha: sha.clone(),
+                tree: data.tree,
+                parents: data.parents,
+                message: data.message,
+                author: data.author.map(|author| author.name),
+            });
+            #[derive(serde::Serialize)]
+            struct CreateCommitResponse {
+                sha: String,
+            }
+            ResponseTemplate::new(201).set_body_json(CreateCommitResponse { sha })
+        })
+        .mount(mock_server)
+        .await;
+}
+
+#[derive(Serialize)]
+struct GitHubGitCommit {
+    sha: String,
+    tree: GitHubGitTree,
+}
+
+#[derive(Serialize)]
+struct GitHubGitTree {
+    sha: String,
+}
+
 async fn mock_check_suites(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
     #[derive(serde::Serialize)]
     struct CheckSuitePayload {
@@ -559,22 +916,177 @@ async fn mock_check_suites(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
     .await;
 }
 
+async fn mock_check_runs(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    #[derive(serde::Serialize)]
+    struct CheckRunPayload {
+        name: String,
+        status: String,
+        conclusion: Option<String>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct CheckRunResponse {
+        total_count: usize,
+        check_runs: Vec<CheckRunPayload>,
+    }
+
+    let repo_name = repo.lock().name.clone();
+    dynamic_mock_req(
+        move |_req: &Request, [sha]: [&str; 1]| {
+            let mut repo = repo.lock();
+            let Some(branch) = repo.get_branch_by_sha(sha) else {
+                return ResponseTemplate::new(404);
+            };
+            let check_runs: Vec<CheckRunPayload> = branch
+                .check_runs
+                .iter()
+                .map(|(name, status)| {
+                    let (status, conclusion) = match status {
+                        CheckSuiteStatus::Pending => ("in_progress", None),
+                        CheckSuiteStatus::Success => ("completed", Some("success".to_string())),
+                        CheckSuiteStatus::Failure => ("completed", Some("failure".to_string())),
+                    };
+                    CheckRunPayload {
+                        name: name.clone(),
+                        status: status.to_string(),
+                        conclusion,
+                    }
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(CheckRunResponse {
+                total_count: check_runs.len(),
+                check_runs,
+            })
+        },
+        "GET",
+        format!("^/repos/{repo_name}/commits/(.*)/check-runs$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
+async fn mock_commit_statuses(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    #[derive(serde::Serialize)]
+    struct StatusPayload {
+        context: String,
+        state: String,
+    }
+
+    #[derive(serde::Serialize)]
+    struct CombinedStatusResponse {
+        total_count: usize,
+        statuses: Vec<StatusPayload>,
+    }
+
+    let repo_name = repo.lock().name.clone();
+    dynamic_mock_req(
+        move |_req: &Request, [sha]: [&str; 1]| {
+            let mut repo = repo.lock();
+            let Some(branch) = repo.get_branch_by_sha(sha) else {
+                return ResponseTemplate::new(404);
+            };
+            let statuses: Vec<StatusPayload> = branch
+                .statuses
+                .iter()
+                .map(|(context, status)| StatusPayload {
+                    context: context.clone(),
+                    state: match status {
+                        CheckSuiteStatus::Pending => "pending",
+                        CheckSuiteStatus::Success => "success",
+                        CheckSuiteStatus::Failure => "failure",
+                    }
+                    .to_string(),
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(CombinedStatusResponse {
+                total_count: statuses.len(),
+                statuses,
+            })
+        },
+        "GET",
+        format!("^/repos/{repo_name}/commits/(.*)/status$"),
+    )
+    .mount(mock_server)
+    .await;
+}
+
+/// Users with review permissions are returned as maintainers, users with try permissions as
+/// collaborators with write access, and other users as collaborators with read access.
+async fn mock_collaborators(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
+    #[derive(Serialize)]
+    struct CollaboratorPermissions {
+        admin: bool,
+        maintain: bool,
+        push: bool,
+        triage: bool,
+        pull: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Collaborator {
+        #[serde(flatten)]
+        user: GitHubUser,
+        permissions: CollaboratorPermissions,
+    }
+
+    let repo_name = repo.lock().name.clone();
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{repo_name}/collaborators")))
+        .respond_with(move |_: &Request| {
+            let repo = repo.lock();
+            let mut users = repo.permissions.users.iter().collect::<Vec<_>>();
+            users.sort_by_key(|(user, _)| user.github_id);
+            let collaborators = users
+                .into_iter()
+                .map(|(user, permissions)| {
+                    let review = permissions.contains(&PermissionType::Review);
+                    let try_build = permissions.contains(&PermissionType::Try);
+                    Collaborator {
+                        user: user.clone().into(),
+                        permissions: CollaboratorPermissions {
+                            admin: false,
+                            maintain: review,
+                            push: review || try_build,
+                            triage: review || try_build,
+                            pull: true,
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            ResponseTemplate::new(200).set_body_json(collaborators)
+        })
+        .mount(mock_server)
+        .await;
+}
+
 async fn mock_config(repo: Arc<Mutex<Repo>>, mock_server: &MockServer) {
-    // Extracted into a block to avoid holding the lock over an await point
-    let mock = {
-        let repo = repo.lock();
-        Mock::given(method("GET"))
-            .and(path(format!(
-                "/repos/{}/contents/rust-bors.toml",
-                repo.name
-            )))
-            .respond_with(
-                ResponseTemplate::new(200)
+    let repo_name = repo.lock().name.clone();
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{repo_name}/contents/rust-bors.toml")))
+        .respond_with(move |req: &Request| {
+            let repo = repo.lock();
+            let reference = req
+                .url
+                .query_pairs()
+                .find(|(key, _)| key == "ref")
+                .map(|(_, value)| value.to_string());
+            // Return the configuration of a PR if its head is requested
+            let config_change = reference.and_then(|reference| {
+                repo.pull_requests
+                    .values()
+                    .find(|pr| pr.head_sha == reference)
+                    .and_then(|pr| pr.config_change.clone())
+            });
+            match config_change {
+                Some(ConfigChange::Modified(config)) => ResponseTemplate::new(200)
+                    .set_body_json(GitHubContent::new("rust-bors.toml", &config)),
+                Some(ConfigChange::Removed) => ResponseTemplate::new(404),
+                None => ResponseTemplate::new(200)
                     .set_body_json(GitHubContent::new("rust-bors.toml", &repo.config)),
-            )
-            .mount(mock_server)
-    };
-    mock.await;
+            }
+        })
+        .mount(mock_server)
+        .await;
 }
 
 /// Represents all repositories for an installation
@@ -591,6 +1103,7 @@ pub struct GitHubRepository {
     name: String,
     url: Url,
     owner: GitHubUser,
+    default_branch: String,
 }
 
 impl From<GithubRepoName> for GitHubRepository {
@@ -600,6 +1113,7 @@ impl From<GithubRepoName> for GitHubRepository {
             name: value.name().to_string(),
             owner: GitHubUser::new(value.owner(), 1001),
             url: format!("https://github.com/{}", value).parse().unwrap(),
+            default_branch: default_branch_name().to_string(),
         }
     }
 }
@@ -622,12 +1136,15 @@ struct GitHubContent {
 
 impl GitHubContent {
     fn new(path: &str, content: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let sha = format!("{:016x}", hasher.finish());
         let content = base64::prelude::BASE64_STANDARD.encode(content);
         let size = content.len() as i64;
         GitHubContent {
             name: path.to_string(),
             path: path.to_string(),
-            sha: "test".to_string(),
+            sha,
             encoding: Some("base64".to_string()),
             content: Some(content),
             size,
@@ -651,6 +1168,18 @@ struct GitHubBranch {
     name: String,
     commit: GitHubCommitObject,
     protected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protection: Option<GitHubBranchProtection>,
+}
+
+#[derive(Serialize)]
+struct GitHubBranchProtection {
+    required_status_checks: GitHubRequiredStatusChecks,
+}
+
+#[derive(Serialize)]
+struct GitHubRequiredStatusChecks {
+    contexts: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -4,8 +4,9 @@ use http::Request;
 use sha2::Sha256;
 
 pub const TEST_WEBHOOK_SECRET: &str = "ABCDEF";
+pub const TEST_ADMIN_TOKEN: &str = "admin-token";
 
-pub fn create_webhook_request(event: &str, body: &str) -> Request<Body> {
+pub fn create_webhook_request(event: &str, body: &str, delivery_id: &str) -> Request<Body> {
     let mut mac = Hmac::<Sha256>::new_from_slice(TEST_WEBHOOK_SECRET.as_bytes()).unwrap();
     mac.update(body.as_bytes());
     let signature = hex::encode(mac.finalize().into_bytes());
@@ -14,6 +15,7 @@ pub fn create_webhook_request(event: &str, body: &str) -> Request<Body> {
 
     Request::post("/github")
         .header("x-github-event", event)
+        .header("x-github-delivery", delivery_id)
         .header("x-hub-signature-256", signature)
         .header("Content-Type", "application/json")
         .body(Body::from(body.to_string()))
@@ -0,0 +1,150 @@
+//! Prometheus metrics collected by bors.
+use std::sync::LazyLock;
+
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// Registry that contains all metrics of bors.
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+/// Number of received webhooks, labelled by the GitHub event type.
+pub static WEBHOOKS_RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "bors_webhooks_received_total",
+                "Number of received webhooks",
+            ),
+            &["event_type"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Number of received commands, labelled by the command name.
+/// Commands that are refused because of insufficient permissions are also counted.
+pub static COMMANDS_RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "bors_commands_received_total",
+                "Number of received commands",
+            ),
+            &["command"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Number of commands refused because of insufficient permissions.
+pub static PERMISSION_DENIALS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "bors_permission_denials_total",
+                "Number of commands refused because of insufficient permissions",
+            ),
+            &["permission"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Number of try builds, labelled by their outcome (started, retried, succeeded, failed,
+/// cancelled or timed_out).
+pub static TRY_BUILDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "bors_try_builds_total",
+                "Number of try builds by their outcome",
+            ),
+            &["outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Duration of GitHub API requests in seconds.
+pub static GITHUB_API_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "bors_github_api_request_duration_seconds",
+                "Duration of GitHub API requests",
+            ),
+            &["request"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Duration of database queries in seconds.
+pub static DB_QUERY_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "bors_db_query_duration_seconds",
+                "Duration of database queries",
+            ),
+            &["query"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Number of events of a repository that have been dispatched to its worker, but not yet handled.
+pub static REPOSITORY_QUEUE_DEPTH: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "bors_repository_queue_depth",
+                "Number of events waiting to be handled by the worker of a repository",
+            ),
+            &["repository"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Whether the configuration of a repository could not be loaded (1) or not (0).
+/// While it is set, the repository keeps using its last valid configuration, or the default
+/// configuration if none was loaded.
+pub static REPOSITORY_CONFIG_INVALID: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register(
+        IntGaugeVec::new(
+            Opts::new(
+                "bors_repository_config_invalid",
+                "Whether the configuration of a repository failed to load",
+            ),
+            &["repository"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Renders all metrics in the Prometheus text format.
+pub fn encode_metrics() -> anyhow::Result<String> {
+    // Make sure that all metrics are registered, even if they were not used yet.
+    LazyLock::force(&WEBHOOKS_RECEIVED);
+    LazyLock::force(&COMMANDS_RECEIVED);
+    LazyLock::force(&PERMISSION_DENIALS);
+    LazyLock::force(&TRY_BUILDS);
+    LazyLock::force(&GITHUB_API_LATENCY);
+    LazyLock::force(&DB_QUERY_LATENCY);
+    LazyLock::force(&REPOSITORY_QUEUE_DEPTH);
+    LazyLock::force(&REPOSITORY_CONFIG_INVALID);
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+fn register<T: Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Cannot register metric");
+    collector
+}
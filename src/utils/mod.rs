@@ -1,3 +1,5 @@
 pub mod logging;
+pub mod metrics;
+pub mod template;
 pub mod text;
 pub mod timing;
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// A text with `{variable}` placeholders, which are replaced with values when the template is
+/// rendered. `{{` and `}}` can be used to write literal braces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    Variable(String),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("Unclosed `{{` at position {0}")]
+    UnclosedBrace(usize),
+    #[error("Unmatched `}}` at position {0}")]
+    UnmatchedBrace(usize),
+    #[error("Unknown variable `{name}`, available variables: {available}")]
+    UnknownVariable { name: String, available: String },
+}
+
+impl Template {
+    /// Parses a template that can only reference the given `variables`.
+    pub fn parse(text: &str, variables: &[&str]) -> Result<Self, TemplateError> {
+        let mut parts = vec![];
+        let mut current = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => current.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => current.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => return Err(TemplateError::UnclosedBrace(position)),
+                        }
+                    }
+                    let name = name.trim();
+                    if !variables.contains(&name) {
+                        return Err(TemplateError::UnknownVariable {
+                            name: name.to_string(),
+                            available: variables
+                                .iter()
+                                .map(|variable| format!("`{variable}`"))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        });
+                    }
+                    if !current.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut current)));
+                    }
+                    parts.push(TemplatePart::Variable(name.to_string()));
+                }
+                '}' => return Err(TemplateError::UnmatchedBrace(position)),
+                c => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            parts.push(TemplatePart::Text(current));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Renders the template. Variables without a value are replaced with an empty string.
+    pub fn render(&self, values: &HashMap<&str, String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => text.as_str(),
+                TemplatePart::Variable(name) => values
+                    .get(name.as_str())
+                    .map(|value| value.as_str())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Formats the template in the syntax from which it was parsed.
+impl Display for Template {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => {
+                    f.write_str(&text.replace('{', "{{").replace('}', "}}"))?
+                }
+                TemplatePart::Variable(name) => write!(f, "{{{name}}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Template, TemplateError};
+
+    #[test]
+    fn render_variables() {
+        let template =
+            Template::parse("PR #{number} by { author }", &["number", "author"]).unwrap();
+        let values = HashMap::from([("number", "1".to_string()), ("author", "foo".to_string())]);
+        assert_eq!(template.render(&values), "PR #1 by foo");
+    }
+
+    #[test]
+    fn render_escaped_braces() {
+        let template = Template::parse("{{{number}}}", &["number"]).unwrap();
+        let values = HashMap::from([("number", "1".to_string())]);
+        assert_eq!(template.render(&values), "{1}");
+    }
+
+    #[test]
+    fn render_missing_value() {
+        let template = Template::parse("a{number}b", &["number"]).unwrap();
+        assert_eq!(template.render(&HashMap::new()), "ab");
+    }
+
+    #[test]
+    fn display_template() {
+        let template = Template::parse("{{PR}} #{ number }", &["number"]).unwrap();
+        assert_eq!(template.to_string(), "{{PR}} #{number}");
+    }
+
+    #[test]
+    fn parse_unknown_variable() {
+        assert_eq!(
+            Template::parse("{foo}", &["number", "author"]),
+            Err(TemplateError::UnknownVariable {
+                name: "foo".to_string(),
+                available: "`number`, `author`".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_brace() {
+        assert_eq!(
+            Template::parse("ab {number", &["number"]),
+            Err(TemplateError::UnclosedBrace(3))
+        );
+    }
+
+    #[test]
+    fn parse_unmatched_brace() {
+        assert_eq!(
+            Template::parse("ab}", &[]),
+            Err(TemplateError::UnmatchedBrace(2))
+        );
+    }
+}
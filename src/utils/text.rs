@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use regex::{Captures, Regex};
 
 /// Replaces github @mentions with backticks to prevent accidental pings
@@ -13,6 +15,19 @@ pub fn suppress_github_mentions(text: &str) -> String {
         .to_string()
 }
 
+/// Formats a duration in a human readable form, e.g. `1h 5m 3s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -26,4 +41,11 @@ mod tests {
             "mail@example.com"
         )
     }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(5)), "5s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 5s");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1h 2m 5s");
+    }
 }
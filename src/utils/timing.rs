@@ -1,6 +1,8 @@
 use std::time::Instant;
 use tracing::trace;
 
+use crate::utils::metrics::{DB_QUERY_LATENCY, GITHUB_API_LATENCY};
+
 // Measures the duration of an async operation and logs it using tracing.
 pub async fn measure_operation<T, F, Fut>(operation_name: &str, f: F) -> T
 where
@@ -23,22 +25,32 @@ where
     result
 }
 
-// Measures the duration of a database query and logs it using tracing.
+// Measures the duration of a database query, logs it using tracing and records it in metrics.
 pub async fn measure_db_query<T, F, Fut>(query_name: &str, f: F) -> T
 where
     F: FnOnce() -> Fut,
     Fut: std::future::Future<Output = T>,
 {
-    measure_operation(&format!("db_query:{query_name}"), f).await
+    let start = Instant::now();
+    let result = measure_operation(&format!("db_query:{query_name}"), f).await;
+    DB_QUERY_LATENCY
+        .with_label_values(&[query_name])
+        .observe(start.elapsed().as_secs_f64());
+    result
 }
 
-// Measures the duration of a network request and logs it using tracing.
+// Measures the duration of a network request, logs it using tracing and records it in metrics.
 pub async fn measure_network_request<T, F, Fut>(request_name: &str, f: F) -> T
 where
     F: FnOnce() -> Fut,
     Fut: std::future::Future<Output = T>,
 {
-    measure_operation(&format!("network_request:{request_name}"), f).await
+    let start = Instant::now();
+    let result = measure_operation(&format!("network_request:{request_name}"), f).await;
+    GITHUB_API_LATENCY
+        .with_label_values(&[request_name])
+        .observe(start.elapsed().as_secs_f64());
+    result
 }
 
 #[cfg(test)]